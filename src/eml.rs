@@ -64,7 +64,7 @@ pub fn qp_decode(encoded: &str) -> String {
         } else {
             let mut buffer = [0u8; 4];
             let encoded_char = char.encode_utf8(&mut buffer);
-            decoded_raw.extend_from_slice(&encoded_char.as_bytes());
+            decoded_raw.extend_from_slice(encoded_char.as_bytes());
         }
     }
 
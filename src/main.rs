@@ -10,6 +10,11 @@ use iced::{
 };
 
 fn main() -> Result<(), iced::Error> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("path-length") {
+        std::process::exit(path_length_checker::cli::run(args));
+    }
+
     iced::application(UI::boot, UI::update, UI::view).run()
 }
 
@@ -80,6 +85,7 @@ impl UI {
 
                 self.path_length_checker.cancel_scan();
                 match site {
+                    #[cfg(windows)]
                     Site::QuickInstall => Task::none(),
                     Site::Eml => {
                         self.site = Site::Eml;
@@ -114,7 +120,7 @@ impl UI {
                                 decoded
                                     .split_ascii_whitespace()
                                     .filter_map(|number| {
-                                        let number = u32::from_str_radix(number, 10).ok()?;
+                                        let number = number.parse::<u32>().ok()?;
                                         char::from_u32(number)
                                     })
                                     .collect::<String>()
@@ -122,7 +128,11 @@ impl UI {
                         );
                         Task::none()
                     }
-                    Site::Home | Site::PathLengthChecker | Site::Nut => Task::none(),
+                    Site::PathLengthChecker => {
+                        path_length_checker::PathLengthChecker::find_interrupted()
+                            .map(Message::PathLengthChecker)
+                    }
+                    Site::Home | Site::Nut => Task::none(),
                 }
             }
             Message::Encoder(message) => self.encoder.update(message).map(Message::Encoder),
@@ -140,20 +150,21 @@ impl UI {
     }
 
     pub fn view(&self) -> iced::Element<'_, Message> {
+        let site_buttons = column![];
+        #[cfg(windows)]
+        let site_buttons = site_buttons.push(button("Quick Install").on_press(Site::QuickInstall));
+
         column![
             row![
                 iced::Element::from(
-                    column![
-                        #[cfg(windows)]
-                        button("Quick Install").on_press(Site::QuickInstall),
-                        button("EML Encode").on_press(Site::Eml),
-                        button("Base64 Encode").on_press(Site::Base64),
-                        button("Unicode Encode").on_press(Site::Unicode),
-                        button("Path Length Checker").on_press(Site::PathLengthChecker),
-                        button("NUT Client").on_press(Site::Nut)
-                    ]
-                    .spacing(10)
-                    .height(Length::Fill)
+                    site_buttons
+                        .push(button("EML Encode").on_press(Site::Eml))
+                        .push(button("Base64 Encode").on_press(Site::Base64))
+                        .push(button("Unicode Encode").on_press(Site::Unicode))
+                        .push(button("Path Length Checker").on_press(Site::PathLengthChecker))
+                        .push(button("NUT Client").on_press(Site::Nut))
+                        .spacing(10)
+                        .height(Length::Fill)
                 )
                 .map(Message::SwitchSite),
                 rule::vertical(2),
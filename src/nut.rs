@@ -1,28 +1,68 @@
 use iced::{
     Task,
-    widget::{button, column, rule},
+    widget::{button, column, row, rule},
 };
 
+mod alarms;
+mod alerts;
+mod apcupsd;
+mod client;
+mod compare;
+mod config;
+mod config_tool;
 mod connect;
 mod monitor;
+#[allow(clippy::module_inception)]
 mod nut;
+mod smtp;
+mod snmp;
 
 #[derive(Clone)]
 pub enum Message {
     Connect(connect::Message),
     Monitor(monitor::Message),
+    Alerts(alerts::Message),
+    Alarms(alarms::Message),
+    ConfigTool(config_tool::Message),
+    ToggleConfigTool,
+    TogglePanel(Panel),
     Disconnect,
 }
 
+/// Optional settings shown above the monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    None,
+    AddServer,
+    Alerts,
+    Alarms,
+}
+
 pub struct Nut {
     connect: connect::Connect,
+    alerts: alerts::Alerts,
+    alarms: alarms::Editor,
+    config_tool: config_tool::ConfigTool,
+    show_config_tool: bool,
+    panel: Panel,
     monitor: Option<monitor::Monitor>,
 }
 
+impl Default for Nut {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Nut {
     pub fn new() -> Self {
         Self {
             connect: connect::Connect::new(),
+            alerts: alerts::Alerts::new(),
+            alarms: alarms::Editor::new(),
+            config_tool: config_tool::ConfigTool::new(),
+            show_config_tool: false,
+            panel: Panel::None,
             monitor: None,
         }
     }
@@ -31,10 +71,17 @@ impl Nut {
         match message {
             Message::Connect(message) => match self.connect.update(message) {
                 connect::Action::Run(task) => task.map(Message::Connect),
-                connect::Action::Client(nut_client) => {
-                    let (monitor, task) = monitor::Monitor::new(nut_client);
-                    self.monitor = Some(monitor);
-                    task.map(Message::Monitor)
+                connect::Action::Client(client) => {
+                    self.panel = Panel::None;
+                    match &mut self.monitor {
+                        Some(monitor) => monitor.add_client(*client).map(Message::Monitor),
+                        None => {
+                            let (monitor, task) =
+                                monitor::Monitor::new(*client, self.alarms.rules().to_vec());
+                            self.monitor = Some(monitor);
+                            task.map(Message::Monitor)
+                        }
+                    }
                 }
                 connect::Action::None => Task::none(),
             },
@@ -42,12 +89,40 @@ impl Nut {
                 if let Some(monitor) = &mut self.monitor {
                     match monitor.update(message) {
                         monitor::Action::None => Task::none(),
+                        monitor::Action::Events(events) => {
+                            self.alerts.notify(events).map(Message::Alerts)
+                        }
                     }
                 } else {
                     Task::none()
                 }
             }
+            Message::Alerts(message) => self.alerts.update(message).map(Message::Alerts),
+            Message::Alarms(message) => {
+                if let alarms::Action::RulesChanged(rules) = self.alarms.update(message)
+                    && let Some(monitor) = &mut self.monitor
+                {
+                    monitor.set_alarm_rules(rules);
+                }
+                Task::none()
+            }
+            Message::ConfigTool(message) => {
+                self.config_tool.update(message).map(Message::ConfigTool)
+            }
+            Message::ToggleConfigTool => {
+                self.show_config_tool = !self.show_config_tool;
+                Task::none()
+            }
+            Message::TogglePanel(panel) => {
+                self.panel = if self.panel == panel {
+                    Panel::None
+                } else {
+                    panel
+                };
+                Task::none()
+            }
             Message::Disconnect => {
+                self.panel = Panel::None;
                 self.monitor = None;
                 Task::none()
             }
@@ -55,10 +130,37 @@ impl Nut {
     }
 
     pub fn view(&self) -> iced::Element<'_, Message> {
+        if self.show_config_tool {
+            return column![
+                button("Back").on_press(Message::ToggleConfigTool),
+                self.config_tool.view().map(Message::ConfigTool),
+            ]
+            .spacing(5)
+            .into();
+        }
+
         match &self.monitor {
-            None => self.connect.view().map(Message::Connect),
+            None => column![
+                button("Config Tool").on_press(Message::ToggleConfigTool),
+                self.connect.view().map(Message::Connect),
+            ]
+            .spacing(5)
+            .into(),
             Some(monitor) => column![
-                button("Disconnect").on_press(Message::Disconnect),
+                row![
+                    button("Disconnect").on_press(Message::Disconnect),
+                    self.panel_button("Add Server", Panel::AddServer),
+                    self.panel_button("Email Alerts", Panel::Alerts),
+                    self.panel_button("Alarm Rules", Panel::Alarms),
+                    button("Config Tool").on_press(Message::ToggleConfigTool),
+                ]
+                .spacing(10),
+                match self.panel {
+                    Panel::None => None,
+                    Panel::AddServer => Some(self.connect.view().map(Message::Connect)),
+                    Panel::Alerts => Some(self.alerts.view().map(Message::Alerts)),
+                    Panel::Alarms => Some(self.alarms.view().map(Message::Alarms)),
+                },
                 rule::horizontal(2),
                 monitor.view().map(Message::Monitor),
             ]
@@ -66,4 +168,9 @@ impl Nut {
             .into(),
         }
     }
+
+    fn panel_button(&self, label: &'static str, panel: Panel) -> button::Button<'_, Message> {
+        button(if self.panel == panel { "Close" } else { label })
+            .on_press(Message::TogglePanel(panel))
+    }
 }
@@ -0,0 +1,525 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
+use iced::{
+    Color, Element, Length,
+    widget::{button, column, container, pick_list, row, text, text_input},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Above,
+    Below,
+    Outside,
+}
+
+impl Condition {
+    pub const ALL: [Condition; 3] = [Condition::Above, Condition::Below, Condition::Outside];
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Above => write!(f, "above"),
+            Condition::Below => write!(f, "below"),
+            Condition::Outside => write!(f, "outside"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    Above(f64),
+    Below(f64),
+    Outside(f64, f64),
+}
+
+/// A user defined alarm on a numeric variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmRule {
+    /// NUT variable name, e.g. `input.voltage`
+    pub variable: String,
+    pub threshold: Threshold,
+    /// How far the value has to go back before the alarm ends.
+    pub hysteresis: f64,
+    /// How long the threshold has to be violated before the alarm starts.
+    pub min_duration: Duration,
+}
+
+impl AlarmRule {
+    fn triggers(&self, value: f64) -> bool {
+        match self.threshold {
+            Threshold::Above(limit) => value > limit,
+            Threshold::Below(limit) => value < limit,
+            Threshold::Outside(low, high) => value < low || value > high,
+        }
+    }
+
+    fn clears(&self, value: f64) -> bool {
+        let hysteresis = self.hysteresis;
+        match self.threshold {
+            Threshold::Above(limit) => value <= limit - hysteresis,
+            Threshold::Below(limit) => value >= limit + hysteresis,
+            Threshold::Outside(low, high) => {
+                value >= low + hysteresis && value <= high - hysteresis
+            }
+        }
+    }
+}
+
+impl Display for AlarmRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.threshold {
+            Threshold::Above(limit) => write!(f, "{} > {}", self.variable, limit)?,
+            Threshold::Below(limit) => write!(f, "{} < {}", self.variable, limit)?,
+            Threshold::Outside(low, high) => {
+                write!(f, "{} outside {}–{}", self.variable, low, high)?
+            }
+        }
+        if self.hysteresis > 0.0 {
+            write!(f, ", hysteresis {}", self.hysteresis)?;
+        }
+        if !self.min_duration.is_zero() {
+            write!(f, ", for {}s", self.min_duration.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+/// One occurrence of an alarm.
+#[derive(Debug, Clone)]
+pub struct AlarmRecord {
+    pub rule: String,
+    /// `ups@host:port`
+    pub ups: String,
+    /// The value that started the alarm
+    pub value: f64,
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Default)]
+struct AlarmState {
+    violated_since: Option<Instant>,
+    /// Index into the history while the alarm is active
+    active: Option<usize>,
+}
+
+/// A rule with its state per UPS.
+#[derive(Debug)]
+struct RuleState {
+    rule: AlarmRule,
+    states: HashMap<String, AlarmState>,
+}
+
+/// Checks the alarm rules against every poll and keeps the alarm history.
+#[derive(Debug, Default)]
+pub struct Evaluator {
+    rules: Vec<RuleState>,
+    history: Vec<AlarmRecord>,
+}
+
+impl Evaluator {
+    /// Replace the rules.
+    ///
+    /// Rules that are still there keep their state and active alarms,
+    /// only the alarms of removed rules end now.
+    pub fn set_rules(&mut self, rules: Vec<AlarmRule>) {
+        let mut old = std::mem::take(&mut self.rules);
+        self.rules = rules
+            .into_iter()
+            .map(|rule| {
+                let states = old
+                    .iter()
+                    .position(|existing| existing.rule == rule)
+                    .map(|index| old.remove(index).states)
+                    .unwrap_or_default();
+                RuleState { rule, states }
+            })
+            .collect();
+
+        let now = Local::now();
+        for state in old.iter().flat_map(|removed| removed.states.values()) {
+            if let Some(index) = state.active {
+                self.history[index].end = Some(now);
+            }
+        }
+    }
+
+    pub fn evaluate(&mut self, ups: &str, vars: &[(String, String)]) {
+        let now = Instant::now();
+
+        for RuleState { rule, states } in &mut self.rules {
+            let Some(value) = vars
+                .iter()
+                .find(|(key, _)| *key == rule.variable)
+                .and_then(|(_, value)| value.trim().parse::<f64>().ok())
+            else {
+                continue;
+            };
+
+            let state = states.entry(ups.to_string()).or_default();
+
+            if let Some(active) = state.active {
+                if rule.clears(value) {
+                    self.history[active].end = Some(Local::now());
+                    state.active = None;
+                    state.violated_since = None;
+                }
+            } else if rule.triggers(value) {
+                let since = *state.violated_since.get_or_insert(now);
+                if now.duration_since(since) >= rule.min_duration {
+                    state.active = Some(self.history.len());
+                    self.history.push(AlarmRecord {
+                        rule: rule.to_string(),
+                        ups: ups.to_string(),
+                        value,
+                        start: Local::now(),
+                        end: None,
+                    });
+                }
+            } else {
+                state.violated_since = None;
+            }
+        }
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &AlarmRecord> {
+        self.history.iter().filter(|record| record.end.is_none())
+    }
+
+    pub fn history(&self) -> &[AlarmRecord] {
+        &self.history
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Variable(String),
+    Condition(Condition),
+    Low(String),
+    High(String),
+    Hysteresis(String),
+    MinDuration(String),
+    Add,
+    Remove(usize),
+}
+
+pub enum Action {
+    None,
+    RulesChanged(Vec<AlarmRule>),
+}
+
+/// Form to create and remove alarm rules.
+pub struct Editor {
+    rules: Vec<AlarmRule>,
+    variable: String,
+    condition: Condition,
+    low: String,
+    high: String,
+    hysteresis: String,
+    min_duration: String,
+    error: Option<String>,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            variable: String::new(),
+            condition: Condition::Above,
+            low: String::new(),
+            high: String::new(),
+            hysteresis: "0".to_string(),
+            min_duration: "0".to_string(),
+            error: None,
+        }
+    }
+
+    pub fn rules(&self) -> &[AlarmRule] {
+        &self.rules
+    }
+
+    #[must_use]
+    pub fn update(&mut self, message: Message) -> Action {
+        match message {
+            Message::Variable(variable) => self.variable = variable,
+            Message::Condition(condition) => self.condition = condition,
+            Message::Low(low) => self.low = low,
+            Message::High(high) => self.high = high,
+            Message::Hysteresis(hysteresis) => self.hysteresis = hysteresis,
+            Message::MinDuration(min_duration) => self.min_duration = min_duration,
+            Message::Add => match self.parse_rule() {
+                Ok(rule) => {
+                    self.error = None;
+                    self.rules.push(rule);
+                    return Action::RulesChanged(self.rules.clone());
+                }
+                Err(error) => self.error = Some(error),
+            },
+            Message::Remove(index) => {
+                if index < self.rules.len() {
+                    self.rules.remove(index);
+                    return Action::RulesChanged(self.rules.clone());
+                }
+            }
+        }
+        Action::None
+    }
+
+    fn parse_rule(&self) -> Result<AlarmRule, String> {
+        let number = |label: &str, value: &str| {
+            value
+                .trim()
+                .replace(',', ".")
+                .parse::<f64>()
+                .map_err(|_| format!("{} is not a number", label))
+        };
+
+        let variable = self.variable.trim().to_string();
+        if variable.is_empty() {
+            return Err("Enter a variable name".to_string());
+        }
+
+        let threshold = match self.condition {
+            Condition::Above => Threshold::Above(number("Value", &self.high)?),
+            Condition::Below => Threshold::Below(number("Value", &self.low)?),
+            Condition::Outside => {
+                let low = number("Minimum", &self.low)?;
+                let high = number("Maximum", &self.high)?;
+                if low >= high {
+                    return Err("Minimum has to be below maximum".to_string());
+                }
+                Threshold::Outside(low, high)
+            }
+        };
+
+        let hysteresis = number("Hysteresis", &self.hysteresis)?;
+        if hysteresis < 0.0 {
+            return Err("Hysteresis can't be negative".to_string());
+        }
+        // The value has to get back into the range by the hysteresis on both sides to clear
+        if let Threshold::Outside(low, high) = threshold
+            && hysteresis * 2.0 >= high - low
+        {
+            return Err(
+                "Hysteresis has to be less than half the range, or the alarm never ends"
+                    .to_string(),
+            );
+        }
+
+        let min_duration = self
+            .min_duration
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| "Minimum duration has to be whole seconds".to_string())?;
+
+        Ok(AlarmRule {
+            variable,
+            threshold,
+            hysteresis,
+            min_duration: Duration::from_secs(min_duration),
+        })
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let value_inputs: Element<'_, Message> = match self.condition {
+            Condition::Above => text_input("Value", &self.high)
+                .on_input(Message::High)
+                .width(100)
+                .into(),
+            Condition::Below => text_input("Value", &self.low)
+                .on_input(Message::Low)
+                .width(100)
+                .into(),
+            Condition::Outside => row![
+                text_input("Min", &self.low)
+                    .on_input(Message::Low)
+                    .width(100),
+                text("–"),
+                text_input("Max", &self.high)
+                    .on_input(Message::High)
+                    .width(100),
+            ]
+            .spacing(5)
+            .into(),
+        };
+
+        container(
+            column![
+                column(self.rules.iter().enumerate().map(|(index, rule)| {
+                    row![
+                        button("Remove").on_press(Message::Remove(index)),
+                        text(rule.to_string()),
+                    ]
+                    .spacing(10)
+                    .into()
+                }))
+                .spacing(5),
+                row![
+                    text_input("Variable, e.g. input.voltage", &self.variable)
+                        .on_input(Message::Variable)
+                        .width(250),
+                    pick_list(Condition::ALL, Some(self.condition), Message::Condition),
+                    value_inputs,
+                    text("Hysteresis"),
+                    text_input("0", &self.hysteresis)
+                        .on_input(Message::Hysteresis)
+                        .width(60),
+                    text("Min. duration (s)"),
+                    text_input("0", &self.min_duration)
+                        .on_input(Message::MinDuration)
+                        .width(60),
+                    button("Add").on_press(Message::Add),
+                ]
+                .spacing(10)
+                .align_y(iced::alignment::Vertical::Center),
+                self.error
+                    .as_ref()
+                    .map(|error| text(error).color(Color::from_rgb8(255, 0, 0))),
+            ]
+            .spacing(10)
+            .width(Length::Fill),
+        )
+        .padding(20)
+        .into()
+    }
+}
+
+/// Banner listing the currently active alarms.
+pub fn banner<'a, Message: 'a>(evaluator: &'a Evaluator) -> Option<Element<'a, Message>> {
+    let active: Vec<&AlarmRecord> = evaluator.active().collect();
+    if active.is_empty() {
+        return None;
+    }
+
+    Some(
+        container(
+            column(active.into_iter().map(|record| {
+                text!(
+                    "⚠ {}: {} (value {}, since {})",
+                    record.ups,
+                    record.rule,
+                    record.value,
+                    record.start.format("%Y-%m-%d %H:%M:%S")
+                )
+                .shaping(text::Shaping::Advanced)
+                .color(Color::WHITE)
+                .into()
+            }))
+            .spacing(5),
+        )
+        .padding(10)
+        .width(Length::Fill)
+        .style(|_| container::Style::default().background(Color::from_rgb8(180, 30, 30)))
+        .into(),
+    )
+}
+
+/// Table of all alarms with their start and end times, newest first.
+pub fn history<'a, Message: 'a>(evaluator: &'a Evaluator) -> Element<'a, Message> {
+    let format = |time: &DateTime<Local>| time.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    column![
+        text("Alarm History").size(18),
+        column(evaluator.history().iter().rev().map(|record| {
+            row![
+                text(format(&record.start)).width(160),
+                text(
+                    record
+                        .end
+                        .as_ref()
+                        .map(format)
+                        .unwrap_or_else(|| "active".to_string())
+                )
+                .width(160),
+                text(&record.ups).width(250),
+                text(&record.rule),
+            ]
+            .spacing(10)
+            .into()
+        }))
+        .spacing(5),
+    ]
+    .spacing(10)
+    .into()
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(variable: &str, threshold: Threshold) -> AlarmRule {
+        AlarmRule {
+            variable: variable.to_string(),
+            threshold,
+            hysteresis: 1.0,
+            min_duration: Duration::ZERO,
+        }
+    }
+
+    fn vars(voltage: &str, temperature: &str) -> Vec<(String, String)> {
+        vec![
+            ("input.voltage".to_string(), voltage.to_string()),
+            ("ups.temperature".to_string(), temperature.to_string()),
+        ]
+    }
+
+    #[test]
+    fn changing_rules_keeps_unchanged_alarms() {
+        let voltage = rule("input.voltage", Threshold::Outside(207.0, 253.0));
+        let temperature = rule("ups.temperature", Threshold::Above(40.0));
+        let mut evaluator = Evaluator::default();
+        evaluator.set_rules(vec![voltage.clone(), temperature.clone()]);
+        evaluator.evaluate("ups@host", &vars("200", "45"));
+        assert_eq!(evaluator.active().count(), 2);
+
+        // Adding an unrelated rule ends nothing and raises nothing twice
+        let charge = rule("battery.charge", Threshold::Below(50.0));
+        evaluator.set_rules(vec![charge, voltage.clone(), temperature]);
+        evaluator.evaluate("ups@host", &vars("200", "45"));
+        assert_eq!(evaluator.history().len(), 2);
+        assert_eq!(evaluator.active().count(), 2);
+
+        // Only the alarm of the removed rule ends
+        evaluator.set_rules(vec![voltage]);
+        let active: Vec<_> = evaluator.active().map(|record| &record.rule).collect();
+        assert_eq!(
+            active,
+            [&"input.voltage outside 207–253, hysteresis 1".to_string()]
+        );
+        assert_eq!(evaluator.history().len(), 2);
+    }
+
+    #[test]
+    fn rejects_hysteresis_that_never_clears() {
+        let mut editor = Editor::new();
+        for message in [
+            Message::Variable("input.voltage".to_string()),
+            Message::Condition(Condition::Outside),
+            Message::Low("207".to_string()),
+            Message::High("253".to_string()),
+            Message::Hysteresis("23".to_string()),
+        ] {
+            let _ = editor.update(message);
+        }
+        assert!(matches!(editor.update(Message::Add), Action::None));
+        assert!(editor.error.is_some());
+
+        let _ = editor.update(Message::Hysteresis("22.5".to_string()));
+        assert!(matches!(
+            editor.update(Message::Add),
+            Action::RulesChanged(_)
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use iced::{
+    Color, Element, Length, Task,
+    widget::{button, checkbox, column, container, grid, pick_list, row, text, text_input},
+};
+use tokio::io;
+
+use crate::nut::{
+    monitor::{Event, EventKind},
+    nut::UpsInfo,
+    smtp::{self, Encryption, SmtpSettings},
+};
+
+#[derive(Clone)]
+pub enum Message {
+    Enabled(bool),
+    Host(String),
+    Port(String),
+    Encryption(Encryption),
+    Username(String),
+    Password(String),
+    From(String),
+    To(String),
+    EventToggled(EventKind, bool),
+    TogglePasswordVisibility,
+    SendTest,
+    Sent(Arc<io::Result<String>>),
+}
+
+/// Sends an email through SMTP when the monitor reports a power event.
+pub struct Alerts {
+    enabled: bool,
+    host: String,
+    port: u16,
+    encryption: Encryption,
+    username: String,
+    password: String,
+    from: String,
+    /// Comma or semicolon separated list of recipients
+    to: String,
+    events: Vec<EventKind>,
+    show_password: bool,
+    sending: usize,
+    last_result: Option<Result<String, String>>,
+}
+
+impl Alerts {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: Encryption::StartTls.default_port(),
+            encryption: Encryption::StartTls,
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+            to: String::new(),
+            events: vec![
+                EventKind::OnBattery,
+                EventKind::LowBattery,
+                EventKind::CommsLost,
+            ],
+            show_password: false,
+            sending: 0,
+            last_result: None,
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Enabled(enabled) => self.enabled = enabled,
+            Message::Host(host) => self.host = host,
+            Message::Port(port) => self.port = port.parse().unwrap_or(0),
+            Message::Encryption(encryption) => {
+                if self.port == self.encryption.default_port() {
+                    self.port = encryption.default_port();
+                }
+                self.encryption = encryption;
+            }
+            Message::Username(username) => self.username = username,
+            Message::Password(password) => self.password = password,
+            Message::From(from) => self.from = from,
+            Message::To(to) => self.to = to,
+            Message::EventToggled(kind, enabled) => {
+                self.events.retain(|event| *event != kind);
+                if enabled {
+                    self.events.push(kind);
+                }
+            }
+            Message::TogglePasswordVisibility => self.show_password = !self.show_password,
+            Message::SendTest => {
+                return self.send(
+                    "[UPS] Test alert".to_string(),
+                    "This is a test of the UPS email alerts.".to_string(),
+                );
+            }
+            Message::Sent(result) => {
+                self.sending = self.sending.saturating_sub(1);
+                self.last_result = Some(match result.as_ref() {
+                    Ok(message) => Ok(message.clone()),
+                    Err(err) => Err(format!("Failed to send alert: {}", err)),
+                });
+            }
+        }
+        Task::none()
+    }
+
+    /// Send one mail per event the user wants to be alerted about.
+    pub fn notify(&mut self, events: Vec<Event>) -> Task<Message> {
+        if !self.enabled {
+            return Task::none();
+        }
+
+        let mut tasks = Vec::new();
+        for event in events {
+            if self.events.contains(&event.kind) {
+                let subject = format!("[UPS] {}: {}", event.kind, event.ups);
+                tasks.push(self.send(subject, alert_body(&event)));
+            }
+        }
+        Task::batch(tasks)
+    }
+
+    fn send(&mut self, subject: String, body: String) -> Task<Message> {
+        let settings = self.settings();
+        self.sending += 1;
+
+        Task::future(async move {
+            let result = smtp::send_mail(&settings, &subject, &body)
+                .await
+                .map(|()| format!("Sent \"{}\"", subject));
+            Message::Sent(Arc::new(result))
+        })
+    }
+
+    fn settings(&self) -> SmtpSettings {
+        SmtpSettings {
+            host: self.host.clone(),
+            port: self.port,
+            encryption: self.encryption,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            from: self.from.clone(),
+            to: self
+                .to
+                .split([',', ';'])
+                .map(str::trim)
+                .filter(|recipient| !recipient.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        container(
+            column![
+                checkbox(self.enabled)
+                    .label("Send email alerts")
+                    .on_toggle(Message::Enabled),
+                grid![
+                    text!("SMTP Server"),
+                    text_input("Host", &self.host).on_input(Message::Host),
+                    text!("Port"),
+                    text_input("Port", &self.port.to_string()).on_input(Message::Port),
+                    text!("Encryption"),
+                    pick_list(Encryption::ALL, Some(self.encryption), Message::Encryption),
+                    text!("Username"),
+                    text_input("Empty for no authentication", &self.username)
+                        .on_input(Message::Username),
+                    text!("Password"),
+                    row![
+                        text_input("Password", &self.password)
+                            .on_input(Message::Password)
+                            .secure(!self.show_password),
+                        if self.show_password {
+                            button("Hide").on_press(Message::TogglePasswordVisibility)
+                        } else {
+                            button("Show").on_press(Message::TogglePasswordVisibility)
+                        },
+                    ]
+                    .spacing(10),
+                    text!("From"),
+                    text_input("ups@example.com", &self.from).on_input(Message::From),
+                    text!("To"),
+                    text_input("admin@example.com, ...", &self.to).on_input(Message::To),
+                ]
+                .columns(2)
+                .spacing(10)
+                .height(Length::Shrink),
+                row(EventKind::ALL.iter().map(|kind| {
+                    checkbox(self.events.contains(kind))
+                        .label(kind.to_string())
+                        .on_toggle(|enabled| Message::EventToggled(*kind, enabled))
+                        .into()
+                }))
+                .spacing(20),
+                row![
+                    button("Send Test Email").on_press(Message::SendTest),
+                    if self.sending > 0 {
+                        text("Sending...").color(Color::from_rgb8(255, 255, 0))
+                    } else {
+                        match &self.last_result {
+                            Some(Ok(message)) => text(message).color(Color::from_rgb8(0, 160, 0)),
+                            Some(Err(error)) => text(error).color(Color::from_rgb8(255, 0, 0)),
+                            None => text(""),
+                        }
+                    },
+                ]
+                .spacing(10),
+            ]
+            .spacing(10),
+        )
+        .padding(20)
+        .into()
+    }
+}
+
+/// The mail body with a snapshot of the most important values.
+fn alert_body(event: &Event) -> String {
+    let info: &UpsInfo = &event.info;
+    let mut body = format!("{}: {}\n\n", event.kind, event.ups);
+
+    let fields = [
+        ("Status", &info.status, ""),
+        ("Manufacturer", &info.manufacturer, ""),
+        ("Model", &info.model, ""),
+        ("Serial", &info.serial, ""),
+        ("Battery charge", &info.battery_charge_percent, " %"),
+        ("Battery runtime", &info.battery_runtime_seconds, " s"),
+        ("Battery voltage", &info.battery_voltage, " V"),
+        ("Load", &info.load_percent, " %"),
+        ("Real power", &info.realpower_watts, " W"),
+        ("Input voltage", &info.input_voltage, " V"),
+        ("Input frequency", &info.input_frequency_hz, " Hz"),
+        ("Output voltage", &info.output_voltage, " V"),
+    ];
+
+    for (label, value, unit) in fields {
+        if let Some(value) = value {
+            body.push_str(&format!("{}: {}{}\n", label, value, unit));
+        }
+    }
+
+    body
+}
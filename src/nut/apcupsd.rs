@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::nut::nut::UpsInfo;
+
+/// Client for the network information server (NIS) of apcupsd.
+///
+/// The NIS closes the connection after each request in most setups,
+/// so a fresh connection is opened for every command.
+#[derive(Debug)]
+pub struct ApcupsdClient {
+    addr: String,
+    /// The STATUS field when the event log was last read
+    events_status: Option<String>,
+    last_event: Option<String>,
+}
+
+impl ApcupsdClient {
+    /// Connect to an apcupsd NIS and check that it answers `status` requests.
+    pub async fn connect(host: impl Into<String>, port: u16) -> io::Result<Self> {
+        let client = ApcupsdClient {
+            addr: format!("{}:{}", host.into(), port),
+            events_status: None,
+            last_event: None,
+        };
+
+        client.status_raw().await?;
+
+        Ok(client)
+    }
+
+    /// The UPS name as configured in apcupsd (`UPSNAME`).
+    ///
+    /// A NIS only ever serves a single UPS, so this is the only entry a
+    /// `list_ups` call can return.
+    pub async fn list_ups(&mut self) -> io::Result<Vec<(String, String)>> {
+        let status = self.status_raw().await?;
+        let name = ups_name(&status);
+        let description = status.get("MODEL").cloned().unwrap_or_default();
+        Ok(vec![(name, description)])
+    }
+
+    /// Fetch the STATUS fields and map them onto NUT variable names.
+    ///
+    /// The most recent entry of the event log is added as `apcupsd.lastevent`.
+    /// apcupsd logs an event whenever its status changes, so the log is only
+    /// read again after the STATUS field changed.
+    pub async fn list_vars_raw(&mut self) -> io::Result<HashMap<String, String>> {
+        let status = self.status_raw().await?;
+        let current = status.get("STATUS").cloned().unwrap_or_default();
+        if self.events_status.as_ref() != Some(&current) {
+            self.last_event = self.events().await?.pop();
+            self.events_status = Some(current);
+        }
+
+        let mut vars = map_status(status);
+        if let Some(event) = &self.last_event {
+            vars.insert("apcupsd.lastevent".to_string(), event.clone());
+        }
+        Ok(vars)
+    }
+
+    /// High-level helper: fetch the STATUS fields and map them into `UpsInfo`.
+    pub async fn get_ups_info(&mut self) -> io::Result<UpsInfo> {
+        let status = self.status_raw().await?;
+        let name = ups_name(&status);
+        Ok(UpsInfo::from_var_map(&name, map_status(status)))
+    }
+
+    /// Low-level: the `status` command, returned as a map from apcupsd field name -> value.
+    pub async fn status_raw(&self) -> io::Result<HashMap<String, String>> {
+        let lines = self.request("status").await?;
+
+        let mut result = HashMap::new();
+        for line in lines {
+            // Expected: "KEY      : value"
+            if let Some((key, value)) = line.split_once(':') {
+                result.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+
+        if result.is_empty() {
+            return Err(io::Error::other("apcupsd returned an empty status"));
+        }
+
+        Ok(result)
+    }
+
+    /// Low-level: the `events` command, one line per logged event.
+    pub async fn events(&self) -> io::Result<Vec<String>> {
+        self.request("events").await
+    }
+
+    // ---------- internal helpers ----------
+
+    async fn request(&self, cmd: &str) -> io::Result<Vec<String>> {
+        let mut stream = TcpStream::connect(&self.addr).await?;
+        write_frame(&mut stream, cmd.as_bytes()).await?;
+
+        let mut lines = Vec::new();
+        while let Some(frame) = read_frame(&mut stream).await? {
+            let line = String::from_utf8_lossy(&frame);
+            let line = line.trim_end_matches(['\n', '\r']);
+            lines.push(line.to_string());
+        }
+
+        Ok(lines)
+    }
+}
+
+/// Every NIS frame is prefixed with its length as a big-endian u16.
+async fn write_frame(stream: &mut TcpStream, data: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "NIS frame too long"))?;
+    stream.write_all(&len.to_be_bytes()).await?;
+    stream.write_all(data).await?;
+    stream.flush().await
+}
+
+/// Read a single frame. A zero-length frame marks the end of a response.
+async fn read_frame(stream: &mut TcpStream) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len).await?;
+    let len = u16::from_be_bytes(len) as usize;
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let mut frame = vec![0u8; len];
+    stream.read_exact(&mut frame).await?;
+    Ok(Some(frame))
+}
+
+fn ups_name(status: &HashMap<String, String>) -> String {
+    status
+        .get("UPSNAME")
+        .filter(|name| !name.is_empty())
+        .or_else(|| status.get("HOSTNAME"))
+        .cloned()
+        .unwrap_or_else(|| "apcupsd".to_string())
+}
+
+/// Map apcupsd STATUS fields onto the equivalent NUT variables.
+///
+/// Fields without a NUT counterpart are kept as `apcupsd.<field>`.
+fn map_status(mut status: HashMap<String, String>) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+
+    // Helper to move a field over with its unit suffix removed
+    let mut map = |field: &str, var: &str| {
+        if let Some(value) = status.remove(field) {
+            let value = strip_unit(&value).map(str::to_string).unwrap_or(value);
+            vars.insert(var.to_string(), value);
+        }
+    };
+
+    map("MODEL", "ups.model");
+    map("SERIALNO", "ups.serial");
+    map("FIRMWARE", "ups.firmware");
+    map("MANDATE", "ups.mfr.date");
+    map("LOADPCT", "ups.load");
+    map("NOMPOWER", "ups.realpower.nominal");
+    map("ITEMP", "ups.temperature");
+
+    map("BCHARGE", "battery.charge");
+    map("MBATTCHG", "battery.charge.low");
+    map("TIMELEFT", "battery.runtime");
+    map("MINTIMEL", "battery.runtime.low");
+    map("BATTV", "battery.voltage");
+    map("NOMBATTV", "battery.voltage.nominal");
+    map("BATTDATE", "battery.date");
+
+    map("LINEV", "input.voltage");
+    map("NOMINV", "input.voltage.nominal");
+    map("LINEFREQ", "input.frequency");
+    map("HITRANS", "input.transfer.high");
+    map("LOTRANS", "input.transfer.low");
+    map("LASTXFER", "input.transfer.reason");
+    map("OUTPUTV", "output.voltage");
+    map("NOMOUTV", "output.voltage.nominal");
+
+    // apcupsd reports runtimes in minutes, NUT in seconds
+    for var in ["battery.runtime", "battery.runtime.low"] {
+        if let Some(minutes) = vars.get_mut(var)
+            && let Ok(parsed) = minutes.parse::<f64>()
+        {
+            *minutes = format!("{:.0}", parsed * 60.0);
+        }
+    }
+
+    if let Some(flags) = status.remove("STATUS") {
+        vars.insert("ups.status".to_string(), map_status_flags(&flags));
+    }
+
+    for (key, value) in status {
+        vars.insert(format!("apcupsd.{}", key.to_lowercase()), value);
+    }
+
+    vars
+}
+
+/// apcupsd reports values like "230.0 Volts" or "45.3 Minutes".
+fn strip_unit(value: &str) -> Option<&str> {
+    let number = value.split_whitespace().next()?;
+    number.parse::<f64>().ok().map(|_| number)
+}
+
+/// Translate apcupsd status words ("ONLINE LOWBATT") into NUT flags ("OL LB").
+fn map_status_flags(flags: &str) -> String {
+    flags
+        .split_whitespace()
+        .map(|flag| match flag {
+            "ONLINE" => "OL",
+            "ONBATT" => "OB",
+            "LOWBATT" => "LB",
+            "REPLACEBATT" => "RB",
+            "OVERLOAD" => "OVER",
+            "CAL" => "CAL",
+            "TRIM" => "TRIM",
+            "BOOST" => "BOOST",
+            "SHUTTING" => "FSD",
+            other => other,
+        })
+        .filter(|flag| *flag != "DOWN")
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const ONLINE: &[&str] = &[
+        "APC      : 001,036,0879",
+        "UPSNAME  : rack-ups",
+        "MODEL    : Back-UPS RS 900G",
+        "STATUS   : ONLINE ",
+        "LINEV    : 230.0 Volts",
+        "LOADPCT  : 12.0 Percent",
+        "BCHARGE  : 100.0 Percent",
+        "TIMELEFT : 45.3 Minutes",
+        "MINTIMEL : 3 Minutes",
+        "SERIALNO : 3B1234X56789",
+        "SENSE    : Medium",
+    ];
+
+    /// Answer every connection with the canned `status`, replaced by the next set of lines
+    /// after each request, and record the commands.
+    async fn nis(statuses: Vec<Vec<&'static str>>) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let received = commands.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter().peekable();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let Some(command) = read_frame(&mut stream).await.unwrap() else {
+                    continue;
+                };
+                let command = String::from_utf8(command).unwrap();
+                received.lock().unwrap().push(command.clone());
+                let lines = match command.as_str() {
+                    "status" if statuses.len() > 1 => statuses.next().unwrap(),
+                    "status" => statuses.peek().unwrap().clone(),
+                    "events" => vec!["2026-10-18 10:00:00 +0200  Power failure."],
+                    _ => Vec::new(),
+                };
+                for line in lines {
+                    write_frame(&mut stream, format!("{}\n", line).as_bytes())
+                        .await
+                        .unwrap();
+                }
+                // End of the response
+                write_frame(&mut stream, b"").await.unwrap();
+            }
+        });
+        (port, commands)
+    }
+
+    #[tokio::test]
+    async fn reads_frames_until_the_end_marker() {
+        let (port, _) = nis(vec![ONLINE.to_vec()]).await;
+        let mut client = ApcupsdClient::connect("127.0.0.1", port).await.unwrap();
+
+        let status = client.status_raw().await.unwrap();
+        assert_eq!(status.len(), ONLINE.len());
+        assert_eq!(status["STATUS"], "ONLINE");
+        assert_eq!(
+            client.list_ups().await.unwrap(),
+            vec![("rack-ups".to_string(), "Back-UPS RS 900G".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_events_only_when_the_status_changes() {
+        let mut on_battery = ONLINE.to_vec();
+        on_battery[3] = "STATUS   : ONBATT LOWBATT";
+        let (port, commands) = nis(vec![
+            ONLINE.to_vec(),
+            ONLINE.to_vec(),
+            ONLINE.to_vec(),
+            on_battery,
+        ])
+        .await;
+        let mut client = ApcupsdClient::connect("127.0.0.1", port).await.unwrap();
+
+        client.list_vars_raw().await.unwrap();
+        client.list_vars_raw().await.unwrap();
+        let vars = client.list_vars_raw().await.unwrap();
+
+        assert_eq!(vars["ups.status"], "OB LB");
+        assert_eq!(
+            vars["apcupsd.lastevent"],
+            "2026-10-18 10:00:00 +0200  Power failure."
+        );
+        assert_eq!(
+            *commands.lock().unwrap(),
+            ["status", "status", "events", "status", "status", "events"]
+        );
+    }
+
+    #[test]
+    fn maps_status_fields() {
+        let status = ONLINE
+            .iter()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        let vars = map_status(status);
+
+        assert_eq!(vars["ups.status"], "OL");
+        assert_eq!(vars["input.voltage"], "230.0");
+        assert_eq!(vars["ups.load"], "12.0");
+        assert_eq!(vars["battery.runtime"], "2718");
+        assert_eq!(vars["battery.runtime.low"], "180");
+        assert_eq!(vars["ups.serial"], "3B1234X56789");
+        assert_eq!(vars["apcupsd.sense"], "Medium");
+        assert!(!vars.contains_key("apcupsd.status"));
+    }
+
+    #[test]
+    fn keeps_unknown_status_words() {
+        assert_eq!(map_status_flags("COMMLOST"), "COMMLOST");
+        assert_eq!(map_status_flags("ONBATT COMMLOST"), "OB COMMLOST");
+        assert_eq!(map_status_flags("SHUTTING DOWN"), "FSD");
+        assert_eq!(map_status_flags("ONLINE REPLACEBATT TRIM"), "OL RB TRIM");
+    }
+}
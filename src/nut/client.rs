@@ -0,0 +1,131 @@
+use std::{collections::HashMap, fmt::Display, io};
+
+use crate::nut::{
+    apcupsd::ApcupsdClient,
+    nut::{NutClient, UpsInfo},
+    snmp::{self, SnmpClient},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Nut,
+    Apcupsd,
+    Snmp,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 3] = [Protocol::Nut, Protocol::Apcupsd, Protocol::Snmp];
+
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Protocol::Nut => 3493,
+            Protocol::Apcupsd => 3551,
+            Protocol::Snmp => 161,
+        }
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Nut => write!(f, "NUT (upsd)"),
+            Protocol::Apcupsd => write!(f, "apcupsd (NIS)"),
+            Protocol::Snmp => write!(f, "SNMP (UPS-MIB)"),
+        }
+    }
+}
+
+/// Protocol specific login information.
+#[derive(Debug, Clone)]
+pub enum Login {
+    /// If `username` is empty, no USERNAME/PASSWORD commands are sent.
+    Nut {
+        username: String,
+        password: String,
+    },
+    Apcupsd,
+    Snmp(snmp::Security),
+}
+
+/// A connection to any of the supported UPS monitoring servers.
+///
+/// Variables are always reported with NUT names, so the monitor
+/// doesn't need to care where they came from.
+#[derive(Debug)]
+pub struct UpsClient {
+    host: String,
+    port: u16,
+    login: Login,
+    backend: Backend,
+}
+
+#[derive(Debug)]
+enum Backend {
+    Nut(NutClient),
+    Apcupsd(ApcupsdClient),
+    Snmp(Box<SnmpClient>),
+}
+
+impl UpsClient {
+    pub async fn connect(host: String, port: u16, login: Login) -> io::Result<Self> {
+        let backend = Self::connect_backend(&host, port, &login).await?;
+
+        Ok(Self {
+            host,
+            port,
+            login,
+            backend,
+        })
+    }
+
+    /// Establish a new connection with the same settings, e.g. after the server went away.
+    pub async fn reconnect(&mut self) -> io::Result<()> {
+        self.backend = Self::connect_backend(&self.host, self.port, &self.login).await?;
+        Ok(())
+    }
+
+    async fn connect_backend(host: &str, port: u16, login: &Login) -> io::Result<Backend> {
+        Ok(match login.clone() {
+            Login::Nut { username, password } => {
+                Backend::Nut(NutClient::connect(host, port, username, password).await?)
+            }
+            Login::Apcupsd => Backend::Apcupsd(ApcupsdClient::connect(host, port).await?),
+            Login::Snmp(security) => {
+                Backend::Snmp(Box::new(SnmpClient::connect(host, port, security).await?))
+            }
+        })
+    }
+
+    /// `host:port` of the server, used to tell UPSes with the same name apart.
+    pub fn server(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// List all UPSes known to the server.
+    ///
+    /// Returns Vec<(ups_name, description)>
+    pub async fn list_ups(&mut self) -> io::Result<Vec<(String, String)>> {
+        match &mut self.backend {
+            Backend::Nut(client) => client.list_ups().await,
+            Backend::Apcupsd(client) => client.list_ups().await,
+            Backend::Snmp(client) => client.list_ups().await,
+        }
+    }
+
+    /// All variables of a UPS, by NUT variable name.
+    pub async fn list_vars_raw(&mut self, ups_name: &str) -> io::Result<HashMap<String, String>> {
+        match &mut self.backend {
+            Backend::Nut(client) => client.list_vars_raw(ups_name).await,
+            Backend::Apcupsd(client) => client.list_vars_raw().await,
+            Backend::Snmp(client) => client.list_vars_raw().await,
+        }
+    }
+
+    pub async fn get_ups_info(&mut self, ups_name: &str) -> io::Result<UpsInfo> {
+        match &mut self.backend {
+            Backend::Nut(client) => client.get_ups_info(ups_name).await,
+            Backend::Apcupsd(client) => client.get_ups_info().await,
+            Backend::Snmp(client) => client.get_ups_info().await,
+        }
+    }
+}
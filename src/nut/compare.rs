@@ -0,0 +1,78 @@
+use std::collections::BTreeSet;
+
+use iced::{
+    Color, Element,
+    widget::{column, row, text},
+};
+
+const DIFFERENT: Color = Color::from_rgb8(255, 165, 0);
+const MISSING: Color = Color::from_rgb8(255, 0, 0);
+
+/// All variables of two UPSes side by side, aligned by name.
+///
+/// Values that differ are highlighted, variables only one UPS reports are marked as missing.
+pub fn view<'a, Message: 'a>(
+    (left_name, left): (&'a str, &'a [(String, String)]),
+    (right_name, right): (&'a str, &'a [(String, String)]),
+) -> Element<'a, Message> {
+    let find = |vars: &'a [(String, String)], key: &str| {
+        vars.iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let keys: BTreeSet<&str> = left
+        .iter()
+        .chain(right)
+        .map(|(key, _)| key.as_str())
+        .collect();
+
+    let mut differences = 0;
+    let mut missing = 0;
+    let rows = keys.into_iter().map(|key| {
+        let left_value = find(left, key);
+        let right_value = find(right, key);
+
+        let cell = |value: Option<&'a str>, color: Option<Color>| {
+            match value {
+                Some(value) => text(value).color_maybe(color),
+                None => text("missing").color(MISSING),
+            }
+            .width(300)
+        };
+
+        let color = match (left_value, right_value) {
+            (Some(left_value), Some(right_value)) if left_value != right_value => {
+                differences += 1;
+                Some(DIFFERENT)
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                missing += 1;
+                Some(MISSING)
+            }
+            _ => None,
+        };
+
+        row![
+            text(key).width(300).color_maybe(color),
+            cell(left_value, color),
+            cell(right_value, color),
+        ]
+        .spacing(10)
+        .into()
+    });
+    let rows: Vec<Element<'a, Message>> = rows.collect();
+
+    column![
+        text!("{} different, {} only on one UPS", differences, missing),
+        row![
+            text("Variable").width(300),
+            text(left_name).width(300),
+            text(right_name).width(300),
+        ]
+        .spacing(10),
+        column(rows).spacing(10),
+    ]
+    .spacing(10)
+    .into()
+}
@@ -0,0 +1,1148 @@
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+use tokio::{fs, io};
+
+use crate::nut::nut::NutClient;
+
+/// Drivers shipped with NUT 2.8.
+const KNOWN_DRIVERS: &[&str] = &[
+    "adelsystem_cbi",
+    "al175",
+    "apcsmart",
+    "apcsmart-old",
+    "apcupsd-ups",
+    "bcmxcp",
+    "bcmxcp_usb",
+    "belkin",
+    "belkinunv",
+    "bestfcom",
+    "bestfortress",
+    "bestuferrups",
+    "bestups",
+    "blazer_ser",
+    "blazer_usb",
+    "clone",
+    "clone-outlet",
+    "dummy-ups",
+    "etapro",
+    "everups",
+    "gamatronic",
+    "generic_gpio_libgpiod",
+    "generic_modbus",
+    "genericups",
+    "huawei-ups2000",
+    "isbmex",
+    "ivtscd",
+    "liebert",
+    "liebert-esp2",
+    "liebert-gxe",
+    "macosx-ups",
+    "masterguard",
+    "metasys",
+    "mge-shut",
+    "mge-utalk",
+    "microdowell",
+    "microsol-apc",
+    "netxml-ups",
+    "nhs_ser",
+    "nut-ipmipsu",
+    "nutdrv_atcl_usb",
+    "nutdrv_qx",
+    "nutdrv_siemens_sitop",
+    "oneac",
+    "optiups",
+    "phoenixcontact_modbus",
+    "pijuice",
+    "powercom",
+    "powerman-pdu",
+    "powerpanel",
+    "rhino",
+    "richcomm_usb",
+    "riello_ser",
+    "riello_usb",
+    "safenet",
+    "snmp-ups",
+    "socomec_jbus",
+    "solis",
+    "tripplite",
+    "tripplite_usb",
+    "tripplitesu",
+    "upscode2",
+    "usbhid-ups",
+    "victronups",
+];
+
+const UPS_CONF_GLOBALS: &[&str] = &[
+    "chroot",
+    "driverpath",
+    "maxstartdelay",
+    "maxretry",
+    "retrydelay",
+    "pollinterval",
+    "user",
+    "group",
+    "synchronous",
+    "nowait",
+    "debug_min",
+];
+
+const UPSD_CONF_DIRECTIVES: &[&str] = &[
+    "LISTEN",
+    "MAXAGE",
+    "TRACKINGDELAY",
+    "ALLOW_NO_DEVICE",
+    "ALLOW_NOT_ALL_LISTENERS",
+    "STATEPATH",
+    "MAXCONN",
+    "CERTFILE",
+    "CERTPATH",
+    "CERTIDENT",
+    "CERTREQUEST",
+    "DISABLE_WEAK_SSL",
+    "DEBUG_MIN",
+];
+
+const UPSMON_CONF_DIRECTIVES: &[&str] = &[
+    "MONITOR",
+    "MINSUPPLIES",
+    "SHUTDOWNCMD",
+    "NOTIFYCMD",
+    "POLLFREQ",
+    "POLLFREQALERT",
+    "POLLFAIL_LOG_THROTTLE_MAX",
+    "HOSTSYNC",
+    "DEADTIME",
+    "POWERDOWNFLAG",
+    "NOTIFYMSG",
+    "NOTIFYFLAG",
+    "RBWARNTIME",
+    "NOCOMMWARNTIME",
+    "FINALDELAY",
+    "RUN_AS_USER",
+    "CERTPATH",
+    "CERTIDENT",
+    "CERTHOST",
+    "CERTVERIFY",
+    "FORCESSL",
+    "SHUTDOWNEXIT",
+    "OFFDURATION",
+    "OBLBDURATION",
+    "OVERDURATION",
+    "DEBUG_MIN",
+];
+
+const USER_KEYS: &[&str] = &["password", "upsmon", "actions", "instcmds"];
+
+/// Keys that may appear more than once in a section.
+const REPEATABLE_KEYS: &[&str] = &["upsmon", "actions", "instcmds"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFile {
+    UpsConf,
+    UpsdConf,
+    UpsdUsers,
+    UpsmonConf,
+}
+
+impl ConfigFile {
+    pub const ALL: [ConfigFile; 4] = [
+        ConfigFile::UpsConf,
+        ConfigFile::UpsdConf,
+        ConfigFile::UpsdUsers,
+        ConfigFile::UpsmonConf,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ConfigFile::UpsConf => "ups.conf",
+            ConfigFile::UpsdConf => "upsd.conf",
+            ConfigFile::UpsdUsers => "upsd.users",
+            ConfigFile::UpsmonConf => "upsmon.conf",
+        }
+    }
+}
+
+impl Display for ConfigFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_name())
+    }
+}
+
+/// The four files that make up a NUT installation.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSet {
+    pub ups_conf: String,
+    pub upsd_conf: String,
+    pub upsd_users: String,
+    pub upsmon_conf: String,
+}
+
+impl ConfigSet {
+    pub fn get(&self, file: ConfigFile) -> &str {
+        match file {
+            ConfigFile::UpsConf => &self.ups_conf,
+            ConfigFile::UpsdConf => &self.upsd_conf,
+            ConfigFile::UpsdUsers => &self.upsd_users,
+            ConfigFile::UpsmonConf => &self.upsmon_conf,
+        }
+    }
+
+    pub fn get_mut(&mut self, file: ConfigFile) -> &mut String {
+        match file {
+            ConfigFile::UpsConf => &mut self.ups_conf,
+            ConfigFile::UpsdConf => &mut self.upsd_conf,
+            ConfigFile::UpsdUsers => &mut self.upsd_users,
+            ConfigFile::UpsmonConf => &mut self.upsmon_conf,
+        }
+    }
+
+    /// Read the files from a folder like `/etc/nut`. Missing files are left empty.
+    pub async fn load(folder: &Path) -> io::Result<Self> {
+        let mut set = ConfigSet::default();
+        for file in ConfigFile::ALL {
+            match fs::read_to_string(folder.join(file.file_name())).await {
+                Ok(content) => *set.get_mut(file) = content,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(set)
+    }
+
+    /// The files that aren't empty, the others are left alone when saving.
+    pub fn written_files(&self) -> Vec<ConfigFile> {
+        ConfigFile::ALL
+            .into_iter()
+            .filter(|file| !self.get(*file).trim().is_empty())
+            .collect()
+    }
+
+    pub async fn save(&self, folder: &Path) -> io::Result<()> {
+        for file in self.written_files() {
+            fs::write(folder.join(file.file_name()), self.get(file)).await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub file: ConfigFile,
+    /// 1-based, `None` if the finding is about the whole file
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}: {}", self.file, line, severity, self.message),
+            None => write!(f, "{}: {}: {}", self.file, severity, self.message),
+        }
+    }
+}
+
+struct Findings {
+    file: ConfigFile,
+    list: Vec<Finding>,
+}
+
+impl Findings {
+    fn push(&mut self, line: Option<usize>, severity: Severity, message: impl Into<String>) {
+        self.list.push(Finding {
+            file: self.file,
+            line,
+            severity,
+            message: message.into(),
+        });
+    }
+
+    fn error(&mut self, line: usize, message: impl Into<String>) {
+        self.push(Some(line), Severity::Error, message);
+    }
+
+    fn warning(&mut self, line: usize, message: impl Into<String>) {
+        self.push(Some(line), Severity::Warning, message);
+    }
+}
+
+/// A non-empty line split into words.
+struct Line {
+    number: usize,
+    words: Vec<String>,
+}
+
+/// Split a file into words the way NUT's parseconf does.
+///
+/// `#` starts a comment, double quotes group words, a backslash escapes the next
+/// character and `=` is a word of its own. A backslash at the end of a line continues
+/// it on the next one, the joined line keeps the number of its first line.
+fn tokenize(content: &str, findings: &mut Findings) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut number = 0;
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    let mut continued = false;
+
+    for (index, raw) in content.lines().enumerate() {
+        if !continued {
+            number = index + 1;
+        }
+        continued = false;
+        let mut chars = raw.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(escaped) => {
+                        word.push(escaped);
+                        in_word = true;
+                    }
+                    None => continued = true,
+                },
+                '"' => {
+                    quoted = !quoted;
+                    in_word = true;
+                }
+                _ if quoted => word.push(c),
+                '#' => break,
+                '=' => {
+                    if in_word {
+                        words.push(std::mem::take(&mut word));
+                        in_word = false;
+                    }
+                    words.push("=".to_string());
+                }
+                _ if c.is_whitespace() => {
+                    if in_word {
+                        words.push(std::mem::take(&mut word));
+                        in_word = false;
+                    }
+                }
+                _ => {
+                    word.push(c);
+                    in_word = true;
+                }
+            }
+        }
+
+        if continued {
+            continue;
+        }
+        if quoted {
+            findings.error(number, "Unterminated quote");
+            quoted = false;
+        }
+        if in_word {
+            words.push(std::mem::take(&mut word));
+            in_word = false;
+        }
+        if !words.is_empty() {
+            lines.push(Line {
+                number,
+                words: std::mem::take(&mut words),
+            });
+        }
+    }
+
+    if continued {
+        findings.error(number, "The last line ends with a backslash");
+        if in_word {
+            words.push(word);
+        }
+        if !words.is_empty() {
+            lines.push(Line { number, words });
+        }
+    }
+
+    lines
+}
+
+/// A `[name]` block with `key = value` entries, as used by ups.conf and upsd.users.
+struct Section {
+    name: String,
+    line: usize,
+    values: HashMap<String, (usize, String)>,
+}
+
+/// Parse a file consisting of sections. Entries before the first section are returned separately.
+fn parse_sections(
+    content: &str,
+    findings: &mut Findings,
+) -> (Vec<(usize, String, String)>, Vec<Section>) {
+    let mut globals = Vec::new();
+    let mut sections: Vec<Section> = Vec::new();
+
+    for line in tokenize(content, findings) {
+        let first = &line.words[0];
+
+        if first.starts_with('[') {
+            let header = line.words.join(" ");
+            let Some(name) = header
+                .strip_prefix('[')
+                .and_then(|header| header.strip_suffix(']'))
+            else {
+                findings.error(line.number, "Section header is missing the closing ]");
+                continue;
+            };
+            let name = name.trim().to_string();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                findings.error(line.number, format!("Invalid section name \"{}\"", name));
+            }
+            if let Some(existing) = sections.iter().find(|section| section.name == name) {
+                findings.error(
+                    line.number,
+                    format!("[{}] is already defined on line {}", name, existing.line),
+                );
+            }
+            sections.push(Section {
+                name,
+                line: line.number,
+                values: HashMap::new(),
+            });
+            continue;
+        }
+
+        // `key = value`, `key value` (upsd.users) or a flag without a value
+        let (key, value) = match line.words.as_slice() {
+            [key] => (key.clone(), String::new()),
+            [key, eq, value @ ..] if eq == "=" => {
+                if value.is_empty() {
+                    findings.error(line.number, format!("{} has no value", key));
+                }
+                (key.clone(), value.join(" "))
+            }
+            [key, value @ ..] => (key.clone(), value.join(" ")),
+            [] => continue,
+        };
+
+        match sections.last_mut() {
+            Some(section) => {
+                if section.values.contains_key(&key) && !REPEATABLE_KEYS.contains(&key.as_str()) {
+                    findings.warning(line.number, format!("{} is set twice", key));
+                }
+                section.values.insert(key, (line.number, value));
+            }
+            None => globals.push((line.number, key, value)),
+        }
+    }
+
+    (globals, sections)
+}
+
+struct MonitorLine {
+    line: usize,
+    ups: String,
+    host: String,
+    port: Option<u16>,
+    power_value: u32,
+    user: String,
+    password: String,
+    role: String,
+}
+
+fn parse_monitor(line: &Line, findings: &mut Findings) -> Option<MonitorLine> {
+    let [_, system, power_value, user, password, role] = line.words.as_slice() else {
+        findings.error(
+            line.number,
+            "MONITOR needs: <ups>@<host>[:<port>] <powervalue> <username> <password> <primary|secondary>",
+        );
+        return None;
+    };
+
+    let Some((ups, server)) = system.split_once('@') else {
+        findings.error(
+            line.number,
+            format!("\"{}\" is not in the form ups@host", system),
+        );
+        return None;
+    };
+
+    let (host, port) = match server.rsplit_once(':') {
+        // IPv6 addresses without port are written in brackets or have more than one colon
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            match port.parse::<u16>() {
+                Ok(port) if port != 0 => (host.to_string(), Some(port)),
+                _ => {
+                    findings.error(line.number, format!("Invalid port \"{}\"", port));
+                    (host.to_string(), None)
+                }
+            }
+        }
+        _ => (server.to_string(), None),
+    };
+
+    let Ok(power_value) = power_value.parse::<u32>() else {
+        findings.error(
+            line.number,
+            format!("Power value \"{}\" is not a number", power_value),
+        );
+        return None;
+    };
+
+    let role = role.to_lowercase();
+    if !["primary", "secondary", "master", "slave"].contains(&role.as_str()) {
+        findings.error(
+            line.number,
+            format!("\"{}\" must be primary or secondary", role),
+        );
+    }
+
+    Some(MonitorLine {
+        line: line.number,
+        ups: ups.to_string(),
+        host: host.trim_matches(['[', ']']).to_string(),
+        port,
+        power_value,
+        user: user.clone(),
+        password: password.clone(),
+        role,
+    })
+}
+
+/// "master" and "slave" are the old names of "primary" and "secondary".
+fn normalize_role(role: &str) -> &str {
+    match role {
+        "master" => "primary",
+        "slave" => "secondary",
+        role => role,
+    }
+}
+
+fn is_local(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// Addresses that make upsd listen on every interface, loopback included.
+fn is_wildcard(host: &str) -> bool {
+    matches!(host, "0.0.0.0" | "::" | "*")
+}
+
+/// Check the syntax of all four files and whether they fit together.
+pub fn validate(set: &ConfigSet) -> Vec<Finding> {
+    let mut ups_findings = Findings {
+        file: ConfigFile::UpsConf,
+        list: Vec::new(),
+    };
+    let (globals, upses) = parse_sections(&set.ups_conf, &mut ups_findings);
+    for (line, key, _) in &globals {
+        if !UPS_CONF_GLOBALS.contains(&key.as_str()) {
+            ups_findings.warning(*line, format!("Unknown global setting \"{}\"", key));
+        }
+    }
+    if upses.is_empty() {
+        ups_findings.push(None, Severity::Error, "No UPS is defined");
+    }
+    for ups in &upses {
+        match ups.values.get("driver") {
+            None => ups_findings.error(ups.line, format!("[{}] has no driver", ups.name)),
+            Some((line, driver)) if !KNOWN_DRIVERS.contains(&driver.as_str()) => {
+                ups_findings.warning(*line, format!("Unknown driver \"{}\"", driver))
+            }
+            Some(_) => {}
+        }
+        if !ups.values.contains_key("port") {
+            ups_findings.error(
+                ups.line,
+                format!("[{}] has no port, use \"auto\" for USB", ups.name),
+            );
+        }
+    }
+
+    let mut upsd_findings = Findings {
+        file: ConfigFile::UpsdConf,
+        list: Vec::new(),
+    };
+    let mut listen_ports = Vec::new();
+    for line in tokenize(&set.upsd_conf, &mut upsd_findings) {
+        let directive = line.words[0].as_str();
+        if !UPSD_CONF_DIRECTIVES.contains(&directive) {
+            upsd_findings.warning(line.number, format!("Unknown directive \"{}\"", directive));
+            continue;
+        }
+        match (directive, &line.words[1..]) {
+            ("LISTEN", [_]) => listen_ports.push(3493),
+            ("LISTEN", [_, port]) => match port.parse::<u16>() {
+                Ok(port) if port != 0 => listen_ports.push(port),
+                _ => upsd_findings.error(line.number, format!("Invalid port \"{}\"", port)),
+            },
+            ("LISTEN", _) => upsd_findings.error(line.number, "LISTEN needs: <address> [<port>]"),
+            ("MAXAGE" | "MAXCONN" | "TRACKINGDELAY" | "DEBUG_MIN", [value])
+                if value.parse::<u32>().is_err() =>
+            {
+                upsd_findings.error(line.number, format!("{} needs a number", directive))
+            }
+            (_, []) if !["ALLOW_NO_DEVICE", "ALLOW_NOT_ALL_LISTENERS"].contains(&directive) => {
+                upsd_findings.error(line.number, format!("{} needs a value", directive))
+            }
+            _ => {}
+        }
+    }
+    if listen_ports.is_empty() {
+        listen_ports.push(3493);
+    }
+
+    let mut users_findings = Findings {
+        file: ConfigFile::UpsdUsers,
+        list: Vec::new(),
+    };
+    let (globals, users) = parse_sections(&set.upsd_users, &mut users_findings);
+    for (line, key, _) in &globals {
+        users_findings.error(*line, format!("\"{}\" is outside of a [user] section", key));
+    }
+    for user in &users {
+        if !user.values.contains_key("password") {
+            users_findings.error(user.line, format!("[{}] has no password", user.name));
+        }
+        for (key, (line, value)) in &user.values {
+            if !USER_KEYS.contains(&key.as_str()) {
+                users_findings.warning(*line, format!("Unknown setting \"{}\"", key));
+            }
+            if key == "upsmon"
+                && !["primary", "secondary", "master", "slave"]
+                    .contains(&value.to_lowercase().as_str())
+            {
+                users_findings.error(*line, "upsmon must be primary or secondary");
+            }
+        }
+    }
+
+    let mut upsmon_findings = Findings {
+        file: ConfigFile::UpsmonConf,
+        list: Vec::new(),
+    };
+    let mut monitors = Vec::new();
+    let mut min_supplies = None;
+    let mut shutdown_cmd = false;
+    for line in tokenize(&set.upsmon_conf, &mut upsmon_findings) {
+        let directive = line.words[0].as_str();
+        if !UPSMON_CONF_DIRECTIVES.contains(&directive) {
+            upsmon_findings.warning(line.number, format!("Unknown directive \"{}\"", directive));
+            continue;
+        }
+        match directive {
+            "MONITOR" => monitors.extend(parse_monitor(&line, &mut upsmon_findings)),
+            "MINSUPPLIES" => match line.words.get(1).map(|value| value.parse::<u32>()) {
+                Some(Ok(value)) => min_supplies = Some((line.number, value)),
+                _ => upsmon_findings.error(line.number, "MINSUPPLIES needs a number"),
+            },
+            "SHUTDOWNCMD" => shutdown_cmd = true,
+            _ if line.words.len() < 2 => {
+                upsmon_findings.error(line.number, format!("{} needs a value", directive))
+            }
+            _ => {}
+        }
+    }
+
+    if monitors.is_empty() {
+        upsmon_findings.push(
+            None,
+            Severity::Warning,
+            "No MONITOR line, nothing is watched",
+        );
+    }
+    if !shutdown_cmd {
+        upsmon_findings.push(
+            None,
+            Severity::Warning,
+            "No SHUTDOWNCMD, the system won't shut down on low battery",
+        );
+    }
+    let power_sum: u32 = monitors.iter().map(|monitor| monitor.power_value).sum();
+    if let Some((line, min_supplies)) = min_supplies
+        && min_supplies > power_sum
+    {
+        upsmon_findings.error(
+            line,
+            format!(
+                "MINSUPPLIES {} is more than the monitored power values add up to ({})",
+                min_supplies, power_sum
+            ),
+        );
+    }
+
+    for monitor in &monitors {
+        match users.iter().find(|user| user.name == monitor.user) {
+            None => upsmon_findings.error(
+                monitor.line,
+                format!("User \"{}\" is not defined in upsd.users", monitor.user),
+            ),
+            Some(user) => {
+                if let Some((_, password)) = user.values.get("password")
+                    && *password != monitor.password
+                {
+                    upsmon_findings.error(
+                        monitor.line,
+                        format!("Password doesn't match upsd.users for \"{}\"", monitor.user),
+                    );
+                }
+                match user.values.get("upsmon") {
+                    None => upsmon_findings.error(
+                        monitor.line,
+                        format!("User \"{}\" has no upsmon line in upsd.users", monitor.user),
+                    ),
+                    Some((_, role))
+                        if normalize_role(&role.to_lowercase())
+                            != normalize_role(&monitor.role) =>
+                    {
+                        upsmon_findings.warning(
+                            monitor.line,
+                            format!(
+                                "MONITOR is {} but \"{}\" is upsmon {} in upsd.users",
+                                monitor.role, monitor.user, role
+                            ),
+                        )
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        if is_local(&monitor.host) {
+            if !upses.iter().any(|ups| ups.name == monitor.ups) {
+                upsmon_findings.error(
+                    monitor.line,
+                    format!("UPS \"{}\" is not defined in ups.conf", monitor.ups),
+                );
+            }
+            let port = monitor.port.unwrap_or(3493);
+            if !listen_ports.contains(&port) {
+                upsmon_findings.warning(
+                    monitor.line,
+                    format!("upsd doesn't listen on port {}", port),
+                );
+            }
+        }
+    }
+
+    let mut findings = Vec::new();
+    findings.extend(ups_findings.list);
+    findings.extend(upsd_findings.list);
+    findings.extend(users_findings.list);
+    findings.extend(upsmon_findings.list);
+    findings.sort_by_key(|finding| {
+        (
+            finding.severity != Severity::Error,
+            finding.file as u8,
+            finding.line,
+        )
+    });
+    findings
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Primary,
+    Secondary,
+}
+
+impl Role {
+    pub const ALL: [Role; 2] = [Role::Primary, Role::Secondary];
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Primary => write!(f, "primary"),
+            Role::Secondary => write!(f, "secondary"),
+        }
+    }
+}
+
+/// The answers of the generator form.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub ups_name: String,
+    pub driver: String,
+    pub port: String,
+    pub description: String,
+    pub listen: String,
+    pub listen_port: u16,
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+    pub shutdown_cmd: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ups_name: "ups".to_string(),
+            driver: "usbhid-ups".to_string(),
+            port: "auto".to_string(),
+            description: String::new(),
+            listen: "127.0.0.1".to_string(),
+            listen_port: 3493,
+            username: "upsmon".to_string(),
+            password: String::new(),
+            role: Role::Primary,
+            shutdown_cmd: "/sbin/shutdown -h +0".to_string(),
+        }
+    }
+}
+
+pub fn known_drivers() -> &'static [&'static str] {
+    KNOWN_DRIVERS
+}
+
+/// Produce a set of files that reference each other consistently.
+///
+/// UPS and user names end up in `[section]` headers and in `ups@host`, where they can't be
+/// quoted, so names that would break those are refused.
+pub fn generate(settings: &Settings) -> Result<ConfigSet, String> {
+    for (kind, name) in [("UPS", &settings.ups_name), ("User", &settings.username)] {
+        if name.is_empty() {
+            return Err(format!("{} name is empty", kind));
+        }
+        if name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '[' | ']' | '"' | '@'))
+        {
+            return Err(format!(
+                "{} name \"{}\" can't contain spaces, brackets, quotes or @",
+                kind, name
+            ));
+        }
+    }
+    if settings.listen_port == 0 {
+        return Err("Listen port can't be 0".to_string());
+    }
+
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+
+    let mut ups_conf = format!(
+        "[{}]\n\tdriver = {}\n\tport = {}\n",
+        settings.ups_name, settings.driver, settings.port
+    );
+    if !settings.description.is_empty() {
+        ups_conf.push_str(&format!("\tdesc = {}\n", quote(&settings.description)));
+    }
+
+    let mut upsd_conf = format!("LISTEN {} {}\n", settings.listen, settings.listen_port);
+    // A wildcard already covers loopback, listening twice on the port keeps upsd from starting
+    if !is_local(&settings.listen) && !is_wildcard(&settings.listen) {
+        upsd_conf.push_str(&format!("LISTEN 127.0.0.1 {}\n", settings.listen_port));
+    }
+
+    let upsd_users = format!(
+        "[{}]\n\tpassword = {}\n\tupsmon {}\n",
+        settings.username,
+        quote(&settings.password),
+        settings.role
+    );
+
+    let upsmon_conf = format!(
+        "MONITOR {}@localhost:{} 1 {} {} {}\nMINSUPPLIES 1\nSHUTDOWNCMD {}\nPOWERDOWNFLAG /etc/killpower\n",
+        settings.ups_name,
+        settings.listen_port,
+        settings.username,
+        quote(&settings.password),
+        settings.role,
+        quote(&settings.shutdown_cmd),
+    );
+
+    Ok(ConfigSet {
+        ups_conf,
+        upsd_conf,
+        upsd_users,
+        upsmon_conf,
+    })
+}
+
+/// Log in to every UPS from the MONITOR lines like upsmon would.
+///
+/// If `host` is not empty, it replaces the host of the MONITOR lines, so a config for
+/// another machine can be tested from here.
+pub async fn test(set: &ConfigSet, host: &str) -> io::Result<Vec<Result<String, String>>> {
+    let mut findings = Findings {
+        file: ConfigFile::UpsmonConf,
+        list: Vec::new(),
+    };
+    let monitors: Vec<MonitorLine> = tokenize(&set.upsmon_conf, &mut findings)
+        .iter()
+        .filter(|line| line.words[0] == "MONITOR")
+        .filter_map(|line| parse_monitor(line, &mut findings))
+        .collect();
+
+    if monitors.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "upsmon.conf has no valid MONITOR line",
+        ));
+    }
+
+    let mut results = Vec::new();
+    for monitor in monitors {
+        let host = if host.is_empty() {
+            monitor.host.as_str()
+        } else {
+            host
+        };
+        let server = format!("{}@{}:{}", monitor.ups, host, monitor.port.unwrap_or(3493));
+
+        let result = async {
+            let mut client = NutClient::connect(
+                host,
+                monitor.port.unwrap_or(3493),
+                &monitor.user,
+                &monitor.password,
+            )
+            .await?;
+            let list = client.list_ups().await?;
+            if !list.iter().any(|(name, _)| *name == monitor.ups) {
+                return Err(io::Error::other(format!(
+                    "the server only knows {}",
+                    list.iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
+            client.login(&monitor.ups).await?;
+            client.logout().await
+        }
+        .await;
+
+        results.push(match result {
+            Ok(()) => Ok(format!("{}: logged in as {}", server, monitor.user)),
+            Err(err) => Err(format!("{}: {}", server, err)),
+        });
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenized(content: &str) -> (Vec<(usize, Vec<String>)>, Vec<Finding>) {
+        let mut findings = Findings {
+            file: ConfigFile::UpsmonConf,
+            list: Vec::new(),
+        };
+        let lines = tokenize(content, &mut findings)
+            .into_iter()
+            .map(|line| (line.number, line.words))
+            .collect();
+        (lines, findings.list)
+    }
+
+    #[test]
+    fn backslash_continues_line() {
+        let (lines, findings) = tokenized(
+            "MONITOR myups@localhost 1 \\\n  monuser \"sec\\\nret\" primary\nMINSUPPLIES 1\n",
+        );
+        assert!(findings.is_empty());
+        assert_eq!(
+            lines,
+            [
+                (
+                    1,
+                    [
+                        "MONITOR",
+                        "myups@localhost",
+                        "1",
+                        "monuser",
+                        "secret",
+                        "primary"
+                    ]
+                    .map(String::from)
+                    .to_vec()
+                ),
+                (4, ["MINSUPPLIES", "1"].map(String::from).to_vec()),
+            ]
+        );
+
+        let (lines, findings) = tokenized("SHUTDOWNCMD halt \\");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(findings.len(), 1);
+    }
+
+    #[test]
+    fn repeated_user_keys_are_allowed() {
+        let mut findings = Findings {
+            file: ConfigFile::UpsdUsers,
+            list: Vec::new(),
+        };
+        parse_sections(
+            "[admin]\npassword = secret\nactions = SET\nactions = FSD\ninstcmds = test.battery.start\ninstcmds = beeper.disable\npassword = other\n",
+            &mut findings,
+        );
+        let messages: Vec<_> = findings
+            .list
+            .iter()
+            .map(|finding| &finding.message)
+            .collect();
+        assert_eq!(messages, ["password is set twice"]);
+    }
+
+    fn generated() -> ConfigSet {
+        generate(&Settings {
+            password: "secret".to_string(),
+            ..Settings::default()
+        })
+        .unwrap()
+    }
+
+    fn errors(set: &ConfigSet) -> Vec<String> {
+        validate(set)
+            .into_iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .map(|finding| finding.message)
+            .collect()
+    }
+
+    fn warnings(set: &ConfigSet) -> Vec<String> {
+        validate(set)
+            .into_iter()
+            .filter(|finding| finding.severity == Severity::Warning)
+            .map(|finding| finding.message)
+            .collect()
+    }
+
+    #[test]
+    fn generated_config_is_valid() {
+        assert_eq!(
+            errors(&generate(&Settings::default()).unwrap()),
+            Vec::<String>::new()
+        );
+        assert_eq!(errors(&generated()), Vec::<String>::new());
+        assert_eq!(warnings(&generated()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn loopback_listener_only_for_other_addresses() {
+        for (listen, lines) in [
+            ("127.0.0.1", 1),
+            ("localhost", 1),
+            ("0.0.0.0", 1),
+            ("::", 1),
+            ("192.168.1.2", 2),
+        ] {
+            let set = generate(&Settings {
+                listen: listen.to_string(),
+                ..Settings::default()
+            })
+            .unwrap();
+            assert_eq!(set.upsd_conf.lines().count(), lines, "{}", listen);
+        }
+    }
+
+    #[test]
+    fn generate_refuses_unquotable_names() {
+        for name in ["my ups", "ups]", "[ups", "ups\t", ""] {
+            assert!(
+                generate(&Settings {
+                    ups_name: name.to_string(),
+                    ..Settings::default()
+                })
+                .is_err(),
+                "{:?}",
+                name
+            );
+            assert!(
+                generate(&Settings {
+                    username: name.to_string(),
+                    ..Settings::default()
+                })
+                .is_err(),
+                "{:?}",
+                name
+            );
+        }
+        assert!(
+            generate(&Settings {
+                listen_port: 0,
+                ..Settings::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn undefined_monitor_user() {
+        let mut set = generated();
+        set.upsd_users = "[admin]\n\tpassword = secret\n\tupsmon primary\n".to_string();
+        assert_eq!(
+            errors(&set),
+            ["User \"upsmon\" is not defined in upsd.users"]
+        );
+    }
+
+    #[test]
+    fn missing_driver_or_port() {
+        let mut set = generated();
+        set.ups_conf = "[ups]\n\tport = auto\n".to_string();
+        assert_eq!(errors(&set), ["[ups] has no driver"]);
+        set.ups_conf = "[ups]\n\tdriver = usbhid-ups\n".to_string();
+        assert_eq!(errors(&set), ["[ups] has no port, use \"auto\" for USB"]);
+    }
+
+    #[test]
+    fn password_mismatch() {
+        let mut set = generated();
+        set.upsd_users = set.upsd_users.replace("secret", "other");
+        assert_eq!(
+            errors(&set),
+            ["Password doesn't match upsd.users for \"upsmon\""]
+        );
+    }
+
+    #[test]
+    fn role_mismatch() {
+        let mut set = generated();
+        set.upsd_users = set.upsd_users.replace("primary", "secondary");
+        assert_eq!(errors(&set), Vec::<String>::new());
+        assert_eq!(
+            warnings(&set),
+            ["MONITOR is primary but \"upsmon\" is upsmon secondary in upsd.users"]
+        );
+
+        // The old names mean the same
+        set.upsd_users = set.upsd_users.replace("secondary", "master");
+        assert_eq!(warnings(&set), Vec::<String>::new());
+    }
+
+    #[test]
+    fn min_supplies_above_power_sum() {
+        let mut set = generated();
+        set.upsmon_conf = set.upsmon_conf.replace("MINSUPPLIES 1", "MINSUPPLIES 2");
+        assert_eq!(
+            errors(&set),
+            ["MINSUPPLIES 2 is more than the monitored power values add up to (1)"]
+        );
+    }
+
+    #[test]
+    fn listen_port_matches_monitor() {
+        let mut set = generated();
+        set.upsd_conf = "LISTEN 127.0.0.1 3494\n".to_string();
+        assert_eq!(warnings(&set), ["upsd doesn't listen on port 3493"]);
+
+        // Without a port LISTEN uses the default
+        set.upsd_conf = "LISTEN 127.0.0.1\n".to_string();
+        assert_eq!(warnings(&set), Vec::<String>::new());
+        set.upsd_conf = String::new();
+        assert_eq!(warnings(&set), Vec::<String>::new());
+
+        set.upsd_conf = "LISTEN 127.0.0.1 0\n".to_string();
+        assert_eq!(errors(&set), ["Invalid port \"0\""]);
+        set.upsd_conf = "LISTEN 127.0.0.1\n".to_string();
+        set.upsmon_conf = set.upsmon_conf.replace(":3493", ":0");
+        assert_eq!(errors(&set), ["Invalid port \"0\""]);
+    }
+}
@@ -0,0 +1,348 @@
+use std::{path::PathBuf, sync::Arc};
+
+use iced::{
+    Color, Element, Font, Length, Task,
+    widget::{
+        button, column, combo_box, container, grid, pick_list, row, scrollable, text, text_editor,
+        text_input,
+    },
+};
+use rfd::{AsyncFileDialog, AsyncMessageDialog, MessageButtons, MessageDialogResult};
+use tokio::{fs, io};
+
+use crate::nut::config::{self, ConfigFile, ConfigSet, Finding, Role, Settings, Severity};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    UpsName(String),
+    Driver(String),
+    Port(String),
+    Description(String),
+    Listen(String),
+    ListenPort(String),
+    Username(String),
+    Password(String),
+    Role(Role),
+    ShutdownCmd(String),
+    Generate,
+    SelectFile(ConfigFile),
+    Edit(text_editor::Action),
+    Validate,
+    Load,
+    Loaded(Arc<io::Result<Option<ConfigSet>>>),
+    Save,
+    Saved(Arc<io::Result<Option<PathBuf>>>),
+    TestHost(String),
+    Test,
+    Tested(Arc<io::Result<Vec<Result<String, String>>>>),
+}
+
+/// Generates, checks and tests the configuration files of a NUT server.
+pub struct ConfigTool {
+    settings: Settings,
+    /// Kept as typed and only parsed on Generate, so a half-typed port isn't lost
+    listen_port: String,
+    drivers: combo_box::State<String>,
+    files: [text_editor::Content; 4],
+    selected: ConfigFile,
+    findings: Option<Vec<Finding>>,
+    status: Option<Result<String, String>>,
+    test_host: String,
+    testing: bool,
+    test_results: Vec<Result<String, String>>,
+}
+
+impl ConfigTool {
+    pub fn new() -> Self {
+        Self {
+            listen_port: Settings::default().listen_port.to_string(),
+            settings: Settings::default(),
+            drivers: combo_box::State::new(
+                config::known_drivers()
+                    .iter()
+                    .map(|driver| driver.to_string())
+                    .collect(),
+            ),
+            files: Default::default(),
+            selected: ConfigFile::UpsConf,
+            findings: None,
+            status: None,
+            test_host: String::new(),
+            testing: false,
+            test_results: Vec::new(),
+        }
+    }
+
+    fn config_set(&self) -> ConfigSet {
+        let mut set = ConfigSet::default();
+        for (index, file) in ConfigFile::ALL.into_iter().enumerate() {
+            *set.get_mut(file) = self.files[index].text();
+        }
+        set
+    }
+
+    fn set_config(&mut self, set: &ConfigSet) {
+        for (index, file) in ConfigFile::ALL.into_iter().enumerate() {
+            self.files[index] = text_editor::Content::with_text(set.get(file));
+        }
+        self.findings = Some(config::validate(set));
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::UpsName(name) => self.settings.ups_name = name,
+            Message::Driver(driver) => self.settings.driver = driver,
+            Message::Port(port) => self.settings.port = port,
+            Message::Description(description) => self.settings.description = description,
+            Message::Listen(listen) => self.settings.listen = listen,
+            Message::ListenPort(port) => self.listen_port = port,
+            Message::Username(username) => self.settings.username = username,
+            Message::Password(password) => self.settings.password = password,
+            Message::Role(role) => self.settings.role = role,
+            Message::ShutdownCmd(shutdown_cmd) => self.settings.shutdown_cmd = shutdown_cmd,
+            Message::Generate => {
+                let generated = match self.listen_port.trim().parse::<u16>() {
+                    Ok(port) if port != 0 => {
+                        self.settings.listen_port = port;
+                        config::generate(&self.settings)
+                    }
+                    _ => Err(format!(
+                        "\"{}\" is not a valid listen port",
+                        self.listen_port
+                    )),
+                };
+                match generated {
+                    Ok(set) => {
+                        self.set_config(&set);
+                        self.status = None;
+                    }
+                    Err(err) => self.status = Some(Err(err)),
+                }
+            }
+            Message::SelectFile(file) => self.selected = file,
+            Message::Edit(action) => {
+                let index = ConfigFile::ALL
+                    .iter()
+                    .position(|file| *file == self.selected)
+                    .unwrap_or_default();
+                self.files[index].perform(action);
+            }
+            Message::Validate => self.findings = Some(config::validate(&self.config_set())),
+            Message::Load => {
+                return Task::future(async {
+                    let result = match AsyncFileDialog::new()
+                        .set_title("Select the NUT configuration folder")
+                        .pick_folder()
+                        .await
+                    {
+                        Some(folder) => ConfigSet::load(folder.path()).await.map(Some),
+                        None => Ok(None),
+                    };
+                    Message::Loaded(Arc::new(result))
+                });
+            }
+            Message::Loaded(result) => match result.as_ref() {
+                Ok(Some(set)) => {
+                    self.set_config(set);
+                    self.status = None;
+                }
+                Ok(None) => {}
+                Err(err) => self.status = Some(Err(format!("Failed to load: {}", err))),
+            },
+            Message::Save => {
+                let set = self.config_set();
+                if set.written_files().is_empty() {
+                    self.status = Some(Err("All files are empty, nothing to save".to_string()));
+                    return Task::none();
+                }
+                return Task::future(async move {
+                    let Some(folder) = AsyncFileDialog::new()
+                        .set_title("Select the folder to write the configuration to")
+                        .pick_folder()
+                        .await
+                    else {
+                        return Message::Saved(Arc::new(Ok(None)));
+                    };
+                    let folder = folder.path().to_path_buf();
+
+                    let mut existing = Vec::new();
+                    for file in set.written_files() {
+                        if fs::try_exists(folder.join(file.file_name()))
+                            .await
+                            .unwrap_or(true)
+                        {
+                            existing.push(file.file_name());
+                        }
+                    }
+                    if !existing.is_empty()
+                        && AsyncMessageDialog::new()
+                            .set_title("Replace files?")
+                            .set_description(format!(
+                                "{} already {} in {}. Replace {}?",
+                                existing.join(", "),
+                                if existing.len() == 1 {
+                                    "exists"
+                                } else {
+                                    "exist"
+                                },
+                                folder.display(),
+                                if existing.len() == 1 { "it" } else { "them" },
+                            ))
+                            .set_buttons(MessageButtons::YesNo)
+                            .show()
+                            .await
+                            != MessageDialogResult::Yes
+                    {
+                        return Message::Saved(Arc::new(Ok(None)));
+                    }
+
+                    let result = set.save(&folder).await.map(|()| Some(folder));
+                    Message::Saved(Arc::new(result))
+                });
+            }
+            Message::Saved(result) => {
+                self.status = match result.as_ref() {
+                    Ok(Some(folder)) => Some(Ok(format!("Saved to {}", folder.display()))),
+                    Ok(None) => None,
+                    Err(err) => Some(Err(format!("Failed to save: {}", err))),
+                }
+            }
+            Message::TestHost(host) => self.test_host = host,
+            Message::Test => {
+                let set = self.config_set();
+                let host = self.test_host.trim().to_string();
+                self.testing = true;
+                self.test_results.clear();
+                return Task::future(async move {
+                    Message::Tested(Arc::new(config::test(&set, &host).await))
+                });
+            }
+            Message::Tested(result) => {
+                self.testing = false;
+                self.test_results = match result.as_ref() {
+                    Ok(results) => results.clone(),
+                    Err(err) => vec![Err(err.to_string())],
+                };
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let index = ConfigFile::ALL
+            .iter()
+            .position(|file| *file == self.selected)
+            .unwrap_or_default();
+
+        let generator = grid![
+            text!("UPS name"),
+            text_input("ups", &self.settings.ups_name).on_input(Message::UpsName),
+            text!("Driver"),
+            combo_box(
+                &self.drivers,
+                "usbhid-ups",
+                Some(&self.settings.driver),
+                Message::Driver
+            )
+            .on_input(Message::Driver),
+            text!("Port"),
+            text_input("auto", &self.settings.port).on_input(Message::Port),
+            text!("Description"),
+            text_input("Optional", &self.settings.description).on_input(Message::Description),
+            text!("Listen address"),
+            text_input("127.0.0.1", &self.settings.listen).on_input(Message::Listen),
+            text!("Listen port"),
+            text_input("3493", &self.listen_port).on_input(Message::ListenPort),
+            text!("upsmon user"),
+            text_input("upsmon", &self.settings.username).on_input(Message::Username),
+            text!("Password"),
+            text_input("Password", &self.settings.password)
+                .secure(true)
+                .on_input(Message::Password),
+            text!("Role"),
+            pick_list(Role::ALL, Some(self.settings.role), Message::Role),
+            text!("Shutdown command"),
+            text_input("/sbin/shutdown -h +0", &self.settings.shutdown_cmd)
+                .on_input(Message::ShutdownCmd),
+        ]
+        .columns(4)
+        .spacing(10)
+        .height(Length::Shrink);
+
+        let findings = self.findings.as_ref().map(|findings| {
+            if findings.is_empty() {
+                return column![text("No problems found").color(Color::from_rgb8(0, 160, 0))];
+            }
+            column(findings.iter().map(|finding| {
+                let color = match finding.severity {
+                    Severity::Error => Color::from_rgb8(255, 0, 0),
+                    Severity::Warning => Color::from_rgb8(255, 165, 0),
+                };
+                text(finding.to_string()).color(color).into()
+            }))
+            .spacing(5)
+        });
+
+        container(scrollable(
+            column![
+                generator,
+                row![
+                    button("Generate").on_press(Message::Generate),
+                    button("Load Folder").on_press(Message::Load),
+                    button("Save to Folder").on_press(Message::Save),
+                    match &self.status {
+                        Some(Ok(message)) => text(message).color(Color::from_rgb8(0, 160, 0)),
+                        Some(Err(error)) => text(error).color(Color::from_rgb8(255, 0, 0)),
+                        None => text(""),
+                    },
+                ]
+                .spacing(10),
+                row(ConfigFile::ALL.into_iter().map(|file| {
+                    button(file.file_name())
+                        .on_press_maybe(
+                            (file != self.selected).then_some(Message::SelectFile(file)),
+                        )
+                        .into()
+                }))
+                .spacing(5),
+                text_editor(&self.files[index])
+                    .font(Font::MONOSPACE)
+                    .placeholder("Generate, load or paste a configuration")
+                    .on_action(Message::Edit)
+                    .height(300),
+                button("Validate").on_press(Message::Validate),
+                findings,
+                row![
+                    text_input(
+                        "Host to test, empty to use the MONITOR lines",
+                        &self.test_host
+                    )
+                    .on_input(Message::TestHost)
+                    .width(350),
+                    button("Test against Server")
+                        .on_press_maybe((!self.testing).then_some(Message::Test)),
+                    self.testing
+                        .then(|| text("Testing...").color(Color::from_rgb8(255, 255, 0))),
+                ]
+                .spacing(10),
+                column(self.test_results.iter().map(|result| {
+                    match result {
+                        Ok(message) => text(message).color(Color::from_rgb8(0, 160, 0)),
+                        Err(error) => text(error).color(Color::from_rgb8(255, 0, 0)),
+                    }
+                    .into()
+                }))
+                .spacing(5),
+            ]
+            .spacing(10)
+            .padding(20),
+        ))
+        .into()
+    }
+}
+
+impl Default for ConfigTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
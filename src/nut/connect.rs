@@ -2,34 +2,47 @@ use std::sync::Arc;
 
 use iced::{
     Color, Element, Length, Task,
-    widget::{button, container, grid, row, text, text_input},
+    widget::{button, container, grid, pick_list, row, text, text_input},
 };
 use tokio::io;
 
-use crate::nut::nut::NutClient;
+use crate::nut::{
+    client::{Login, Protocol, UpsClient},
+    snmp::{AuthProtocol, Security, Version},
+};
 
 #[derive(Clone)]
 pub enum Message {
+    Protocol(Protocol),
     Host(String),
     Port(String),
     Username(String),
     Password(String),
+    SnmpVersion(Version),
+    Community(String),
+    AuthProtocol(AuthProtocol),
+    PrivacyPassword(String),
     Connect,
-    ConnectResult(Arc<io::Result<NutClient>>),
+    ConnectResult(Arc<io::Result<UpsClient>>),
     TogglePasswordVisibility,
 }
 
 pub enum Action {
     Run(Task<Message>),
-    Client(NutClient),
+    Client(Box<UpsClient>),
     None,
 }
 
 pub struct Connect {
+    protocol: Protocol,
     host: String,
     port: u16,
     username: String,
     password: String,
+    snmp_version: Version,
+    community: String,
+    auth_protocol: AuthProtocol,
+    privacy_password: String,
     show_password: bool,
     connecting: bool,
     error: Option<String>,
@@ -38,10 +51,15 @@ pub struct Connect {
 impl Connect {
     pub fn new() -> Self {
         Self {
+            protocol: Protocol::Nut,
             host: String::new(),
-            port: 3493,
+            port: Protocol::Nut.default_port(),
             username: String::new(),
             password: String::new(),
+            snmp_version: Version::V2c,
+            community: "public".to_string(),
+            auth_protocol: AuthProtocol::Sha1,
+            privacy_password: String::new(),
             show_password: false,
             connecting: false,
             error: None,
@@ -51,24 +69,33 @@ impl Connect {
     #[must_use]
     pub fn update(&mut self, message: Message) -> Action {
         match message {
+            Message::Protocol(protocol) => {
+                if self.port == self.protocol.default_port() {
+                    self.port = protocol.default_port();
+                }
+                self.protocol = protocol;
+            }
             Message::Host(host) => self.host = host,
             Message::Port(port) => self.port = port.parse().unwrap_or(0),
             Message::Username(username) => self.username = username,
             Message::Password(password) => self.password = password,
+            Message::SnmpVersion(version) => self.snmp_version = version,
+            Message::Community(community) => self.community = community,
+            Message::AuthProtocol(protocol) => self.auth_protocol = protocol,
+            Message::PrivacyPassword(password) => self.privacy_password = password,
             Message::TogglePasswordVisibility => self.show_password = !self.show_password,
             Message::Connect => {
                 let host = self.host.clone();
                 let port = self.port;
-                let username = self.username.clone();
-                let password = self.password.clone();
+                let login = self.login();
 
                 self.error = None;
                 self.connecting = true;
 
                 return Action::Run(
-                    Task::future(async move {
-                        Arc::new(NutClient::connect(host, port, username, password).await)
-                    })
+                    Task::future(
+                        async move { Arc::new(UpsClient::connect(host, port, login).await) },
+                    )
                     .map(Message::ConnectResult),
                 );
             }
@@ -78,7 +105,7 @@ impl Connect {
                 match result {
                     Ok(client) => {
                         self.error = None;
-                        return Action::Client(client);
+                        return Action::Client(Box::new(client));
                     }
                     Err(err) => {
                         self.error = Some(err.to_string());
@@ -89,42 +116,131 @@ impl Connect {
         Action::None
     }
 
+    fn login(&self) -> Login {
+        match (self.protocol, self.snmp_version) {
+            (Protocol::Nut, _) => Login::Nut {
+                username: self.username.clone(),
+                password: self.password.clone(),
+            },
+            (Protocol::Apcupsd, _) => Login::Apcupsd,
+            (Protocol::Snmp, Version::V2c) => {
+                Login::Snmp(Security::Community(self.community.clone()))
+            }
+            (Protocol::Snmp, Version::V3) => {
+                // An empty password means noAuthNoPriv, an empty privacy password authNoPriv
+                let auth = (!self.password.is_empty())
+                    .then(|| (self.auth_protocol, self.password.clone()));
+                let privacy =
+                    (!self.privacy_password.is_empty()).then(|| self.privacy_password.clone());
+                Login::Snmp(Security::Usm {
+                    user: self.username.clone(),
+                    auth,
+                    privacy,
+                })
+            }
+        }
+    }
+
     pub fn view(&self) -> Element<'_, Message> {
+        let password_input = |placeholder, value, on_input: fn(String) -> Message| {
+            row![
+                text_input(placeholder, value)
+                    .on_input(on_input)
+                    .secure(!self.show_password),
+                if self.show_password {
+                    button("Hide").on_press(Message::TogglePasswordVisibility)
+                } else {
+                    button("Show").on_press(Message::TogglePasswordVisibility)
+                },
+            ]
+            .spacing(10)
+        };
+
+        let mut fields = grid![
+            text!("Protocol"),
+            pick_list(Protocol::ALL, Some(self.protocol), Message::Protocol),
+            text!("Host"),
+            text_input("Host", &self.host).on_input(Message::Host),
+            text!("Port"),
+            text_input("Port", &self.port.to_string()).on_input(Message::Port),
+        ];
+
+        match self.protocol {
+            Protocol::Nut => {
+                fields = fields
+                    .push(text!("Username"))
+                    .push(text_input("Username", &self.username).on_input(Message::Username))
+                    .push(text!("Password"))
+                    .push(password_input(
+                        "Password",
+                        &self.password,
+                        Message::Password,
+                    ));
+            }
+            // apcupsd's NIS has no authentication
+            Protocol::Apcupsd => (),
+            Protocol::Snmp => {
+                fields = fields.push(text!("SNMP Version")).push(pick_list(
+                    Version::ALL,
+                    Some(self.snmp_version),
+                    Message::SnmpVersion,
+                ));
+                match self.snmp_version {
+                    Version::V2c => {
+                        fields = fields.push(text!("Community")).push(password_input(
+                            "Community",
+                            &self.community,
+                            Message::Community,
+                        ));
+                    }
+                    Version::V3 => {
+                        fields = fields
+                            .push(text!("Username"))
+                            .push(
+                                text_input("Username", &self.username).on_input(Message::Username),
+                            )
+                            .push(text!("Authentication"))
+                            .push(pick_list(
+                                AuthProtocol::ALL,
+                                Some(self.auth_protocol),
+                                Message::AuthProtocol,
+                            ))
+                            .push(text!("Auth Password"))
+                            .push(password_input(
+                                "Empty for noAuthNoPriv",
+                                &self.password,
+                                Message::Password,
+                            ))
+                            .push(text!("Privacy Password (AES)"))
+                            .push(password_input(
+                                "Empty for authNoPriv",
+                                &self.privacy_password,
+                                Message::PrivacyPassword,
+                            ));
+                    }
+                }
+            }
+        }
+
         container(
-            grid![
-                text!("Host"),
-                text_input("Host", &self.host).on_input(Message::Host),
-                text!("Port"),
-                text_input("Port", &self.port.to_string()).on_input(Message::Port),
-                text!("Username"),
-                text_input("Username", &self.username).on_input(Message::Username),
-                text!("Password"),
-                row![
-                    text_input("Password", &self.password)
-                        .on_input(Message::Password)
-                        .secure(!self.show_password),
-                    if self.show_password {
-                        button("Hide").on_press(Message::TogglePasswordVisibility)
-                    } else {
-                        button("Show").on_press(Message::TogglePasswordVisibility)
-                    },
-                ]
-                .spacing(10),
-                button("Connect").on_press_maybe((!self.connecting).then_some(Message::Connect)),
-                if self.connecting {
+            fields
+                .push(
+                    button("Connect")
+                        .on_press_maybe((!self.connecting).then_some(Message::Connect)),
+                )
+                .push(if self.connecting {
                     text("Connecting...").color(Color::from_rgb8(255, 255, 0))
                 } else {
                     text("")
-                },
-                if let Some(error) = &self.error {
+                })
+                .push(if let Some(error) = &self.error {
                     text(error).color(Color::from_rgb8(255, 0, 0))
                 } else {
                     text("")
-                }
-            ]
-            .columns(2)
-            .spacing(10)
-            .height(Length::Shrink),
+                })
+                .columns(2)
+                .spacing(10)
+                .height(Length::Shrink),
         )
         .padding(20)
         .into()
@@ -1,78 +1,228 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    sync::Arc,
+    time::Duration,
+};
 
 use iced::{
-    Element, Length, Task,
+    Color, Element, Length, Task,
     task::{self, sipper},
-    widget::{column, row, scrollable, text},
+    widget::{button, column, pick_list, row, scrollable, text},
 };
 use tokio::{io, time::sleep};
 
-use crate::nut::nut::NutClient;
+use crate::nut::{
+    alarms::{self, AlarmRule, Evaluator},
+    client::UpsClient,
+    compare,
+    nut::UpsInfo,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Clone)]
 pub enum Message {
-    Info(HashMap<String, Vec<(String, String)>>),
-    Error(Arc<Result<(), io::Error>>),
+    Info(String, HashMap<String, Vec<(String, String)>>),
+    Lost(String, String),
+    Error(String, Arc<Result<(), io::Error>>),
     Select(String),
+    SelectCompare(String),
+    ToggleCompare,
 }
 
 pub enum Action {
     None,
+    Events(Vec<Event>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    OnBattery,
+    OnLine,
+    LowBattery,
+    CommsLost,
+    CommsRestored,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 5] = [
+        EventKind::OnBattery,
+        EventKind::OnLine,
+        EventKind::LowBattery,
+        EventKind::CommsLost,
+        EventKind::CommsRestored,
+    ];
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventKind::OnBattery => write!(f, "On battery"),
+            EventKind::OnLine => write!(f, "Back on line power"),
+            EventKind::LowBattery => write!(f, "Low battery"),
+            EventKind::CommsLost => write!(f, "Communication lost"),
+            EventKind::CommsRestored => write!(f, "Communication restored"),
+        }
+    }
+}
+
+/// A change in the power situation of a UPS.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub kind: EventKind,
+    /// `ups@host:port`
+    pub ups: String,
+    /// The last known values of the UPS.
+    pub info: UpsInfo,
 }
 
+/// Polls one or more servers and shows the variables of their UPSes.
+///
+/// UPSes are listed as `ups@host:port`, so servers may share UPS names.
 pub struct Monitor {
     status: HashMap<String, Vec<(String, String)>>,
     list: Vec<String>,
-    error: Option<String>,
-    _drop_handle: task::Handle,
+    /// Current error per server
+    errors: BTreeMap<String, String>,
+    _drop_handles: Vec<task::Handle>,
     selected: Option<String>,
+    comparing: bool,
+    /// The UPS shown next to the selected one while comparing
+    compare_with: Option<String>,
+    alarms: Evaluator,
 }
 
 impl Monitor {
-    pub fn new(client: NutClient) -> (Self, Task<Message>) {
+    pub fn new(client: UpsClient, rules: Vec<AlarmRule>) -> (Self, Task<Message>) {
+        let mut alarms = Evaluator::default();
+        alarms.set_rules(rules);
+        let mut monitor = Self {
+            status: HashMap::new(),
+            list: Vec::new(),
+            _drop_handles: Vec::new(),
+            errors: BTreeMap::new(),
+            selected: None,
+            comparing: false,
+            compare_with: None,
+            alarms,
+        };
+        let task = monitor.add_client(client);
+        (monitor, task)
+    }
+
+    /// Start polling another server alongside the existing ones.
+    ///
+    /// If the connection breaks, the server is reconnected until it answers again.
+    pub fn add_client(&mut self, client: UpsClient) -> Task<Message> {
+        let server = client.server();
+        let update_server = server.clone();
+        let error_server = server.clone();
+
         let (task, handle) = Task::sip(
             sipper(|mut sender| async move {
                 let mut client = client;
                 let list = client.list_ups().await?;
                 loop {
-                    let mut info = HashMap::new();
-                    for (name, _desc) in &list {
-                        let status = client.list_vars_raw(name).await?;
-                        let mut status = status.into_iter().collect::<Vec<(String, String)>>();
-                        status.sort();
-                        info.insert(name.clone(), status);
+                    match poll(&mut client, &list, &server).await {
+                        Ok(info) => sender.send(Ok(info)).await,
+                        Err(err) => {
+                            sender.send(Err(err.to_string())).await;
+                            while client.reconnect().await.is_err() {
+                                sleep(RECONNECT_INTERVAL).await;
+                            }
+                            continue;
+                        }
                     }
-                    sender.send(info).await;
-                    sleep(Duration::from_secs(2)).await;
+                    sleep(POLL_INTERVAL).await;
                 }
             }),
-            Message::Info,
-            |result| Message::Error(Arc::new(result)),
+            move |update| match update {
+                Ok(info) => Message::Info(update_server.clone(), info),
+                Err(err) => Message::Lost(update_server.clone(), err),
+            },
+            move |result| Message::Error(error_server.clone(), Arc::new(result)),
         )
         .abortable();
 
-        (
-            Self {
-                status: HashMap::new(),
-                list: Vec::new(),
-                _drop_handle: handle,
-                error: None,
-                selected: None,
-            },
-            task,
-        )
+        self._drop_handles.push(handle);
+        task
+    }
+
+    pub fn set_alarm_rules(&mut self, rules: Vec<AlarmRule>) {
+        self.alarms.set_rules(rules);
     }
 
     pub fn update(&mut self, message: Message) -> Action {
         match message {
-            Message::Info(info) => {
-                self.list = info.keys().cloned().collect();
-                self.status = info;
-                Action::None
+            Message::Info(server, info) => {
+                let mut events = Vec::new();
+                let restored = self.errors.remove(&server).is_some();
+
+                for (ups, vars) in &info {
+                    self.alarms.evaluate(ups, vars);
+
+                    let previous = self.status.get(ups).map(|vars| status_flags(vars));
+                    let current = status_flags(vars);
+                    let mut push = |kind| {
+                        events.push(Event {
+                            kind,
+                            ups: ups.clone(),
+                            info: ups_info(ups, vars),
+                        })
+                    };
+
+                    if restored {
+                        push(EventKind::CommsRestored);
+                    }
+                    // The first poll only establishes the initial state
+                    let Some(previous) = previous else {
+                        continue;
+                    };
+                    let added = |flag: &str| {
+                        current.iter().any(|f| f == flag) && !previous.iter().any(|f| f == flag)
+                    };
+                    let removed = |flag: &str| {
+                        !current.iter().any(|f| f == flag) && previous.iter().any(|f| f == flag)
+                    };
+                    if added("OB") {
+                        push(EventKind::OnBattery);
+                    }
+                    if removed("OB") {
+                        push(EventKind::OnLine);
+                    }
+                    if added("LB") {
+                        push(EventKind::LowBattery);
+                    }
+                }
+
+                self.status.extend(info);
+                self.list = self.status.keys().cloned().collect();
+                self.list.sort();
+                Action::Events(events)
             }
-            Message::Error(err) => {
+            Message::Lost(server, error) => {
+                if self.errors.insert(server.clone(), error).is_some() {
+                    return Action::None;
+                }
+
+                let suffix = format!("@{}", server);
+                let events = self
+                    .status
+                    .iter()
+                    .filter(|(ups, _)| ups.ends_with(&suffix))
+                    .map(|(ups, vars)| Event {
+                        kind: EventKind::CommsLost,
+                        ups: ups.clone(),
+                        info: ups_info(ups, vars),
+                    })
+                    .collect();
+                Action::Events(events)
+            }
+            Message::Error(server, err) => {
                 if let Err(err) = err.as_ref() {
-                    self.error = Some(err.to_string());
+                    self.errors.insert(server, err.to_string());
                 }
                 Action::None
             }
@@ -80,31 +230,104 @@ impl Monitor {
                 self.selected = Some(selected);
                 Action::None
             }
+            Message::SelectCompare(compare_with) => {
+                self.compare_with = Some(compare_with);
+                Action::None
+            }
+            Message::ToggleCompare => {
+                self.comparing = !self.comparing;
+                Action::None
+            }
         }
     }
 
     pub(crate) fn view(&self) -> Element<'_, Message> {
-        scrollable(column![
-            iced::widget::pick_list(
-                self.list.as_slice(),
-                self.selected.as_ref(),
-                Message::Select,
-            ),
-            self.selected
-                .as_ref()
-                .map(|name| {
-                    self.status.get(name.as_str()).map(|status| {
-                        column(
-                            status
-                                .iter()
-                                .map(|(key, value)| row![text(key).width(300), text(value)].into()),
-                        )
-                        .spacing(10)
+        scrollable(
+            column![
+                alarms::banner(&self.alarms),
+                column(self.errors.iter().map(|(server, error)| {
+                    text!("{}: {}", server, error)
+                        .color(Color::from_rgb8(255, 0, 0))
+                        .into()
+                })),
+                row![
+                    pick_list(
+                        self.list.as_slice(),
+                        self.selected.as_ref(),
+                        Message::Select,
+                    ),
+                    button(if self.comparing {
+                        "Close Compare"
+                    } else {
+                        "Compare"
                     })
-                })
-                .flatten()
-        ])
+                    .on_press(Message::ToggleCompare),
+                    self.comparing.then(|| pick_list(
+                        self.list.as_slice(),
+                        self.compare_with.as_ref(),
+                        Message::SelectCompare,
+                    )),
+                ]
+                .spacing(10),
+                if self.comparing {
+                    self.view_compare()
+                } else {
+                    self.view_selected()
+                },
+                (!self.alarms.history().is_empty()).then(|| alarms::history(&self.alarms)),
+            ]
+            .spacing(10),
+        )
         .width(Length::Fill)
         .into()
     }
+
+    fn view_selected(&self) -> Option<Element<'_, Message>> {
+        let status = self.status.get(self.selected.as_ref()?)?;
+        Some(
+            column(
+                status
+                    .iter()
+                    .map(|(key, value)| row![text(key).width(300), text(value)].into()),
+            )
+            .spacing(10)
+            .into(),
+        )
+    }
+
+    fn view_compare(&self) -> Option<Element<'_, Message>> {
+        let left = self.selected.as_ref()?;
+        let right = self.compare_with.as_ref()?;
+        Some(compare::view(
+            (left, self.status.get(left)?),
+            (right, self.status.get(right)?),
+        ))
+    }
+}
+
+/// Fetch the variables of all UPSes of a server, keyed by `ups@host:port`.
+async fn poll(
+    client: &mut UpsClient,
+    list: &[(String, String)],
+    server: &str,
+) -> io::Result<HashMap<String, Vec<(String, String)>>> {
+    let mut info = HashMap::new();
+    for (name, _desc) in list {
+        let status = client.list_vars_raw(name).await?;
+        let mut status = status.into_iter().collect::<Vec<(String, String)>>();
+        status.sort();
+        info.insert(format!("{}@{}", name, server), status);
+    }
+    Ok(info)
+}
+
+fn status_flags(vars: &[(String, String)]) -> Vec<String> {
+    vars.iter()
+        .find(|(key, _)| key == "ups.status")
+        .map(|(_, value)| value.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn ups_info(ups: &str, vars: &[(String, String)]) -> UpsInfo {
+    UpsInfo::from_var_map(ups, vars.iter().cloned().collect())
 }
@@ -38,7 +38,7 @@ pub struct UpsInfo {
 }
 
 impl UpsInfo {
-    fn from_var_map(ups_name: &str, mut vars: HashMap<String, String>) -> Self {
+    pub(crate) fn from_var_map(ups_name: &str, mut vars: HashMap<String, String>) -> Self {
         // Helper to pull a key out of the map and return it
         let mut take = |key: &str| vars.remove(key);
 
@@ -130,10 +130,7 @@ impl NutClient {
 
         let first = self.read_line().await?;
         if !first.starts_with("BEGIN LIST UPS") {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Unexpected response: {}", first),
-            ));
+            return Err(io::Error::other(format!("Unexpected response: {}", first)));
         }
 
         let mut result = Vec::new();
@@ -150,10 +147,10 @@ impl NutClient {
                 let _ups = parts.next(); // "UPS"
                 let name = parts
                     .next()
-                    .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Missing UPS name"))?;
+                    .ok_or_else(|| io::Error::other("Missing UPS name"))?;
                 let desc_raw = parts
                     .next()
-                    .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Missing UPS desc"))?;
+                    .ok_or_else(|| io::Error::other("Missing UPS desc"))?;
                 let desc = strip_quotes(desc_raw);
                 result.push((name.to_string(), desc));
             }
@@ -168,6 +165,12 @@ impl NutClient {
         Ok(UpsInfo::from_var_map(ups_name, vars))
     }
 
+    /// Attach to a UPS like upsmon does. This is where upsd checks the password.
+    pub async fn login(&mut self, ups_name: &str) -> io::Result<()> {
+        self.send_command(&format!("LOGIN {}", ups_name)).await?;
+        self.expect_ok().await
+    }
+
     /// Optional: gracefully log out.
     pub async fn logout(&mut self) -> io::Result<()> {
         self.send_command("LOGOUT").await?;
@@ -198,10 +201,7 @@ impl NutClient {
 
         let first = self.read_line().await?;
         if !first.starts_with("BEGIN LIST VAR") {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Unexpected response: {}", first),
-            ));
+            return Err(io::Error::other(format!("Unexpected response: {}", first)));
         }
 
         let mut result = HashMap::new();
@@ -218,17 +218,17 @@ impl NutClient {
                 let _var = parts.next(); // "VAR"
                 let ups = parts
                     .next()
-                    .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Missing UPS name"))?;
+                    .ok_or_else(|| io::Error::other("Missing UPS name"))?;
                 if ups != ups_name {
                     continue;
                 }
 
                 let var_name = parts
                     .next()
-                    .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Missing var name"))?;
+                    .ok_or_else(|| io::Error::other("Missing var name"))?;
                 let value_raw = parts
                     .next()
-                    .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Missing var value"))?;
+                    .ok_or_else(|| io::Error::other("Missing var value"))?;
                 let value = strip_quotes(value_raw);
 
                 result.insert(var_name.to_string(), value);
@@ -267,10 +267,7 @@ impl NutClient {
         if line.starts_with("OK") {
             Ok(())
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Expected OK, got: {}", line),
-            ))
+            Err(io::Error::other(format!("Expected OK, got: {}", line)))
         }
     }
 }
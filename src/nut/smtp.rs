@@ -0,0 +1,436 @@
+use std::{
+    fmt::Display,
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Local};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{ClientConfig, RootCertStore, crypto::ring, pki_types::ServerName},
+};
+
+use crate::eml;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encryption {
+    None,
+    StartTls,
+    Tls,
+}
+
+impl Encryption {
+    pub const ALL: [Encryption; 3] = [Encryption::None, Encryption::StartTls, Encryption::Tls];
+
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Encryption::None => 25,
+            Encryption::StartTls => 587,
+            Encryption::Tls => 465,
+        }
+    }
+}
+
+impl Display for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encryption::None => write!(f, "None"),
+            Encryption::StartTls => write!(f, "STARTTLS"),
+            Encryption::Tls => write!(f, "TLS"),
+        }
+    }
+}
+
+/// Everything needed to deliver a mail through an SMTP server.
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub encryption: Encryption,
+    /// If empty, no AUTH command is sent.
+    pub username: String,
+    pub password: String,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// Send a plain text mail. The body is sent quoted-printable encoded.
+pub async fn send_mail(settings: &SmtpSettings, subject: &str, body: &str) -> io::Result<()> {
+    if settings.to.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No recipients configured",
+        ));
+    }
+
+    let stream = TcpStream::connect((settings.host.as_str(), settings.port)).await?;
+    let stream: Box<dyn Stream> = match settings.encryption {
+        Encryption::Tls => Box::new(tls_connect(&settings.host, stream).await?),
+        Encryption::None | Encryption::StartTls => Box::new(stream),
+    };
+
+    let mut connection = Connection {
+        stream: BufReader::new(stream),
+    };
+    connection.expect(&[220]).await?;
+
+    let mut extensions = connection.ehlo().await?;
+
+    if settings.encryption == Encryption::StartTls {
+        connection.command("STARTTLS", &[220]).await?;
+        let stream = connection.stream.into_inner();
+        connection = Connection {
+            stream: BufReader::new(Box::new(tls_connect(&settings.host, stream).await?)),
+        };
+        extensions = connection.ehlo().await?;
+    }
+
+    if !settings.username.is_empty() {
+        let auth = extensions
+            .iter()
+            .find_map(|extension| extension.strip_prefix("AUTH "))
+            .unwrap_or_default();
+
+        if auth
+            .split_whitespace()
+            .any(|mechanism| mechanism == "PLAIN")
+        {
+            let credentials = format!("\0{}\0{}", settings.username, settings.password);
+            connection
+                .command(
+                    &format!("AUTH PLAIN {}", BASE64_STANDARD.encode(credentials)),
+                    &[235],
+                )
+                .await?;
+        } else {
+            connection.command("AUTH LOGIN", &[334]).await?;
+            connection
+                .command(&BASE64_STANDARD.encode(&settings.username), &[334])
+                .await?;
+            connection
+                .command(&BASE64_STANDARD.encode(&settings.password), &[235])
+                .await?;
+        }
+    }
+
+    connection
+        .command(&format!("MAIL FROM:<{}>", settings.from), &[250])
+        .await?;
+    for recipient in &settings.to {
+        // 251: the server forwards the mail to another address
+        connection
+            .command(&format!("RCPT TO:<{}>", recipient), &[250, 251])
+            .await?;
+    }
+    connection.command("DATA", &[354]).await?;
+
+    let message = format_message(settings, subject, body, Local::now());
+    connection.write(&message).await?;
+    connection.command(".", &[250]).await?;
+
+    // The mail is accepted at this point, so a failing QUIT doesn't matter
+    let _ = connection.command("QUIT", &[221]).await;
+
+    Ok(())
+}
+
+fn format_message(
+    settings: &SmtpSettings,
+    subject: &str,
+    body: &str,
+    now: DateTime<Local>,
+) -> String {
+    let subject = if subject.is_ascii() {
+        subject.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", BASE64_STANDARD.encode(subject))
+    };
+
+    let mut message = String::new();
+    message.push_str(&format!("From: <{}>\r\n", settings.from));
+    message.push_str(&format!(
+        "To: {}\r\n",
+        settings
+            .to
+            .iter()
+            .map(|recipient| format!("<{}>", recipient))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    message.push_str(&format!("Subject: {}\r\n", subject));
+    message.push_str(&format!("Date: {}\r\n", now.to_rfc2822()));
+    message.push_str(&format!(
+        "Message-ID: {}\r\n",
+        message_id(&settings.from, now)
+    ));
+    message.push_str("MIME-Version: 1.0\r\n");
+    message.push_str("Content-Type: text/plain; charset=UTF-8\r\n");
+    message.push_str("Content-Transfer-Encoding: quoted-printable\r\n");
+    message.push_str("\r\n");
+
+    let body = body.replace("\r\n", "\n");
+    for line in body.split('\n') {
+        for encoded in eml::qp_encode(line).split('\n') {
+            // Dot stuffing, a single dot would end the DATA section
+            if encoded.starts_with('.') {
+                message.push('.');
+            }
+            message.push_str(encoded);
+            message.push_str("\r\n");
+        }
+    }
+
+    message
+}
+
+/// A unique ID below the domain of the sender, as relays expect one.
+fn message_id(from: &str, now: DateTime<Local>) -> String {
+    static SENT: AtomicU64 = AtomicU64::new(0);
+    let domain = from
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .filter(|domain| !domain.is_empty())
+        .unwrap_or("toolbox.localhost");
+    format!(
+        "<{}.{}.{}@{}>",
+        now.timestamp_micros(),
+        std::process::id(),
+        SENT.fetch_add(1, Ordering::Relaxed),
+        domain
+    )
+}
+
+async fn tls_connect<S>(host: &str, stream: S) -> io::Result<tokio_rustls::client::TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+struct Connection {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+impl Connection {
+    /// Send EHLO and return the advertised extensions.
+    async fn ehlo(&mut self) -> io::Result<Vec<String>> {
+        self.write("EHLO toolbox\r\n").await?;
+        let lines = self.expect(&[250]).await?;
+        Ok(lines.into_iter().skip(1).collect())
+    }
+
+    async fn command(&mut self, command: &str, expected: &[u16]) -> io::Result<Vec<String>> {
+        self.write(&format!("{}\r\n", command)).await?;
+        self.expect(expected).await
+    }
+
+    async fn write(&mut self, data: &str) -> io::Result<()> {
+        let stream = self.stream.get_mut();
+        stream.write_all(data.as_bytes()).await?;
+        stream.flush().await
+    }
+
+    /// Read a (possibly multi-line) reply and check that its code is one of `expected`.
+    async fn expect(&mut self, expected: &[u16]) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed by server",
+                ));
+            }
+            let line = line.trim_end();
+
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse::<u16>().ok())
+                .ok_or_else(|| io::Error::other(format!("Invalid SMTP reply: {}", line)))?;
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+
+            if last {
+                if !expected.contains(&code) {
+                    return Err(io::Error::other(format!("SMTP server replied: {}", line)));
+                }
+                return Ok(lines);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    use super::*;
+
+    /// What the stand-in server received.
+    #[derive(Debug, Default)]
+    struct Received {
+        commands: Vec<String>,
+        /// The DATA section as sent, before removing the dot stuffing
+        data: Vec<String>,
+    }
+
+    /// Serve a single connection, answering RCPT with `rcpt_reply`.
+    async fn server(rcpt_reply: &'static str) -> (u16, JoinHandle<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            let mut received = Received::default();
+
+            write.write_all(b"220 stand-in ESMTP\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                received.commands.push(line.clone());
+                let reply = match line.split_whitespace().next().unwrap_or_default() {
+                    "EHLO" => "250-stand-in\r\n250-8BITMIME\r\n250 AUTH LOGIN PLAIN\r\n",
+                    "AUTH" => "235 Authenticated\r\n",
+                    "MAIL" => "250 OK\r\n",
+                    "RCPT" => rcpt_reply,
+                    "DATA" => {
+                        write.write_all(b"354 Go ahead\r\n").await.unwrap();
+                        while let Some(line) = lines.next_line().await.unwrap() {
+                            if line == "." {
+                                break;
+                            }
+                            received.data.push(line);
+                        }
+                        "250 Queued\r\n"
+                    }
+                    "QUIT" => {
+                        write.write_all(b"221 Bye\r\n").await.unwrap();
+                        break;
+                    }
+                    _ => "500 Unknown command\r\n",
+                };
+                write.write_all(reply.as_bytes()).await.unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    fn settings(port: u16) -> SmtpSettings {
+        SmtpSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            encryption: Encryption::None,
+            username: "alerts".to_string(),
+            password: "secret".to_string(),
+            from: "ups@example.com".to_string(),
+            to: vec![
+                "admin@example.com".to_string(),
+                "oncall@example.com".to_string(),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_mail() {
+        let (port, server) = server("250 OK\r\n").await;
+        let body = "UPS on battery\n.\n.hidden\nÜbertemperatur im Serverraum";
+        send_mail(&settings(port), "Power lost", body)
+            .await
+            .unwrap();
+        let received = server.await.unwrap();
+
+        let credentials = BASE64_STANDARD.encode("\0alerts\0secret");
+        assert_eq!(
+            received.commands,
+            [
+                "EHLO toolbox".to_string(),
+                format!("AUTH PLAIN {}", credentials),
+                "MAIL FROM:<ups@example.com>".to_string(),
+                "RCPT TO:<admin@example.com>".to_string(),
+                "RCPT TO:<oncall@example.com>".to_string(),
+                "DATA".to_string(),
+                "QUIT".to_string(),
+            ]
+        );
+
+        let separator = received.data.iter().position(String::is_empty).unwrap();
+        let (headers, body) = received.data.split_at(separator);
+        assert!(headers.contains(&"Subject: Power lost".to_string()));
+        assert!(headers.contains(&"Content-Transfer-Encoding: quoted-printable".to_string()));
+        let date = headers
+            .iter()
+            .find_map(|header| header.strip_prefix("Date: "));
+        assert!(DateTime::parse_from_rfc2822(date.unwrap()).is_ok());
+        let id = headers
+            .iter()
+            .find_map(|header| header.strip_prefix("Message-ID: "))
+            .unwrap();
+        assert!(id.starts_with('<') && id.ends_with("@example.com>"));
+
+        assert_eq!(
+            &body[1..],
+            [
+                "UPS on battery",
+                "..",
+                "..hidden",
+                "=C3=9Cbertemperatur im Serverraum"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_forwarded_recipient() {
+        let (port, server) = server("251 User not local; will forward\r\n").await;
+        send_mail(&settings(port), "Test", "Test").await.unwrap();
+        assert!(received_data(server).await);
+    }
+
+    #[tokio::test]
+    async fn error_reply_fails() {
+        let (port, server) = server("550 No such user\r\n").await;
+        let err = send_mail(&settings(port), "Test", "Test")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("550 No such user"));
+        assert!(!received_data(server).await);
+    }
+
+    async fn received_data(server: JoinHandle<Received>) -> bool {
+        !server.await.unwrap().data.is_empty()
+    }
+
+    #[test]
+    fn message_ids_are_unique() {
+        let now = Local::now();
+        assert_ne!(message_id("a@b.de", now), message_id("a@b.de", now));
+        assert!(message_id("no-domain", now).ends_with("@toolbox.localhost>"));
+    }
+}
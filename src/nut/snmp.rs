@@ -0,0 +1,1491 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::io;
+use std::time::Duration;
+
+use aes::{
+    Aes128,
+    cipher::{BlockEncrypt, KeyInit as _, generic_array::GenericArray},
+};
+use hmac::{Hmac, KeyInit as _, Mac};
+use md5::Md5;
+use sha1::{Digest, Sha1};
+use tokio::{
+    net::{UdpSocket, lookup_host},
+    time::{Instant, timeout_at},
+};
+
+use crate::nut::nut::UpsInfo;
+
+/// upsMIB from RFC 1628
+const UPS_MIB: &[u32] = &[1, 3, 6, 1, 2, 1, 33, 1];
+
+/// The parts of the UPS-MIB that are polled, relative to `upsObjects`.
+const POLLED_GROUPS: &[&[u32]] = &[
+    &[1],    // upsIdent
+    &[2],    // upsBattery
+    &[3],    // upsInput
+    &[4],    // upsOutput
+    &[6, 1], // upsAlarmsPresent
+    &[9],    // upsConfig
+];
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+const RETRIES: usize = 3;
+const MAX_MESSAGE_SIZE: i64 = 65507;
+const MAX_REPETITIONS: i64 = 25;
+
+// BER tags
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const IP_ADDRESS: u8 = 0x40;
+const COUNTER32: u8 = 0x41;
+const GAUGE32: u8 = 0x42;
+const TIME_TICKS: u8 = 0x43;
+const OPAQUE: u8 = 0x44;
+const COUNTER64: u8 = 0x46;
+const NO_SUCH_OBJECT: u8 = 0x80;
+const NO_SUCH_INSTANCE: u8 = 0x81;
+const END_OF_MIB_VIEW: u8 = 0x82;
+
+// PDU tags
+const GET_REQUEST: u8 = 0xA0;
+const RESPONSE: u8 = 0xA2;
+const GET_BULK_REQUEST: u8 = 0xA5;
+const REPORT: u8 = 0xA8;
+
+/// usmStatsNotInTimeWindows, sent when our engine time is out of date.
+const NOT_IN_TIME_WINDOW: &[u32] = &[1, 3, 6, 1, 6, 3, 15, 1, 1, 2, 0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    V2c,
+    V3,
+}
+
+impl Version {
+    pub const ALL: [Version; 2] = [Version::V2c, Version::V3];
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Version::V2c => write!(f, "v2c"),
+            Version::V3 => write!(f, "v3"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthProtocol {
+    Md5,
+    Sha1,
+}
+
+impl AuthProtocol {
+    pub const ALL: [AuthProtocol; 2] = [AuthProtocol::Md5, AuthProtocol::Sha1];
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            AuthProtocol::Md5 => Md5::digest(data).to_vec(),
+            AuthProtocol::Sha1 => Sha1::digest(data).to_vec(),
+        }
+    }
+
+    /// HMAC-96 as used by the USM: the first 12 bytes of the HMAC.
+    fn hmac96(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mac = match self {
+            AuthProtocol::Md5 => {
+                let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC accepts any key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            AuthProtocol::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        };
+        mac[..12].to_vec()
+    }
+
+    /// Password to localized key as described in RFC 3414, A.2.
+    fn localize_key(&self, password: &str, engine_id: &[u8]) -> io::Result<Vec<u8>> {
+        if password.len() < 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "SNMPv3 passwords must be at least 8 characters long",
+            ));
+        }
+
+        let password = password.as_bytes();
+        let expanded: Vec<u8> = password.iter().copied().cycle().take(1 << 20).collect();
+        let key = self.hash(&expanded);
+
+        let mut localized = key.clone();
+        localized.extend_from_slice(engine_id);
+        localized.extend_from_slice(&key);
+        Ok(self.hash(&localized))
+    }
+}
+
+impl Display for AuthProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthProtocol::Md5 => write!(f, "MD5"),
+            AuthProtocol::Sha1 => write!(f, "SHA-1"),
+        }
+    }
+}
+
+/// How to authenticate against the SNMP agent.
+#[derive(Debug, Clone)]
+pub enum Security {
+    /// SNMPv2c with a community string.
+    Community(String),
+    /// SNMPv3 user based security model.
+    ///
+    /// Privacy always uses AES-128 and requires authentication.
+    Usm {
+        user: String,
+        auth: Option<(AuthProtocol, String)>,
+        privacy: Option<String>,
+    },
+}
+
+/// A single SNMP value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    OctetString(Vec<u8>),
+    Null,
+    Oid(Vec<u32>),
+    IpAddress([u8; 4]),
+    Counter32(u32),
+    Gauge32(u32),
+    TimeTicks(u32),
+    Opaque(Vec<u8>),
+    Counter64(u64),
+    NoSuchObject,
+    NoSuchInstance,
+    EndOfMibView,
+}
+
+impl Value {
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            Value::Counter32(value) | Value::Gauge32(value) | Value::TimeTicks(value) => {
+                Some(*value as i64)
+            }
+            Value::Counter64(value) => i64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::OctetString(bytes) | Value::Opaque(bytes) => {
+                let printable = bytes
+                    .iter()
+                    .all(|byte| byte.is_ascii_graphic() || *byte == b' ');
+                if printable {
+                    write!(f, "{}", String::from_utf8_lossy(bytes))
+                } else {
+                    for byte in bytes {
+                        write!(f, "{:02X}", byte)?;
+                    }
+                    Ok(())
+                }
+            }
+            Value::Null => Ok(()),
+            Value::Oid(oid) => write!(f, "{}", format_oid(oid)),
+            Value::IpAddress([a, b, c, d]) => write!(f, "{}.{}.{}.{}", a, b, c, d),
+            Value::Counter32(value) | Value::Gauge32(value) | Value::TimeTicks(value) => {
+                write!(f, "{}", value)
+            }
+            Value::Counter64(value) => write!(f, "{}", value),
+            Value::NoSuchObject => write!(f, "noSuchObject"),
+            Value::NoSuchInstance => write!(f, "noSuchInstance"),
+            Value::EndOfMibView => write!(f, "endOfMibView"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SnmpClient {
+    socket: UdpSocket,
+    security: SecurityState,
+    request_id: i32,
+    ups_name: String,
+    description: String,
+}
+
+#[derive(Debug)]
+enum SecurityState {
+    Community(Vec<u8>),
+    Usm(Usm),
+}
+
+#[derive(Debug)]
+struct Usm {
+    user: Vec<u8>,
+    /// Protocol and localized key
+    auth: Option<(AuthProtocol, Vec<u8>)>,
+    /// Localized AES-128 key
+    privacy: Option<Vec<u8>>,
+    engine_id: Vec<u8>,
+    engine_boots: u32,
+    engine_time: u32,
+    salt: u64,
+}
+
+impl SnmpClient {
+    /// Connect to an SNMP agent and read the UPS identification.
+    ///
+    /// For SNMPv3 this also runs the engine discovery and localizes the keys.
+    pub async fn connect(
+        host: impl Into<String>,
+        port: u16,
+        security: Security,
+    ) -> io::Result<Self> {
+        let addr = lookup_host(format!("{}:{}", host.into(), port))
+            .await?
+            .next()
+            .ok_or_else(|| io::Error::other("Host not found"))?;
+        let socket = if addr.is_ipv4() {
+            UdpSocket::bind("0.0.0.0:0").await?
+        } else {
+            UdpSocket::bind("[::]:0").await?
+        };
+        socket.connect(addr).await?;
+
+        let (security, pending) = match security {
+            Security::Community(community) => {
+                (SecurityState::Community(community.into_bytes()), None)
+            }
+            Security::Usm {
+                user,
+                auth,
+                privacy,
+            } => {
+                if privacy.is_some() && auth.is_none() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "SNMPv3 privacy requires authentication",
+                    ));
+                }
+                let usm = Usm {
+                    user: user.into_bytes(),
+                    auth: None,
+                    privacy: None,
+                    engine_id: Vec::new(),
+                    engine_boots: 0,
+                    engine_time: 0,
+                    salt: std::process::id() as u64,
+                };
+                (SecurityState::Usm(usm), Some((auth, privacy)))
+            }
+        };
+
+        let mut client = SnmpClient {
+            socket,
+            security,
+            request_id: std::process::id() as i32 & 0x7fff,
+            ups_name: String::new(),
+            description: String::new(),
+        };
+
+        if let Some((auth, privacy)) = pending {
+            client.discover_engine().await?;
+            if let SecurityState::Usm(usm) = &mut client.security
+                && let Some((protocol, password)) = auth
+            {
+                usm.auth = Some((protocol, protocol.localize_key(&password, &usm.engine_id)?));
+                if let Some(password) = privacy {
+                    let mut key = protocol.localize_key(&password, &usm.engine_id)?;
+                    key.truncate(16);
+                    usm.privacy = Some(key);
+                }
+            }
+        }
+
+        let model = [UPS_MIB, &[1, 2, 0]].concat();
+        let name = [UPS_MIB, &[1, 5, 0]].concat();
+        let ident = client.get(&[model.clone(), name.clone()]).await?;
+        let ident_value = |oid: &Vec<u32>| {
+            ident
+                .iter()
+                .find(|(ident_oid, value)| {
+                    ident_oid == oid && matches!(value, Value::OctetString(_))
+                })
+                .map(|(_, value)| value.to_string())
+                .filter(|value| !value.is_empty())
+        };
+        client.description = ident_value(&model).unwrap_or_default();
+        client.ups_name = ident_value(&name)
+            .or_else(|| ident_value(&model))
+            .unwrap_or_else(|| "ups".to_string());
+
+        Ok(client)
+    }
+
+    /// An SNMP agent only describes a single UPS, named after `upsIdentName`.
+    pub async fn list_ups(&mut self) -> io::Result<Vec<(String, String)>> {
+        Ok(vec![(self.ups_name.clone(), self.description.clone())])
+    }
+
+    /// Walk the UPS-MIB and map the values onto NUT variable names.
+    pub async fn list_vars_raw(&mut self) -> io::Result<HashMap<String, String>> {
+        let mut values = Vec::new();
+        for group in POLLED_GROUPS {
+            values.extend(self.walk(&[UPS_MIB, group].concat()).await?);
+        }
+        Ok(map_ups_mib(values))
+    }
+
+    /// High-level helper: walk the UPS-MIB and map it into `UpsInfo`.
+    pub async fn get_ups_info(&mut self) -> io::Result<UpsInfo> {
+        let vars = self.list_vars_raw().await?;
+        Ok(UpsInfo::from_var_map(&self.ups_name, vars))
+    }
+
+    /// GET the given OIDs.
+    pub async fn get(&mut self, oids: &[Vec<u32>]) -> io::Result<Vec<(Vec<u32>, Value)>> {
+        self.request(GET_REQUEST, 0, 0, oids).await
+    }
+
+    /// Fetch every value below `root` using GETBULK requests.
+    pub async fn walk(&mut self, root: &[u32]) -> io::Result<Vec<(Vec<u32>, Value)>> {
+        let mut result = Vec::new();
+        let mut current = root.to_vec();
+
+        loop {
+            let varbinds = self
+                .request(GET_BULK_REQUEST, 0, MAX_REPETITIONS, &[current.clone()])
+                .await?;
+            if varbinds.is_empty() {
+                return Ok(result);
+            }
+
+            for (oid, value) in varbinds {
+                if !oid.starts_with(root) || value == Value::EndOfMibView {
+                    return Ok(result);
+                }
+                if oid <= current {
+                    return Err(io::Error::other(format!(
+                        "Agent returned OIDs out of order at {}",
+                        format_oid(&oid)
+                    )));
+                }
+                current = oid.clone();
+                result.push((oid, value));
+            }
+        }
+    }
+
+    // ---------- internal helpers ----------
+
+    /// Send an empty, unauthenticated request to learn the engine ID, boots and time.
+    async fn discover_engine(&mut self) -> io::Result<()> {
+        match self.request(GET_REQUEST, 0, 0, &[]).await {
+            Ok(_) => Ok(()),
+            Err(err) => match &self.security {
+                // The expected answer is a report, which already updated the engine info
+                SecurityState::Usm(usm) if !usm.engine_id.is_empty() => Ok(()),
+                _ => Err(err),
+            },
+        }
+    }
+
+    async fn request(
+        &mut self,
+        pdu_type: u8,
+        non_repeaters: i64,
+        max_repetitions: i64,
+        oids: &[Vec<u32>],
+    ) -> io::Result<Vec<(Vec<u32>, Value)>> {
+        self.request_id = self.request_id.wrapping_add(1) & 0x7fff_ffff;
+        let request_id = self.request_id;
+        let pdu = encode_pdu(pdu_type, request_id, non_repeaters, max_repetitions, oids);
+
+        let mut buffer = vec![0u8; MAX_MESSAGE_SIZE as usize];
+        let mut resynced = false;
+        let mut attempt = 0;
+
+        while attempt < RETRIES {
+            attempt += 1;
+            let message = self.wrap(request_id, &pdu)?;
+            self.socket.send(&message).await?;
+
+            // Stray, late or forged datagrams are dropped without restarting the timeout
+            let deadline = Instant::now() + TIMEOUT;
+            let received = loop {
+                let len = match timeout_at(deadline, self.socket.recv(&mut buffer)).await {
+                    Ok(len) => len?,
+                    Err(_) => break None,
+                };
+                let Ok(Some(response)) = self.unwrap(&buffer[..len]) else {
+                    continue;
+                };
+                let Ok((tag, id, error_status, error_index, varbinds)) = decode_pdu(&response)
+                else {
+                    continue;
+                };
+                if id != request_id && tag != REPORT {
+                    continue;
+                }
+                break Some((tag, error_status, error_index, varbinds));
+            };
+
+            let Some((tag, error_status, error_index, varbinds)) = received else {
+                continue;
+            };
+
+            if tag == REPORT {
+                let not_in_time = varbinds
+                    .first()
+                    .is_some_and(|(oid, _)| oid == NOT_IN_TIME_WINDOW);
+                if not_in_time && !resynced {
+                    resynced = true;
+                    attempt -= 1;
+                    continue;
+                }
+                let report = varbinds
+                    .first()
+                    .map(|(oid, _)| format_oid(oid))
+                    .unwrap_or_default();
+                return Err(io::Error::other(format!(
+                    "Agent sent report {}",
+                    report_name(&report)
+                )));
+            }
+
+            if tag != RESPONSE {
+                return Err(io::Error::other(format!("Unexpected PDU type {:02X}", tag)));
+            }
+
+            if error_status != 0 {
+                return Err(io::Error::other(format!(
+                    "Agent returned {} at index {}",
+                    error_status_name(error_status),
+                    error_index
+                )));
+            }
+
+            return Ok(varbinds);
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "No response from SNMP agent",
+        ))
+    }
+
+    /// Wrap a PDU into a v2c or v3 message.
+    fn wrap(&mut self, msg_id: i32, pdu: &[u8]) -> io::Result<Vec<u8>> {
+        let usm = match &mut self.security {
+            SecurityState::Community(community) => {
+                return Ok(sequence(&[&integer(1), &tlv(OCTET_STRING, community), pdu]));
+            }
+            SecurityState::Usm(usm) => usm,
+        };
+
+        let mut flags = 0x04; // reportable
+        if usm.auth.is_some() {
+            flags |= 0x01;
+        }
+
+        let scoped_pdu = sequence(&[
+            &tlv(OCTET_STRING, &usm.engine_id),
+            &tlv(OCTET_STRING, b""),
+            pdu,
+        ]);
+
+        let (msg_data, priv_params) = match &usm.privacy {
+            Some(key) => {
+                flags |= 0x02;
+                usm.salt = usm.salt.wrapping_add(1);
+                let salt = usm.salt.to_be_bytes();
+                let mut encrypted = scoped_pdu;
+                aes_cfb(
+                    key,
+                    aes_iv(usm.engine_boots, usm.engine_time, &salt),
+                    &mut encrypted,
+                    true,
+                );
+                (tlv(OCTET_STRING, &encrypted), salt.to_vec())
+            }
+            None => (scoped_pdu, Vec::new()),
+        };
+
+        let auth_params = if usm.auth.is_some() {
+            vec![0u8; 12]
+        } else {
+            Vec::new()
+        };
+        let before_auth = [
+            tlv(OCTET_STRING, &usm.engine_id),
+            integer(usm.engine_boots as i64),
+            integer(usm.engine_time as i64),
+            tlv(OCTET_STRING, &usm.user),
+        ]
+        .concat();
+        let params_content = [
+            before_auth.as_slice(),
+            &tlv(OCTET_STRING, &auth_params),
+            &tlv(OCTET_STRING, &priv_params),
+        ]
+        .concat();
+        let security_params = tlv(SEQUENCE, &params_content);
+
+        let global_data = sequence(&[
+            &integer(msg_id as i64),
+            &integer(MAX_MESSAGE_SIZE),
+            &tlv(OCTET_STRING, &[flags]),
+            &integer(3), // USM
+        ]);
+
+        let version = integer(3);
+        let wrapped_params = tlv(OCTET_STRING, &security_params);
+        let content_len = version.len() + global_data.len() + wrapped_params.len() + msg_data.len();
+        let mut message = sequence(&[&version, &global_data, &wrapped_params, &msg_data]);
+
+        if let Some((protocol, key)) = &usm.auth {
+            // Walk the headers up to the content of msgAuthenticationParameters
+            let auth_offset = header_len(content_len)
+                + version.len()
+                + global_data.len()
+                + header_len(security_params.len())
+                + header_len(params_content.len())
+                + before_auth.len()
+                + header_len(auth_params.len());
+            let mac = protocol.hmac96(key, &message);
+            message[auth_offset..auth_offset + 12].copy_from_slice(&mac);
+        }
+
+        Ok(message)
+    }
+
+    /// Unwrap a received message and return the PDU.
+    ///
+    /// Returns `None` for messages that should be ignored.
+    fn unwrap(&mut self, message: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let mut reader = Reader::new(message);
+        let mut outer = reader.expect(SEQUENCE)?;
+        let version = decode_integer(outer.expect(INTEGER)?.content())?;
+
+        let usm = match &mut self.security {
+            SecurityState::Community(community) => {
+                if version != 1 || outer.expect(OCTET_STRING)?.content() != community.as_slice() {
+                    return Ok(None);
+                }
+                return Ok(Some(outer.content().to_vec()));
+            }
+            SecurityState::Usm(usm) => usm,
+        };
+
+        if version != 3 {
+            return Ok(None);
+        }
+
+        let mut global_data = outer.expect(SEQUENCE)?;
+        let _msg_id = global_data.expect(INTEGER)?;
+        let _max_size = global_data.expect(INTEGER)?;
+        let flags = global_data
+            .expect(OCTET_STRING)?
+            .content()
+            .first()
+            .copied()
+            .unwrap_or(0);
+
+        let mut security_params = outer.expect(OCTET_STRING)?.expect(SEQUENCE)?;
+        let engine_id = security_params.expect(OCTET_STRING)?.content().to_vec();
+        let engine_boots = decode_integer(security_params.expect(INTEGER)?.content())? as u32;
+        let engine_time = decode_integer(security_params.expect(INTEGER)?.content())? as u32;
+        let _user = security_params.expect(OCTET_STRING)?;
+        let auth_params = security_params.expect(OCTET_STRING)?;
+        let priv_params = security_params.expect(OCTET_STRING)?.content().to_vec();
+
+        if flags & 0x01 != 0 {
+            let Some((protocol, key)) = &usm.auth else {
+                return Ok(None);
+            };
+            let mut zeroed = message.to_vec();
+            zeroed[auth_params.range()].fill(0);
+            if protocol.hmac96(key, &zeroed) != auth_params.content() {
+                return Err(io::Error::other("SNMPv3 authentication failed"));
+            }
+        }
+
+        // Only trust the engine info from the discovery or authenticated messages
+        if usm.engine_id.is_empty() || flags & 0x01 != 0 {
+            usm.engine_id = engine_id;
+            usm.engine_boots = engine_boots;
+            usm.engine_time = engine_time;
+        }
+
+        let scoped_pdu = if flags & 0x02 != 0 {
+            let Some(key) = &usm.privacy else {
+                return Ok(None);
+            };
+            let mut decrypted = outer.expect(OCTET_STRING)?.content().to_vec();
+            aes_cfb(
+                key,
+                aes_iv(engine_boots, engine_time, &priv_params),
+                &mut decrypted,
+                false,
+            );
+            decrypted
+        } else {
+            outer.expect(SEQUENCE)?.whole().to_vec()
+        };
+
+        let mut scoped_pdu_reader = Reader::new(&scoped_pdu);
+        let mut scoped = scoped_pdu_reader.expect(SEQUENCE)?;
+        let _context_engine_id = scoped.expect(OCTET_STRING)?;
+        let _context_name = scoped.expect(OCTET_STRING)?;
+        Ok(Some(scoped.content().to_vec()))
+    }
+}
+
+/// Map UPS-MIB values onto the equivalent NUT variables.
+///
+/// Values without a NUT counterpart are kept as `upsmib.<object>`.
+fn map_ups_mib(values: Vec<(Vec<u32>, Value)>) -> HashMap<String, String> {
+    let mut rest: BTreeMap<Vec<u32>, Value> = values
+        .into_iter()
+        .filter(|(oid, _)| oid.starts_with(UPS_MIB))
+        .map(|(oid, value)| (oid[UPS_MIB.len()..].to_vec(), value))
+        .collect();
+    let mut vars = HashMap::new();
+
+    let mut status = Vec::new();
+    match rest.get(&vec![4, 1, 0]).and_then(Value::as_i64) {
+        Some(2) => status.push("OFF"),
+        Some(3) => status.push("OL"),
+        Some(4) => status.push("BYPASS"),
+        Some(5) => status.push("OB"),
+        Some(6) => status.extend(["OL", "BOOST"]),
+        Some(7) => status.extend(["OL", "TRIM"]),
+        _ => (),
+    }
+    if let Some(battery_status) = rest.remove(&vec![2, 1, 0]).and_then(|v| v.as_i64()) {
+        if battery_status == 3 || battery_status == 4 {
+            status.push("LB");
+        }
+        let name = match battery_status {
+            2 => "batteryNormal",
+            3 => "batteryLow",
+            4 => "batteryDepleted",
+            _ => "unknown",
+        };
+        vars.insert("upsmib.upsBatteryStatus".to_string(), name.to_string());
+    }
+    if rest
+        .get(&vec![6, 1, 0])
+        .and_then(Value::as_i64)
+        .is_some_and(|alarms| alarms > 0)
+    {
+        status.push("ALARM");
+    }
+    if !status.is_empty() {
+        vars.insert("ups.status".to_string(), status.join(" "));
+    }
+
+    // Helper to move a value over, optionally scaled
+    let mut map = |oid: &[u32], var: &str, scale: Scale| {
+        if let Some(value) = rest.remove(oid) {
+            let value = match (scale, value.as_i64()) {
+                (Scale::Tenths, Some(number)) => format!("{:.1}", number as f64 / 10.0),
+                (Scale::Minutes, Some(number)) => (number * 60).to_string(),
+                _ => value.to_string(),
+            };
+            vars.insert(var.to_string(), value);
+        }
+    };
+
+    map(&[1, 1, 0], "ups.mfr", Scale::None);
+    map(&[1, 2, 0], "ups.model", Scale::None);
+    map(&[1, 3, 0], "ups.firmware", Scale::None);
+    map(&[1, 4, 0], "ups.firmware.aux", Scale::None);
+    map(&[1, 5, 0], "ups.id", Scale::None);
+
+    map(&[2, 2, 0], "upsmib.upsSecondsOnBattery", Scale::None);
+    map(&[2, 3, 0], "battery.runtime", Scale::Minutes);
+    map(&[2, 4, 0], "battery.charge", Scale::None);
+    map(&[2, 5, 0], "battery.voltage", Scale::Tenths);
+    map(&[2, 6, 0], "battery.current", Scale::Tenths);
+    map(&[2, 7, 0], "battery.temperature", Scale::None);
+
+    map(&[3, 1, 0], "upsmib.upsInputLineBads", Scale::None);
+    map(&[3, 2, 0], "upsmib.upsInputNumLines", Scale::None);
+    map(&[3, 3, 1, 2, 1], "input.frequency", Scale::Tenths);
+    map(&[3, 3, 1, 3, 1], "input.voltage", Scale::None);
+    map(&[3, 3, 1, 4, 1], "input.current", Scale::Tenths);
+    map(&[3, 3, 1, 5, 1], "input.realpower", Scale::None);
+
+    map(&[4, 1, 0], "upsmib.upsOutputSource", Scale::None);
+    map(&[4, 2, 0], "output.frequency", Scale::Tenths);
+    map(&[4, 3, 0], "upsmib.upsOutputNumLines", Scale::None);
+    map(&[4, 4, 1, 2, 1], "output.voltage", Scale::None);
+    map(&[4, 4, 1, 3, 1], "output.current", Scale::Tenths);
+    map(&[4, 4, 1, 4, 1], "ups.realpower", Scale::None);
+    map(&[4, 4, 1, 5, 1], "ups.load", Scale::None);
+
+    map(&[6, 1, 0], "upsmib.upsAlarmsPresent", Scale::None);
+
+    map(&[9, 1, 0], "input.voltage.nominal", Scale::None);
+    map(&[9, 2, 0], "input.frequency.nominal", Scale::Tenths);
+    map(&[9, 3, 0], "output.voltage.nominal", Scale::None);
+    map(&[9, 4, 0], "output.frequency.nominal", Scale::Tenths);
+    map(&[9, 5, 0], "ups.power.nominal", Scale::None);
+    map(&[9, 6, 0], "ups.realpower.nominal", Scale::None);
+    map(&[9, 7, 0], "battery.runtime.low", Scale::Minutes);
+
+    for (oid, value) in rest {
+        vars.insert(format!("upsmib.{}", format_oid(&oid)), value.to_string());
+    }
+
+    vars
+}
+
+#[derive(Clone, Copy)]
+enum Scale {
+    None,
+    Tenths,
+    Minutes,
+}
+
+fn format_oid(oid: &[u32]) -> String {
+    oid.iter()
+        .map(|arc| arc.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn report_name(oid: &str) -> String {
+    let name = match oid {
+        "1.3.6.1.6.3.15.1.1.1.0" => "usmStatsUnsupportedSecLevels",
+        "1.3.6.1.6.3.15.1.1.2.0" => "usmStatsNotInTimeWindows",
+        "1.3.6.1.6.3.15.1.1.3.0" => "usmStatsUnknownUserNames",
+        "1.3.6.1.6.3.15.1.1.4.0" => "usmStatsUnknownEngineIDs",
+        "1.3.6.1.6.3.15.1.1.5.0" => "usmStatsWrongDigests",
+        "1.3.6.1.6.3.15.1.1.6.0" => "usmStatsDecryptionErrors",
+        other => other,
+    };
+    name.to_string()
+}
+
+fn error_status_name(status: i64) -> String {
+    let name = match status {
+        1 => "tooBig",
+        2 => "noSuchName",
+        3 => "badValue",
+        4 => "readOnly",
+        5 => "genErr",
+        6 => "noAccess",
+        16 => "authorizationError",
+        other => return format!("error {}", other),
+    };
+    name.to_string()
+}
+
+// ---------- crypto ----------
+
+fn aes_iv(engine_boots: u32, engine_time: u32, salt: &[u8]) -> [u8; 16] {
+    let mut iv = [0u8; 16];
+    iv[..4].copy_from_slice(&engine_boots.to_be_bytes());
+    iv[4..8].copy_from_slice(&engine_time.to_be_bytes());
+    let salt_len = salt.len().min(8);
+    iv[8..8 + salt_len].copy_from_slice(&salt[..salt_len]);
+    iv
+}
+
+/// AES-128 in CFB128 mode as required by RFC 3826.
+fn aes_cfb(key: &[u8], iv: [u8; 16], data: &mut [u8], encrypt: bool) {
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+    let mut feedback = iv;
+    for chunk in data.chunks_mut(16) {
+        let mut keystream = GenericArray::from(feedback);
+        cipher.encrypt_block(&mut keystream);
+        for (index, byte) in chunk.iter_mut().enumerate() {
+            let input = *byte;
+            *byte ^= keystream[index];
+            feedback[index] = if encrypt { *byte } else { input };
+        }
+    }
+}
+
+// ---------- BER encoding ----------
+
+/// Length of the tag and length bytes `tlv` puts in front of `len` bytes of content.
+fn header_len(len: usize) -> usize {
+    if len < 0x80 {
+        2
+    } else {
+        2 + (usize::BITS - len.leading_zeros()).div_ceil(8) as usize
+    }
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut result = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        result.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+        result.push(0x80 | (bytes.len() - skip) as u8);
+        result.extend_from_slice(&bytes[skip..]);
+    }
+    result.extend_from_slice(content);
+    result
+}
+
+fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    tlv(SEQUENCE, &parts.concat())
+}
+
+fn integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    // Strip redundant sign bytes while keeping the sign bit intact
+    while start < 7
+        && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xFF && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    tlv(INTEGER, &bytes[start..])
+}
+
+fn object_identifier(oid: &[u32]) -> Vec<u8> {
+    let mut content = Vec::new();
+    if oid.len() >= 2 {
+        encode_arc(&mut content, oid[0] * 40 + oid[1]);
+        for arc in &oid[2..] {
+            encode_arc(&mut content, *arc);
+        }
+    }
+    tlv(OBJECT_IDENTIFIER, &content)
+}
+
+fn encode_arc(content: &mut Vec<u8>, arc: u32) {
+    let mut groups = vec![(arc & 0x7F) as u8];
+    let mut rest = arc >> 7;
+    while rest > 0 {
+        groups.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    content.extend(groups.iter().rev());
+}
+
+fn encode_pdu(
+    tag: u8,
+    request_id: i32,
+    non_repeaters: i64,
+    max_repetitions: i64,
+    oids: &[Vec<u32>],
+) -> Vec<u8> {
+    let varbinds: Vec<u8> = oids
+        .iter()
+        .flat_map(|oid| sequence(&[&object_identifier(oid), &tlv(NULL, &[])]))
+        .collect();
+    tlv(
+        tag,
+        &[
+            integer(request_id as i64),
+            integer(non_repeaters),
+            integer(max_repetitions),
+            tlv(SEQUENCE, &varbinds),
+        ]
+        .concat(),
+    )
+}
+
+// ---------- BER decoding ----------
+
+type Pdu = (u8, i32, i64, i64, Vec<(Vec<u32>, Value)>);
+
+fn decode_pdu(data: &[u8]) -> io::Result<Pdu> {
+    let mut reader = Reader::new(data);
+    let (tag, mut pdu) = reader.next()?;
+    let request_id = decode_integer(pdu.expect(INTEGER)?.content())? as i32;
+    let error_status = decode_integer(pdu.expect(INTEGER)?.content())?;
+    let error_index = decode_integer(pdu.expect(INTEGER)?.content())?;
+
+    let mut varbinds = Vec::new();
+    let mut list = pdu.expect(SEQUENCE)?;
+    while !list.is_empty() {
+        let mut varbind = list.expect(SEQUENCE)?;
+        let oid = decode_oid(varbind.expect(OBJECT_IDENTIFIER)?.content())?;
+        let (value_tag, value) = varbind.next()?;
+        varbinds.push((oid, decode_value(value_tag, value.content())?));
+    }
+
+    Ok((tag, request_id, error_status, error_index, varbinds))
+}
+
+fn decode_value(tag: u8, content: &[u8]) -> io::Result<Value> {
+    Ok(match tag {
+        INTEGER => Value::Integer(decode_integer(content)?),
+        OCTET_STRING => Value::OctetString(content.to_vec()),
+        NULL => Value::Null,
+        OBJECT_IDENTIFIER => Value::Oid(decode_oid(content)?),
+        IP_ADDRESS => Value::IpAddress(
+            content
+                .try_into()
+                .map_err(|_| io::Error::other("Invalid IP address"))?,
+        ),
+        COUNTER32 => Value::Counter32(decode_unsigned(content)? as u32),
+        GAUGE32 => Value::Gauge32(decode_unsigned(content)? as u32),
+        TIME_TICKS => Value::TimeTicks(decode_unsigned(content)? as u32),
+        OPAQUE => Value::Opaque(content.to_vec()),
+        COUNTER64 => Value::Counter64(decode_unsigned(content)?),
+        NO_SUCH_OBJECT => Value::NoSuchObject,
+        NO_SUCH_INSTANCE => Value::NoSuchInstance,
+        END_OF_MIB_VIEW => Value::EndOfMibView,
+        other => {
+            return Err(io::Error::other(format!(
+                "Unsupported value type {:02X}",
+                other
+            )));
+        }
+    })
+}
+
+fn decode_integer(content: &[u8]) -> io::Result<i64> {
+    if content.is_empty() || content.len() > 8 {
+        return Err(io::Error::other("Invalid integer length"));
+    }
+    let negative = content[0] & 0x80 != 0;
+    let mut value: i64 = if negative { -1 } else { 0 };
+    for byte in content {
+        value = (value << 8) | *byte as i64;
+    }
+    Ok(value)
+}
+
+fn decode_unsigned(content: &[u8]) -> io::Result<u64> {
+    let content = content.strip_prefix(&[0]).unwrap_or(content);
+    if content.len() > 8 {
+        return Err(io::Error::other("Invalid integer length"));
+    }
+    Ok(content
+        .iter()
+        .fold(0u64, |value, byte| (value << 8) | *byte as u64))
+}
+
+fn decode_oid(content: &[u8]) -> io::Result<Vec<u32>> {
+    let mut arcs = Vec::new();
+    let mut arc: u32 = 0;
+    for byte in content {
+        arc = arc
+            .checked_mul(128)
+            .ok_or_else(|| io::Error::other("OID arc too large"))?
+            | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - first * 40);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        }
+    }
+    Ok(arcs)
+}
+
+/// Cursor over BER encoded data that remembers absolute positions,
+/// so authentication parameters can be located in the original message.
+struct Reader<'a> {
+    data: &'a [u8],
+    start: usize,
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            start: 0,
+            pos: 0,
+            end: data.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.end
+    }
+
+    /// The content that hasn't been read yet.
+    fn content(&self) -> &'a [u8] {
+        &self.data[self.pos..self.end]
+    }
+
+    /// The complete TLV including tag and length.
+    fn whole(&self) -> &'a [u8] {
+        &self.data[self.start..self.end]
+    }
+
+    fn range(&self) -> std::ops::Range<usize> {
+        self.pos..self.end
+    }
+
+    fn next(&mut self) -> io::Result<(u8, Reader<'a>)> {
+        let truncated = || io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated SNMP message");
+        let start = self.pos;
+        let tag = *self.data.get(self.pos).ok_or_else(truncated)?;
+        let first = *self.data.get(self.pos + 1).ok_or_else(truncated)?;
+        self.pos += 2;
+
+        let len = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count > 4 {
+                return Err(io::Error::other("Invalid BER length"));
+            }
+            let bytes = self
+                .data
+                .get(self.pos..self.pos + count)
+                .ok_or_else(truncated)?;
+            self.pos += count;
+            bytes
+                .iter()
+                .fold(0usize, |len, byte| (len << 8) | *byte as usize)
+        };
+
+        let content_start = self.pos;
+        let content_end = content_start + len;
+        if content_end > self.end {
+            return Err(truncated());
+        }
+        self.pos = content_end;
+
+        Ok((
+            tag,
+            Reader {
+                data: self.data,
+                start,
+                pos: content_start,
+                end: content_end,
+            },
+        ))
+    }
+
+    fn expect(&mut self, expected: u8) -> io::Result<Reader<'a>> {
+        let (tag, reader) = self.next()?;
+        if tag != expected {
+            return Err(io::Error::other(format!(
+                "Expected BER tag {:02X}, got {:02X}",
+                expected, tag
+            )));
+        }
+        Ok(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGINE_ID: &[u8] = b"\x80\x00\x1f\x88\x04toolbox";
+    const COMMUNITY: &str = "public";
+    const USER: &str = "monitor";
+    const AUTH_PASSWORD: &str = "auth-password";
+    const PRIV_PASSWORD: &str = "priv-password";
+
+    /// The UPS-MIB of the stand-in agent.
+    fn mib() -> BTreeMap<Vec<u32>, Value> {
+        let ups = |oid: &[u32]| [UPS_MIB, oid].concat();
+        BTreeMap::from([
+            (ups(&[1, 1, 0]), Value::OctetString(b"Toolbox".to_vec())),
+            (
+                ups(&[1, 2, 0]),
+                Value::OctetString(b"Smart-UPS 1500".to_vec()),
+            ),
+            (ups(&[1, 5, 0]), Value::OctetString(b"rack-ups".to_vec())),
+            (ups(&[2, 1, 0]), Value::Integer(2)),
+            (ups(&[2, 3, 0]), Value::Integer(42)),
+            (ups(&[2, 4, 0]), Value::Integer(97)),
+            (ups(&[2, 5, 0]), Value::Integer(272)),
+            (ups(&[3, 3, 1, 2, 1]), Value::Integer(500)),
+            (ups(&[3, 3, 1, 3, 1]), Value::Integer(231)),
+            (ups(&[4, 1, 0]), Value::Integer(3)),
+            (ups(&[4, 4, 1, 5, 1]), Value::Gauge32(18)),
+            (ups(&[6, 1, 0]), Value::Gauge32(0)),
+            (ups(&[9, 7, 0]), Value::Integer(2)),
+            (ups(&[12, 1, 0]), Value::Integer(1)),
+        ])
+    }
+
+    fn encode_value(value: &Value) -> Vec<u8> {
+        let mut encoded = match value {
+            Value::Integer(value) => integer(*value),
+            Value::OctetString(bytes) => tlv(OCTET_STRING, bytes),
+            Value::Gauge32(value) => integer(*value as i64),
+            Value::NoSuchObject => tlv(NO_SUCH_OBJECT, &[]),
+            Value::EndOfMibView => tlv(END_OF_MIB_VIEW, &[]),
+            other => panic!("The agent doesn't send {:?}", other),
+        };
+        if let Value::Gauge32(_) = value {
+            encoded[0] = GAUGE32;
+        }
+        encoded
+    }
+
+    fn response(tag: u8, request_id: i32, varbinds: &[(Vec<u32>, Value)]) -> Vec<u8> {
+        let varbinds: Vec<u8> = varbinds
+            .iter()
+            .flat_map(|(oid, value)| sequence(&[&object_identifier(oid), &encode_value(value)]))
+            .collect();
+        tlv(
+            tag,
+            &[
+                integer(request_id as i64),
+                integer(0),
+                integer(0),
+                tlv(SEQUENCE, &varbinds),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Answer a GET or GETBULK from the MIB.
+    fn answer(mib: &BTreeMap<Vec<u32>, Value>, pdu: &[u8]) -> (i32, Vec<(Vec<u32>, Value)>) {
+        let mut reader = Reader::new(pdu);
+        let (tag, mut fields) = reader.next().unwrap();
+        let request_id = decode_integer(fields.expect(INTEGER).unwrap().content()).unwrap() as i32;
+        let _non_repeaters = fields.expect(INTEGER).unwrap();
+        let max_repetitions = decode_integer(fields.expect(INTEGER).unwrap().content()).unwrap();
+        let (_, _, _, _, requested) = decode_pdu(pdu).unwrap();
+
+        let mut varbinds = Vec::new();
+        for (oid, _) in requested {
+            match tag {
+                GET_REQUEST => {
+                    let value = mib.get(&oid).cloned().unwrap_or(Value::NoSuchObject);
+                    varbinds.push((oid, value));
+                }
+                GET_BULK_REQUEST => {
+                    let next: Vec<_> = mib
+                        .range(oid.clone()..)
+                        .filter(|(next, _)| **next != oid)
+                        .take(max_repetitions as usize)
+                        .map(|(oid, value)| (oid.clone(), value.clone()))
+                        .collect();
+                    if next.len() < max_repetitions as usize {
+                        varbinds.extend(next);
+                        varbinds.push((oid, Value::EndOfMibView));
+                    } else {
+                        varbinds.extend(next);
+                    }
+                }
+                other => panic!("Unexpected PDU {:02X}", other),
+            }
+        }
+        (request_id, varbinds)
+    }
+
+    /// A v2c agent. With `noisy` every answer is preceded by garbage and a wrong request-id.
+    async fn v2c_agent(noisy: bool) -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mib = mib();
+            let mut buffer = vec![0u8; MAX_MESSAGE_SIZE as usize];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buffer).await.unwrap();
+                let mut reader = Reader::new(&buffer[..len]);
+                let mut outer = reader.expect(SEQUENCE).unwrap();
+                assert_eq!(
+                    decode_integer(outer.expect(INTEGER).unwrap().content()).unwrap(),
+                    1
+                );
+                if outer.expect(OCTET_STRING).unwrap().content() != COMMUNITY.as_bytes() {
+                    continue;
+                }
+                let (request_id, varbinds) = answer(&mib, outer.content());
+
+                let wrap = |pdu: &[u8]| {
+                    sequence(&[&integer(1), &tlv(OCTET_STRING, COMMUNITY.as_bytes()), pdu])
+                };
+                if noisy {
+                    socket.send_to(b"garbage", peer).await.unwrap();
+                    let stale = response(RESPONSE, request_id.wrapping_add(1000), &[]);
+                    socket.send_to(&wrap(&stale), peer).await.unwrap();
+                }
+                let pdu = response(RESPONSE, request_id, &varbinds);
+                socket.send_to(&wrap(&pdu), peer).await.unwrap();
+            }
+        });
+        port
+    }
+
+    /// A v3 agent with authPriv. With `noisy` every answer is preceded by one with a bad HMAC.
+    async fn v3_agent(protocol: AuthProtocol, noisy: bool) -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let mut privacy = protocol.localize_key(PRIV_PASSWORD, ENGINE_ID).unwrap();
+        privacy.truncate(16);
+        // The agent uses the same message handling as the client, from the other side
+        let mut agent = SnmpClient {
+            socket: UdpSocket::bind("127.0.0.1:0").await.unwrap(),
+            security: SecurityState::Usm(Usm {
+                user: USER.as_bytes().to_vec(),
+                auth: Some((
+                    protocol,
+                    protocol.localize_key(AUTH_PASSWORD, ENGINE_ID).unwrap(),
+                )),
+                privacy: Some(privacy),
+                engine_id: ENGINE_ID.to_vec(),
+                engine_boots: 7,
+                engine_time: 1234,
+                salt: 0x5eed,
+            }),
+            request_id: 0,
+            ups_name: String::new(),
+            description: String::new(),
+        };
+        tokio::spawn(async move {
+            let mib = mib();
+            let mut buffer = vec![0u8; MAX_MESSAGE_SIZE as usize];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buffer).await.unwrap();
+                let message = buffer[..len].to_vec();
+                let Ok(Some(pdu)) = agent.unwrap(&message) else {
+                    continue;
+                };
+                let (request_id, varbinds) = answer(&mib, &pdu);
+
+                // Discovery: the client doesn't know the engine yet and sends no auth
+                let flags = Reader::new(&message)
+                    .expect(SEQUENCE)
+                    .and_then(|mut outer| {
+                        outer.expect(INTEGER)?;
+                        let mut global_data = outer.expect(SEQUENCE)?;
+                        global_data.expect(INTEGER)?;
+                        global_data.expect(INTEGER)?;
+                        Ok(global_data.expect(OCTET_STRING)?.content()[0])
+                    })
+                    .unwrap();
+                if flags & 0x01 == 0 {
+                    let SecurityState::Usm(usm) = &mut agent.security else {
+                        unreachable!()
+                    };
+                    let keys = (usm.auth.take(), usm.privacy.take());
+                    let unknown_engine = [1, 3, 6, 1, 6, 3, 15, 1, 1, 4, 0];
+                    let report = response(
+                        REPORT,
+                        request_id,
+                        &[(unknown_engine.to_vec(), Value::Gauge32(1))],
+                    );
+                    let wrapped = agent.wrap(request_id, &report).unwrap();
+                    let SecurityState::Usm(usm) = &mut agent.security else {
+                        unreachable!()
+                    };
+                    (usm.auth, usm.privacy) = keys;
+                    socket.send_to(&wrapped, peer).await.unwrap();
+                    continue;
+                }
+
+                let pdu = response(RESPONSE, request_id, &varbinds);
+                if noisy {
+                    let mut forged = agent.wrap(request_id, &pdu).unwrap();
+                    let last = forged.len() - 1;
+                    forged[last] ^= 0xFF;
+                    socket.send_to(&forged, peer).await.unwrap();
+                }
+                let wrapped = agent.wrap(request_id, &pdu).unwrap();
+                socket.send_to(&wrapped, peer).await.unwrap();
+            }
+        });
+        port
+    }
+
+    fn usm(protocol: AuthProtocol) -> Security {
+        Security::Usm {
+            user: USER.to_string(),
+            auth: Some((protocol, AUTH_PASSWORD.to_string())),
+            privacy: Some(PRIV_PASSWORD.to_string()),
+        }
+    }
+
+    fn assert_polled(vars: &HashMap<String, String>) {
+        assert_eq!(vars["ups.status"], "OL");
+        assert_eq!(vars["ups.model"], "Smart-UPS 1500");
+        assert_eq!(vars["battery.charge"], "97");
+        assert_eq!(vars["battery.voltage"], "27.2");
+        assert_eq!(vars["battery.runtime"], "2520");
+        assert_eq!(vars["input.frequency"], "50.0");
+        assert_eq!(vars["ups.load"], "18");
+        // upsMIB.12 is not polled
+        assert!(!vars.keys().any(|var| var.starts_with("upsmib.12")));
+    }
+
+    #[tokio::test]
+    async fn v2c_get_and_walk() {
+        let port = v2c_agent(false).await;
+        let mut client =
+            SnmpClient::connect("127.0.0.1", port, Security::Community(COMMUNITY.into()))
+                .await
+                .unwrap();
+        assert_eq!(
+            client.list_ups().await.unwrap(),
+            vec![("rack-ups".to_string(), "Smart-UPS 1500".to_string())]
+        );
+        assert_polled(&client.list_vars_raw().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn v2c_ignores_stray_datagrams() {
+        let port = v2c_agent(true).await;
+        let mut client =
+            SnmpClient::connect("127.0.0.1", port, Security::Community(COMMUNITY.into()))
+                .await
+                .unwrap();
+        assert_polled(&client.list_vars_raw().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn v3_auth_priv() {
+        for protocol in AuthProtocol::ALL {
+            let port = v3_agent(protocol, false).await;
+            let mut client = SnmpClient::connect("127.0.0.1", port, usm(protocol))
+                .await
+                .unwrap();
+            assert_eq!(client.ups_name, "rack-ups");
+            assert_polled(&client.list_vars_raw().await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn v3_drops_bad_hmac() {
+        let port = v3_agent(AuthProtocol::Sha1, true).await;
+        let mut client = SnmpClient::connect("127.0.0.1", port, usm(AuthProtocol::Sha1))
+            .await
+            .unwrap();
+        assert_polled(&client.list_vars_raw().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn hmac96_is_checked() {
+        let mut client = SnmpClient {
+            socket: UdpSocket::bind("127.0.0.1:0").await.unwrap(),
+            security: SecurityState::Usm(Usm {
+                user: USER.as_bytes().to_vec(),
+                auth: Some((
+                    AuthProtocol::Md5,
+                    AuthProtocol::Md5
+                        .localize_key(AUTH_PASSWORD, ENGINE_ID)
+                        .unwrap(),
+                )),
+                privacy: None,
+                engine_id: ENGINE_ID.to_vec(),
+                engine_boots: 1,
+                engine_time: 2,
+                salt: 0,
+            }),
+            request_id: 0,
+            ups_name: String::new(),
+            description: String::new(),
+        };
+        let pdu = response(RESPONSE, 5, &[]);
+        let message = client.wrap(5, &pdu).unwrap();
+        assert_eq!(client.unwrap(&message).unwrap(), Some(pdu));
+
+        // Any changed byte, including the MAC itself, fails the check
+        for index in [message.len() - 1, message.len() / 2] {
+            let mut tampered = message.clone();
+            tampered[index] ^= 0x01;
+            assert!(client.unwrap(&tampered).is_err());
+        }
+    }
+
+    #[test]
+    fn localized_keys_match_rfc_3414() {
+        let engine_id = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+        assert_eq!(
+            AuthProtocol::Md5
+                .localize_key("maplesyrup", &engine_id)
+                .unwrap(),
+            [
+                0x52, 0x6f, 0x5e, 0xed, 0x9f, 0xcc, 0xe2, 0x6f, 0x89, 0x64, 0xc2, 0x93, 0x07, 0x87,
+                0xd8, 0x2b
+            ]
+        );
+        assert_eq!(
+            AuthProtocol::Sha1
+                .localize_key("maplesyrup", &engine_id)
+                .unwrap(),
+            [
+                0x66, 0x95, 0xfe, 0xbc, 0x92, 0x88, 0xe3, 0x62, 0x82, 0x23, 0x5f, 0xc7, 0x15, 0x1f,
+                0x12, 0x84, 0x97, 0xb3, 0x8f, 0x3f
+            ]
+        );
+    }
+
+    #[test]
+    fn aes_cfb_round_trip() {
+        // NIST SP 800-38A, F.3.13 CFB128-AES128.Encrypt, first block
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let iv: [u8; 16] = std::array::from_fn(|index| index as u8);
+        let mut block = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+            0x17, 0x2a,
+        ];
+        aes_cfb(&key, iv, &mut block, true);
+        assert_eq!(
+            block,
+            [
+                0x3b, 0x3f, 0xd9, 0x2e, 0xb7, 0x2d, 0xad, 0x20, 0x33, 0x34, 0x49, 0xf8, 0xe8, 0x3c,
+                0xfb, 0x4a
+            ]
+        );
+
+        // Messages are rarely a multiple of the block size
+        let plain: Vec<u8> = (0..45).collect();
+        let mut data = plain.clone();
+        aes_cfb(&key, aes_iv(3, 4, &[1; 8]), &mut data, true);
+        assert_ne!(data, plain);
+        aes_cfb(&key, aes_iv(3, 4, &[1; 8]), &mut data, false);
+        assert_eq!(data, plain);
+    }
+
+    #[test]
+    fn maps_ups_mib() {
+        let mut values: Vec<_> = mib().into_iter().collect();
+        let ups = |oid: &[u32]| [UPS_MIB, oid].concat();
+        values.retain(|(oid, _)| *oid != ups(&[2, 1, 0]) && *oid != ups(&[6, 1, 0]));
+        values.push((ups(&[2, 1, 0]), Value::Integer(3)));
+        values.push((ups(&[6, 1, 0]), Value::Gauge32(2)));
+        values.push((ups(&[4, 1, 0]), Value::Integer(5)));
+        // Outside the UPS-MIB
+        values.push((vec![1, 3, 6, 1, 2, 1, 1, 5, 0], Value::Integer(1)));
+
+        let vars = map_ups_mib(values);
+        assert_eq!(vars["ups.status"], "OB LB ALARM");
+        assert_eq!(vars["upsmib.upsBatteryStatus"], "batteryLow");
+        assert_eq!(vars["battery.runtime"], "2520");
+        assert_eq!(vars["battery.runtime.low"], "120");
+        assert_eq!(vars["battery.voltage"], "27.2");
+        assert_eq!(vars["input.voltage"], "231");
+        assert_eq!(vars["ups.mfr"], "Toolbox");
+        assert_eq!(vars["upsmib.12.1.0"], "1");
+        assert!(!vars.keys().any(|var| var.starts_with("upsmib.1.3.6")));
+    }
+}
@@ -1,10 +1,51 @@
-use std::{mem, ops::Not, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    ops::Not,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Local};
 
 use iced::{Length, Task, alignment::Vertical, task::sipper};
 use rfd::{AsyncFileDialog, FileHandle};
-use tokio::{fs, io::AsyncWriteExt, time::Instant};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
+use archive::ArchivePolicy;
+use checkpoint::{Checkpoint, Resumed};
+use filter::Filter;
+use lint::{Finding, Rule};
+use measure::{Measure, Mode, Profile};
+use report::{CsvOptions, Delimiter, Findings, Format, Paths, Report};
+use scan::{LinkPolicy, SkippedLink};
+use session::{Comparison, Session};
+use spool::{Spool, Spools};
+
+mod archive;
+mod checkpoint;
+pub mod cli;
+mod filter;
+mod hotspots;
+mod lint;
+mod measure;
+mod rename;
+mod rename_tool;
+mod report;
+mod scan;
+mod session;
+mod spool;
+mod table;
+mod watch;
+
+/// Findings beyond this are only in the export.
+const MAX_FINDINGS_SHOWN: usize = 1000;
+
 #[derive(Debug, Clone)]
 pub enum Message {
     SelectFolder,
@@ -13,28 +54,109 @@ pub enum Message {
     ScanComplete,
     Error(String),
     LimitChanged(String),
+    ModeChanged(Mode),
+    IncludeRootChanged(bool),
+    TargetChanged(String),
+    ProfileChanged(Profile),
+    ParallelismChanged(String),
+    LinkPolicyChanged(LinkPolicy),
+    ArchivePolicyChanged(ArchivePolicy),
+    SpoolToggled(bool),
+    ExcludeChanged(String),
+    IncludeChanged(String),
     StartScan,
+    ResumeScan,
+    Resumed(Result<Arc<Resumed>, String>),
+    Interrupted(Option<PathBuf>),
     ScanUpdate {
         now_scanned: u64,
         new_paths_over_limit: Vec<OverLimit>,
+        new_findings: Vec<Finding>,
+        new_skipped_links: Vec<SkippedLink>,
     },
-    ExportCsv,
-    CsvExportComplete(Result<String, String>),
+    StartWatching,
+    StopWatching,
+    WatchUpdate(Result<Vec<watch::Hit>, String>),
+    RuleFilterToggled(Rule, bool),
+    Table(table::Message),
+    Page(usize),
+    ShowHotspots(bool),
+    Hotspots(hotspots::Message),
+    RenameTool(rename_tool::Message),
+    ToggleRenameTool,
+    FormatChanged(Format),
+    DelimiterChanged(Delimiter),
+    BomToggled(bool),
+    Export,
+    ExportFindings,
+    ExportComplete(Result<String, String>),
+    SaveSession,
+    LoadSession,
+    SessionLoaded(Result<Arc<Session>, String>),
+    CompareSession,
+    CompareLoaded(Result<Arc<Session>, String>),
+    CloseComparison,
+    ExportComparison,
 }
 
 pub struct PathLengthChecker {
     selecting: bool,
     selected: Option<PathBuf>,
+    /// The folder the current results are from
+    scan_root: PathBuf,
+    scan_started: DateTime<Local>,
     scan_status: ScanStatus,
+    /// The folder of a scan that can be resumed
+    interrupted: Option<PathBuf>,
+    /// Empty when the results are spooled to disk
     paths_over_limit: Vec<OverLimit>,
+    /// Keep the results of the next scan on disk
+    spool_results: bool,
+    spool: Option<Spool>,
+    /// The page of the spool that is shown
+    page: usize,
+    page_rows: Vec<OverLimit>,
+    table: table::Table,
+    hotspots: hotspots::Hotspots,
+    /// Show the folder tree instead of the table
+    show_hotspots: bool,
     scanned: u64,
     limit_input: String,
     limit: usize,
     scan_limit: usize,
+    measure: Measure,
+    scan_measure: Measure,
+    target_input: String,
+    parallelism_input: String,
+    parallelism: usize,
+    links: LinkPolicy,
+    archives: ArchivePolicy,
+    exclude_input: String,
+    include_input: String,
+    filter: Filter,
+    filter_error: Option<String>,
+    scan_filter: Filter,
+    /// Only the first findings of every rule when spooled
+    findings: Vec<Finding>,
+    findings_spool: Option<Spool<Finding>>,
+    /// How many findings every rule has, including those only in the spool
+    finding_counts: HashMap<Rule, usize>,
+    /// Rules whose findings are shown and exported
+    rule_filter: Vec<Rule>,
+    skipped_links: Vec<SkippedLink>,
     errors: Vec<String>,
+    watching: Option<CancellationToken>,
+    /// Newest last
+    watch_hits: Vec<watch::Hit>,
+    rename_tool: rename_tool::RenameTool,
+    show_rename_tool: bool,
+    export_format: Format,
+    csv_options: CsvOptions,
     exporting: bool,
     export_message: Option<String>,
     export_success: bool,
+    /// Shown instead of the scan when set
+    comparison: Option<Comparison>,
 }
 
 enum ScanStatus {
@@ -80,10 +202,49 @@ impl ScanStatus {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EntryKind {
+    File,
+    Folder,
+    Link,
+}
+
+impl Display for EntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryKind::File => write!(f, "File"),
+            EntryKind::Folder => write!(f, "Folder"),
+            EntryKind::Link => write!(f, "Link"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OverLimit {
     path: String,
-    size: u64,
+    length: u64,
+    /// How `length` was measured
+    mode: Mode,
+    /// Where the path ends up in a migration simulation
+    target_path: Option<String>,
+    kind: EntryKind,
+    /// Size in bytes, only for files
+    file_size: Option<u64>,
+    modified: Option<DateTime<Local>>,
+    /// 1 for entries directly in the scanned folder
+    depth: usize,
+    /// The longest name in the measured path
+    longest_name: String,
+    longest_name_length: usize,
+    /// The archive an entry was found in, it only exists once extracted
+    #[serde(default)]
+    archive: Option<String>,
+}
+
+impl Default for PathLengthChecker {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PathLengthChecker {
@@ -91,16 +252,50 @@ impl PathLengthChecker {
         Self {
             selecting: false,
             selected: None,
+            scan_root: PathBuf::new(),
+            scan_started: Local::now(),
             scan_status: ScanStatus::WaitingForStart,
+            interrupted: None,
             paths_over_limit: Vec::new(),
+            spool_results: false,
+            spool: None,
+            page: 0,
+            page_rows: Vec::new(),
+            table: table::Table::new(),
+            hotspots: hotspots::Hotspots::new(),
+            show_hotspots: false,
             scanned: 0,
             limit_input: "240".to_string(),
             limit: 240,
             scan_limit: 240,
+            measure: Measure::default(),
+            scan_measure: Measure::default(),
+            target_input: String::new(),
+            parallelism_input: "8".to_string(),
+            parallelism: 8,
+            links: LinkPolicy::DontFollow,
+            archives: ArchivePolicy::Ignore,
+            exclude_input: String::new(),
+            include_input: String::new(),
+            filter: Filter::default(),
+            filter_error: None,
+            scan_filter: Filter::default(),
+            findings: Vec::new(),
+            findings_spool: None,
+            finding_counts: HashMap::new(),
+            rule_filter: Rule::ALL.to_vec(),
+            skipped_links: Vec::new(),
             errors: Vec::new(),
+            watching: None,
+            watch_hits: Vec::new(),
+            rename_tool: rename_tool::RenameTool::new(),
+            show_rename_tool: false,
+            export_format: Format::Csv,
+            csv_options: CsvOptions::default(),
             exporting: false,
             export_message: None,
             export_success: false,
+            comparison: None,
         }
     }
 
@@ -115,18 +310,23 @@ impl PathLengthChecker {
             }
             Message::SelectedFolder(selected) => {
                 self.selecting = false;
-                if let Some(selected) = selected {
-                    if let Some(selected) = Arc::into_inner(selected) {
-                        let selected: PathBuf = selected.path().into();
-                        self.selected = Some(selected.clone());
-                        self.scan_status = ScanStatus::WaitingForStart;
-                    }
+                if let Some(selected) = selected
+                    && let Some(selected) = Arc::into_inner(selected)
+                {
+                    let selected: PathBuf = selected.path().into();
+                    self.selected = Some(selected.clone());
+                    self.scan_status = ScanStatus::WaitingForStart;
                 }
                 Task::none()
             }
             Message::AbortScan | Message::ScanComplete => {
-                self.cancel_scan();
-                Task::none()
+                self.scan_status.cancel();
+                self.hotspots.build(
+                    &self.paths_over_limit,
+                    &self.scan_root,
+                    self.scan_measure.mode,
+                );
+                Self::find_interrupted()
             }
             Message::Error(err) => {
                 self.errors.push(err);
@@ -139,102 +339,352 @@ impl PathLengthChecker {
                 }
                 Task::none()
             }
+            Message::ModeChanged(mode) => {
+                self.measure.mode = mode;
+                Task::none()
+            }
+            Message::IncludeRootChanged(include_root) => {
+                self.measure.include_root = include_root;
+                Task::none()
+            }
+            Message::TargetChanged(target) => {
+                let trimmed = target.trim();
+                self.measure.target = (!trimmed.is_empty()).then(|| trimmed.to_string());
+                self.target_input = target;
+                Task::none()
+            }
+            Message::ProfileChanged(profile) => {
+                // A limit that was typed in is kept
+                if self.limit == self.measure.profile.limit() {
+                    self.limit = profile.limit();
+                    self.limit_input = self.limit.to_string();
+                }
+                self.measure.profile = profile;
+                Task::none()
+            }
+            Message::ParallelismChanged(parallelism) => {
+                if let Ok(parsed) = parallelism.parse::<usize>()
+                    && parsed > 0
+                {
+                    self.parallelism = parsed;
+                }
+                self.parallelism_input = parallelism;
+                Task::none()
+            }
+            Message::LinkPolicyChanged(links) => {
+                self.links = links;
+                Task::none()
+            }
+            Message::ArchivePolicyChanged(archives) => {
+                self.archives = archives;
+                Task::none()
+            }
+            Message::SpoolToggled(spool_results) => {
+                self.spool_results = spool_results;
+                Task::none()
+            }
+            Message::ExcludeChanged(exclude) => {
+                self.exclude_input = exclude;
+                self.parse_filter();
+                Task::none()
+            }
+            Message::IncludeChanged(include) => {
+                self.include_input = include;
+                self.parse_filter();
+                Task::none()
+            }
             Message::StartScan => {
-                if let Some(ref folder) = self.selected {
+                if let Some(folder) = self.selected.clone()
+                    && self.filter_error.is_none()
+                {
                     self.scan_status.cancel();
-                    self.paths_over_limit.clear();
+                    self.stop_watching();
+                    self.watch_hits.clear();
                     self.errors.clear();
+                    self.paths_over_limit.clear();
+                    self.spool = None;
+                    self.findings_spool = None;
+                    if self.spool_results {
+                        match Spools::create() {
+                            Ok(spools) => {
+                                self.spool = Some(spools.paths);
+                                self.findings_spool = Some(spools.findings);
+                            }
+                            Err(err) => self.errors.push(format!(
+                                "Failed to create temporary file, results are kept in memory: {}",
+                                err
+                            )),
+                        }
+                    }
+                    self.page = 0;
+                    self.page_rows.clear();
+                    self.table.clear();
+                    self.hotspots.clear();
+                    self.findings.clear();
+                    self.finding_counts.clear();
+                    self.skipped_links.clear();
                     self.scanned = 0;
                     self.export_message = None;
                     let token = CancellationToken::new();
                     self.scan_status = ScanStatus::Scanning(token.clone());
                     self.scan_limit = self.limit;
-                    self.start_scan(folder.clone(), self.limit, token)
+                    self.scan_root = folder.clone();
+                    self.scan_started = Local::now();
+                    self.scan_measure = self.measure.clone();
+                    self.scan_filter = self.filter.clone();
+                    let settings = scan::Settings {
+                        root: folder.clone(),
+                        limit: self.limit,
+                        measure: self.measure.clone(),
+                        parallelism: self.parallelism,
+                        links: self.links,
+                        archives: self.archives,
+                        filter: self.filter.clone(),
+                    };
+                    let checkpoint = match Checkpoint::create(
+                        &checkpoint_path(&folder),
+                        &settings,
+                        self.scan_started,
+                    ) {
+                        Ok(checkpoint) => Some(checkpoint),
+                        Err(err) => {
+                            self.errors.push(format!(
+                                "Failed to create checkpoint, scan can't be resumed: {}",
+                                err
+                            ));
+                            None
+                        }
+                    };
+                    self.interrupted = None;
+                    self.start_scan(settings, None, checkpoint, token)
                 } else {
                     Task::none()
                 }
             }
+            Message::ResumeScan => {
+                let Some(root) = self.interrupted.clone() else {
+                    return Task::none();
+                };
+                // The results found before go straight to the spools
+                let spools = self.spool_results.then(Spools::create).and_then(|spools| {
+                    spools
+                        .inspect_err(|err| {
+                            self.errors.push(format!(
+                                "Failed to create temporary file, results are kept in memory: {}",
+                                err
+                            ))
+                        })
+                        .ok()
+                });
+                Task::future(async move {
+                    let resumed = tokio::task::spawn_blocking(move || {
+                        checkpoint::load(&checkpoint_path(&root), spools)
+                    })
+                    .await
+                    .unwrap_or_else(|err| Err(err.to_string()));
+                    Message::Resumed(resumed.map(Arc::new))
+                })
+            }
+            Message::Interrupted(root) => {
+                // A scan that was started meanwhile looks again when it ends
+                if !self.scan_status.is_scanning() {
+                    self.interrupted = root;
+                }
+                Task::none()
+            }
+            Message::Resumed(result) => match result.map(Arc::into_inner) {
+                Ok(Some(resumed)) => self.resume(resumed),
+                Ok(None) => Task::none(),
+                Err(err) => {
+                    self.errors.push(err);
+                    Task::none()
+                }
+            },
             Message::ScanUpdate {
                 now_scanned,
                 new_paths_over_limit,
+                new_findings,
+                new_skipped_links,
             } => {
                 self.scanned = now_scanned;
-                self.paths_over_limit.extend(new_paths_over_limit);
+                self.add_paths(new_paths_over_limit);
+                self.add_findings(new_findings);
+                self.skipped_links.extend(new_skipped_links);
+                Task::none()
+            }
+            Message::StartWatching => {
+                self.stop_watching();
+                self.watch_hits.clear();
+                let token = CancellationToken::new();
+                self.watching = Some(token.clone());
+                let settings = scan::Settings {
+                    root: self.scan_root.clone(),
+                    limit: self.scan_limit,
+                    measure: self.scan_measure.clone(),
+                    parallelism: self.parallelism,
+                    links: LinkPolicy::DontFollow,
+                    archives: ArchivePolicy::Ignore,
+                    filter: self.scan_filter.clone(),
+                };
+                let sipper = sipper(move |mut sender| async move {
+                    let (hits, mut receiver) = mpsc::channel(16);
+                    let forward = async {
+                        while let Some(hits) = receiver.recv().await {
+                            sender.send(Message::WatchUpdate(hits)).await;
+                        }
+                    };
+                    tokio::join!(watch::run(settings, token, hits), forward);
+                });
+                Task::sip(sipper, |value| value, |_| Message::StopWatching)
+            }
+            Message::StopWatching => {
+                self.stop_watching();
+                Task::none()
+            }
+            Message::WatchUpdate(Ok(hits)) => {
+                self.add_paths(hits.iter().map(|hit| hit.path.clone()).collect());
+                if self.spool.is_none() {
+                    self.hotspots.build(
+                        &self.paths_over_limit,
+                        &self.scan_root,
+                        self.scan_measure.mode,
+                    );
+                }
+                self.watch_hits.extend(hits);
+                Task::none()
+            }
+            Message::WatchUpdate(Err(err)) => {
+                self.errors.push(err);
+                Task::none()
+            }
+            Message::RuleFilterToggled(rule, enabled) => {
+                self.rule_filter.retain(|filtered| *filtered != rule);
+                if enabled {
+                    self.rule_filter.push(rule);
+                }
+                Task::none()
+            }
+            Message::Table(message) => {
+                // Only the current page when spooled
+                let rows = if self.spool.is_some() {
+                    &self.page_rows
+                } else {
+                    &self.paths_over_limit
+                };
+                self.table.update(message, rows).map(Message::Table)
+            }
+            Message::Page(page) => {
+                self.page = page;
+                self.load_page();
                 Task::none()
             }
-            Message::ExportCsv => {
-                if self.paths_over_limit.is_empty() {
+            Message::ShowHotspots(show_hotspots) => {
+                self.show_hotspots = show_hotspots;
+                Task::none()
+            }
+            Message::Hotspots(message) => self.hotspots.update(message).map(Message::Hotspots),
+            Message::RenameTool(message) => self
+                .rename_tool
+                .update(
+                    message,
+                    rename_tool::Scan {
+                        paths: &self.paths_over_limit,
+                        root: &self.scan_root,
+                        measure: &self.scan_measure,
+                    },
+                )
+                .map(Message::RenameTool),
+            Message::ToggleRenameTool => {
+                self.show_rename_tool = !self.show_rename_tool;
+                Task::none()
+            }
+            Message::FormatChanged(format) => {
+                self.export_format = format;
+                Task::none()
+            }
+            Message::DelimiterChanged(delimiter) => {
+                self.csv_options.delimiter = delimiter;
+                Task::none()
+            }
+            Message::BomToggled(bom) => {
+                self.csv_options.bom = bom;
+                Task::none()
+            }
+            Message::Export => {
+                let paths = match &mut self.spool {
+                    Some(spool) => match spool.reader() {
+                        Ok(reader) => Paths::Spool(reader),
+                        Err(err) => {
+                            self.export_message =
+                                Some(format!("Failed to read temporary file: {}", err));
+                            self.export_success = false;
+                            return Task::none();
+                        }
+                    },
+                    None => Paths::Memory(self.paths_over_limit.clone()),
+                };
+                if paths.is_empty() {
                     Task::none()
                 } else {
                     self.exporting = true;
                     self.export_message = None;
-                    let paths_to_export = self.paths_over_limit.clone();
+                    let report = Report {
+                        root: self.scan_root.to_string_lossy().to_string(),
+                        limit: self.scan_limit,
+                        measure: self.scan_measure.clone(),
+                        timestamp: self.scan_started,
+                        scanned: self.scanned,
+                        paths,
+                        skipped_links: self.skipped_links.clone(),
+                        errors: self.errors.clone(),
+                        filter: self.scan_filter.clone(),
+                    };
+                    let format = self.export_format;
+                    let csv_options = self.csv_options;
                     Task::future(async move {
-                        let file_handle = AsyncFileDialog::new()
-                            .set_file_name("path_length_report.csv")
-                            .add_filter("CSV", &["csv"])
-                            .save_file()
-                            .await;
-
-                        if let Some(file_handle) = file_handle {
-                            let export_count = paths_to_export.len();
-                            let file_path = file_handle.path().to_path_buf();
-
-                            match tokio::fs::File::create(&file_path).await {
-                                Ok(mut file) => {
-                                    // Write CSV header
-                                    if let Err(e) = file.write_all(b"Length;Path\n").await {
-                                        return Message::CsvExportComplete(Err(format!(
-                                            "Failed to write CSV header: {}",
-                                            e
-                                        )));
-                                    }
-
-                                    // Write in chunks of 1000 lines
-                                    for chunk in paths_to_export.chunks(1000) {
-                                        let mut chunk_content = String::new();
-                                        for path in chunk {
-                                            chunk_content.push_str(&format!(
-                                                "{};\"{}\"\n",
-                                                path.size, path.path,
-                                            ));
-                                        }
-
-                                        if let Err(e) =
-                                            file.write_all(chunk_content.as_bytes()).await
-                                        {
-                                            return Message::CsvExportComplete(Err(format!(
-                                                "Failed to write CSV chunk: {}",
-                                                e
-                                            )));
-                                        }
-                                    }
-
-                                    if let Err(e) = file.flush().await {
-                                        return Message::CsvExportComplete(Err(format!(
-                                            "Failed to flush CSV file: {}",
-                                            e
-                                        )));
-                                    }
-
-                                    Message::CsvExportComplete(Ok(format!(
-                                        "Exported {} paths to {}",
-                                        export_count,
-                                        file_path.display()
-                                    )))
-                                }
-                                Err(e) => Message::CsvExportComplete(Err(format!(
-                                    "Failed to create CSV file: {}",
-                                    e
-                                ))),
-                            }
-                        } else {
-                            Message::CsvExportComplete(Err("Export cancelled".to_string()))
-                        }
+                        Message::ExportComplete(
+                            save_export(
+                                "path_length_report",
+                                format,
+                                report.paths.len(),
+                                move |out| report.write(format, csv_options, out),
+                            )
+                            .await,
+                        )
                     })
                 }
             }
-            Message::CsvExportComplete(result) => {
+            Message::ExportFindings => {
+                let entries = self.filtered_finding_count();
+                if entries == 0 {
+                    return Task::none();
+                }
+                let findings = match &mut self.findings_spool {
+                    Some(spool) => match spool.reader() {
+                        Ok(reader) => Findings::Spool(reader),
+                        Err(err) => {
+                            self.export_message =
+                                Some(format!("Failed to read temporary file: {}", err));
+                            self.export_success = false;
+                            return Task::none();
+                        }
+                    },
+                    None => Findings::Memory(self.filtered_findings().cloned().collect()),
+                };
+                self.exporting = true;
+                self.export_message = None;
+                let rules = self.rule_filter.clone();
+                let csv_options = self.csv_options;
+                Task::future(async move {
+                    Message::ExportComplete(
+                        save_export("name_findings", Format::Csv, entries, move |out| {
+                            report::findings_csv(&findings, &rules, csv_options, out)
+                        })
+                        .await,
+                    )
+                })
+            }
+            Message::ExportComplete(result) => {
                 self.exporting = false;
                 match result {
                     Ok(success_msg) => {
@@ -249,12 +699,328 @@ impl PathLengthChecker {
                     }
                 }
             }
+            Message::SaveSession => {
+                self.exporting = true;
+                self.export_message = None;
+                let session = self.session();
+                Task::future(async move {
+                    let result = match session.to_json() {
+                        Ok(contents) => {
+                            save_export(
+                                "path_length_session",
+                                Format::Json,
+                                session.paths.len(),
+                                move |out| out.write_all(contents.as_bytes()),
+                            )
+                            .await
+                        }
+                        Err(err) => Err(err),
+                    };
+                    Message::ExportComplete(result)
+                })
+            }
+            Message::LoadSession => {
+                Task::future(async { Message::SessionLoaded(load_session().await) })
+            }
+            Message::SessionLoaded(result) => {
+                match result.map(Arc::unwrap_or_clone) {
+                    Ok(session) => self.restore(session),
+                    Err(err) => {
+                        self.export_message = Some(err);
+                        self.export_success = false;
+                    }
+                }
+                Task::none()
+            }
+            Message::CompareSession => {
+                Task::future(async { Message::CompareLoaded(load_session().await) })
+            }
+            Message::CompareLoaded(result) => {
+                match result {
+                    Ok(before) => {
+                        self.comparison = Some(Comparison::new(&before, &self.session()));
+                    }
+                    Err(err) => {
+                        self.export_message = Some(err);
+                        self.export_success = false;
+                    }
+                }
+                Task::none()
+            }
+            Message::CloseComparison => {
+                self.comparison = None;
+                Task::none()
+            }
+            Message::ExportComparison => {
+                let Some(comparison) = &self.comparison else {
+                    return Task::none();
+                };
+                self.exporting = true;
+                self.export_message = None;
+                let fixed = comparison.fixed.iter().map(|path| {
+                    [
+                        "Fixed".to_string(),
+                        path.length.to_string(),
+                        String::new(),
+                        path.path.clone(),
+                    ]
+                });
+                let new = comparison.new.iter().map(|path| {
+                    [
+                        "New".to_string(),
+                        String::new(),
+                        path.length.to_string(),
+                        path.path.clone(),
+                    ]
+                });
+                let remaining = comparison.remaining.iter().map(|remaining| {
+                    [
+                        "Remaining".to_string(),
+                        remaining.before.to_string(),
+                        remaining.path.length.to_string(),
+                        remaining.path.path.clone(),
+                    ]
+                });
+                let entries =
+                    comparison.fixed.len() + comparison.new.len() + comparison.remaining.len();
+                let contents = report::csv(
+                    &["Status", "Length Before", "Length After", "Path"],
+                    fixed.chain(new).chain(remaining),
+                    self.csv_options,
+                );
+                Task::future(async move {
+                    Message::ExportComplete(
+                        save_export("path_length_comparison", Format::Csv, entries, move |out| {
+                            out.write_all(contents.as_bytes())
+                        })
+                        .await,
+                    )
+                })
+            }
+        }
+    }
+
+    fn add_paths(&mut self, paths: Vec<OverLimit>) {
+        if let Some(spool) = &mut self.spool {
+            if let Err(err) = spool.extend(paths) {
+                self.errors
+                    .push(format!("Failed to write temporary file: {}", err));
+            }
+            // New rows only show up on the last page
+            if self.page_rows.len() < spool::PAGE_SIZE {
+                self.load_page();
+            }
+        } else {
+            self.paths_over_limit.extend(paths);
+            self.table.extend(&self.paths_over_limit);
+        }
+    }
+
+    fn add_findings(&mut self, findings: Vec<Finding>) {
+        if let Some(spool) = &mut self.findings_spool
+            && let Err(err) = spool.extend(findings.iter().cloned())
+        {
+            self.errors
+                .push(format!("Failed to write temporary file: {}", err));
+        }
+        for finding in findings {
+            let count = self.finding_counts.entry(finding.rule).or_default();
+            *count += 1;
+            if self.findings_spool.is_none() || *count <= MAX_FINDINGS_SHOWN {
+                self.findings.push(finding);
+            }
+        }
+    }
+
+    /// Count the findings of a resumed scan and keep the first ones of every rule to show.
+    fn load_findings(&mut self, mut spool: Spool<Finding>) {
+        let (counts, findings) = (&mut self.finding_counts, &mut self.findings);
+        let read = spool.reader().and_then(|reader| {
+            reader.try_for_each(|finding| {
+                let count = counts.entry(finding.rule).or_default();
+                *count += 1;
+                if *count <= MAX_FINDINGS_SHOWN {
+                    findings.push(finding.clone());
+                }
+                Ok(())
+            })
+        });
+        if let Err(err) = read {
+            self.errors
+                .push(format!("Failed to read temporary file: {}", err));
+        }
+        self.findings_spool = Some(spool);
+    }
+
+    fn hits(&self) -> usize {
+        match &self.spool {
+            Some(spool) => spool.len(),
+            None => self.paths_over_limit.len(),
+        }
+    }
+
+    fn load_page(&mut self) {
+        let Some(spool) = &mut self.spool else {
+            return;
+        };
+        match spool.page(self.page) {
+            Ok(rows) => self.page_rows = rows,
+            Err(err) => self
+                .errors
+                .push(format!("Failed to read temporary file: {}", err)),
+        }
+        self.table.clear();
+        self.table.extend(&self.page_rows);
+    }
+
+    /// The current results, to save or compare.
+    fn session(&self) -> Session {
+        Session {
+            version: session::VERSION,
+            root: self.scan_root.to_string_lossy().to_string(),
+            limit: self.scan_limit,
+            measure: self.scan_measure.clone(),
+            timestamp: self.scan_started,
+            scanned: self.scanned,
+            exclude: self
+                .scan_filter
+                .exclude
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            include: self
+                .scan_filter
+                .include
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            paths: self.paths_over_limit.clone(),
+            findings: self.findings.clone(),
+            skipped_links: self.skipped_links.clone(),
+            errors: self.errors.clone(),
+        }
+    }
+
+    /// Show the results of an interrupted scan and continue it.
+    fn resume(&mut self, resumed: Resumed) -> Task<Message> {
+        let checkpoint = match Checkpoint::append(&checkpoint_path(&resumed.settings.root)) {
+            Ok(checkpoint) => checkpoint,
+            Err(err) => {
+                self.errors
+                    .push(format!("Failed to open checkpoint: {}", err));
+                return Task::none();
+            }
+        };
+
+        let settings = resumed.settings;
+        let results = resumed.results;
+        self.restore(Session {
+            version: session::VERSION,
+            root: settings.root.to_string_lossy().to_string(),
+            limit: settings.limit,
+            measure: settings.measure.clone(),
+            timestamp: resumed.started,
+            scanned: results.scanned,
+            exclude: settings
+                .filter
+                .exclude
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            include: settings
+                .filter
+                .include
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            paths: results.over_limit,
+            findings: results.findings,
+            skipped_links: results.skipped_links,
+            errors: results.errors,
+        });
+        self.hotspots.clear();
+        if let Some(spools) = resumed.spools {
+            self.spool = Some(spools.paths);
+            self.page = 0;
+            self.load_page();
+            self.load_findings(spools.findings);
         }
+        self.parallelism = settings.parallelism;
+        self.parallelism_input = settings.parallelism.to_string();
+        self.links = settings.links;
+        self.archives = settings.archives;
+        self.interrupted = None;
+
+        let token = CancellationToken::new();
+        self.scan_status = ScanStatus::Scanning(token.clone());
+        self.start_scan(settings, Some(resumed.state), Some(checkpoint), token)
+    }
+
+    /// Show a saved session as if it was just scanned, with its settings for the next scan.
+    fn restore(&mut self, session: Session) {
+        self.scan_status.cancel();
+        self.stop_watching();
+        self.watch_hits.clear();
+        self.scan_status = ScanStatus::Done;
+        self.comparison = None;
+        self.export_message = None;
+
+        let root = PathBuf::from(&session.root);
+        self.selected = Some(root.clone());
+        self.scan_root = root;
+        self.scan_started = session.timestamp;
+        self.scanned = session.scanned;
+        self.limit = session.limit;
+        self.limit_input = session.limit.to_string();
+        self.scan_limit = session.limit;
+        self.target_input = session.measure.target.clone().unwrap_or_default();
+        self.measure = session.measure.clone();
+        self.scan_measure = session.measure;
+        self.exclude_input = join_patterns(&session.exclude);
+        self.include_input = join_patterns(&session.include);
+        self.parse_filter();
+        self.scan_filter = self.filter.clone();
+
+        self.paths_over_limit = session.paths;
+        self.spool = None;
+        self.page_rows.clear();
+        self.findings.clear();
+        self.findings_spool = None;
+        self.finding_counts.clear();
+        self.add_findings(session.findings);
+        self.skipped_links = session.skipped_links;
+        self.errors = session.errors;
+        self.table.clear();
+        self.table.extend(&self.paths_over_limit);
+        self.hotspots.build(
+            &self.paths_over_limit,
+            &self.scan_root,
+            self.scan_measure.mode,
+        );
     }
 
     pub fn view(&self) -> iced::Element<'_, Message> {
         use iced::widget::{column, *};
 
+        if self.show_rename_tool {
+            return column![
+                button(text("Back")).on_press(Message::ToggleRenameTool),
+                self.rename_tool
+                    .view(
+                        self.scan_limit,
+                        self.scan_status.is_done() && !self.paths_over_limit.is_empty()
+                    )
+                    .map(Message::RenameTool),
+            ]
+            .spacing(20)
+            .padding(20)
+            .into();
+        }
+
+        if let Some(comparison) = &self.comparison {
+            return self.view_comparison(comparison);
+        }
+
         let main_controls = column![
             row![
                 button(text("Select Folder")).on_press_maybe(if self.selecting {
@@ -276,12 +1042,87 @@ impl PathLengthChecker {
                     .on_input(Message::LimitChanged)
                     .on_submit(Message::StartScan)
                     .width(Length::Fixed(100.0)),
+                pick_list(Mode::ALL, Some(self.measure.mode), Message::ModeChanged),
+                checkbox(self.measure.include_root)
+                    .label("Include root folder")
+                    .on_toggle_maybe(
+                        (self.measure.target.is_none() && self.measure.profile == Profile::Windows)
+                            .then_some(Message::IncludeRootChanged)
+                    ),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Parallel Folder Reads:"),
+                text_input("", &self.parallelism_input)
+                    .on_input(Message::ParallelismChanged)
+                    .width(Length::Fixed(100.0)),
+                pick_list(
+                    LinkPolicy::ALL,
+                    Some(self.links),
+                    Message::LinkPolicyChanged
+                ),
+                pick_list(
+                    ArchivePolicy::ALL,
+                    Some(self.archives),
+                    Message::ArchivePolicyChanged
+                ),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                pick_list(
+                    Profile::ALL,
+                    Some(self.measure.profile),
+                    Message::ProfileChanged
+                ),
+                match self.measure.profile {
+                    Profile::Windows => row![
+                        text("Migration Target:"),
+                        text_input(r"e.g. \\newserver\share\Archiv", &self.target_input)
+                            .on_input(Message::TargetChanged)
+                            .on_submit(Message::StartScan),
+                    ],
+                    Profile::SharePoint => row![
+                        text("Library URL:"),
+                        text_input(
+                            "e.g. https://contoso.sharepoint.com/sites/Team/Shared Documents",
+                            &self.target_input
+                        )
+                        .on_input(Message::TargetChanged)
+                        .on_submit(Message::StartScan),
+                    ],
+                }
+                .spacing(10)
+                .align_y(Vertical::Center),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Exclude:"),
+                text_input(
+                    r".git, node_modules, ~snapshot, $RECYCLE.BIN, re:^Archiv/\d{4}$",
+                    &self.exclude_input
+                )
+                .on_input(Message::ExcludeChanged),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Only check:"),
+                text_input("*.docx, *.xlsx", &self.include_input).on_input(Message::IncludeChanged),
             ]
             .spacing(10)
             .align_y(Vertical::Center),
+            self.filter_error
+                .as_ref()
+                .map(|err| text(err).color(iced::Color::from_rgb(0.8, 0.2, 0.2))),
             row![
                 button(text("Start Scan")).on_press_maybe(
-                    if self.selected.is_some() && !self.scan_status.is_scanning() {
+                    if self.selected.is_some()
+                        && !self.scan_status.is_scanning()
+                        && self.filter_error.is_none()
+                    {
                         Some(Message::StartScan)
                     } else {
                         None
@@ -292,18 +1133,75 @@ impl PathLengthChecker {
                 } else {
                     None
                 }),
-                button(text("Export CSV")).on_press_maybe(
-                    if !self.paths_over_limit.is_empty()
-                        && !self.exporting
-                        && self.scan_status.is_done()
-                    {
-                        Some(Message::ExportCsv)
+                if self.watching.is_some() {
+                    button(text("Stop Watching")).on_press(Message::StopWatching)
+                } else {
+                    button(text("Watch for New Paths")).on_press_maybe(
+                        self.scan_status.is_done().then_some(Message::StartWatching),
+                    )
+                },
+                button(text("Shorten Paths"))
+                    .on_press_maybe(self.spool.is_none().then_some(Message::ToggleRenameTool)),
+                self.interrupted.as_ref().map(|root| {
+                    button(text(format!("Resume Scan of {}", root.display()))).on_press_maybe(
+                        self.scan_status
+                            .is_scanning()
+                            .not()
+                            .then_some(Message::ResumeScan),
+                    )
+                }),
+            ]
+            .spacing(10),
+            row![
+                pick_list(
+                    Format::ALL,
+                    Some(self.export_format),
+                    Message::FormatChanged
+                ),
+                text("CSV Delimiter:"),
+                pick_list(
+                    Delimiter::ALL,
+                    Some(self.csv_options.delimiter),
+                    Message::DelimiterChanged
+                ),
+                checkbox(self.csv_options.bom)
+                    .label("Byte order mark for Excel")
+                    .on_toggle(Message::BomToggled),
+                button(text("Export")).on_press_maybe(
+                    if self.hits() > 0 && !self.exporting && self.scan_status.is_done() {
+                        Some(Message::Export)
                     } else {
                         None
                     }
                 ),
             ]
-            .spacing(10),
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                button(text("Save Session")).on_press_maybe(
+                    (self.scan_status.is_done() && self.spool.is_none() && !self.exporting)
+                        .then_some(Message::SaveSession)
+                ),
+                button(text("Load Session")).on_press_maybe(
+                    self.scan_status
+                        .is_scanning()
+                        .not()
+                        .then_some(Message::LoadSession)
+                ),
+                button(text("Compare with Saved Session")).on_press_maybe(
+                    (self.scan_status.is_done() && self.spool.is_none())
+                        .then_some(Message::CompareSession)
+                ),
+                checkbox(self.spool_results)
+                    .label("Keep results on disk for huge scans")
+                    .on_toggle(Message::SpoolToggled),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            // They need all results in memory
+            (self.spool_results || self.spool.is_some()).then(|| text(
+                "With results on disk, Hotspots, Shorten Paths and Save or Compare Session are not available"
+            )),
         ]
         .spacing(10);
 
@@ -320,19 +1218,100 @@ impl PathLengthChecker {
             },
             if !self.scan_status.has_results() {
                 None
-            } else if self.paths_over_limit.is_empty() {
+            } else if self.hits() == 0 {
                 Some(text("No paths over limit found"))
+            } else if let Some(target) = &self.scan_measure.target {
+                Some(
+                    text(format!(
+                        "{} paths will be over the limit ({} {}) after moving to {}",
+                        self.hits(),
+                        self.scan_limit,
+                        self.scan_measure.mode,
+                        target,
+                    ))
+                    .size(18),
+                )
             } else {
                 Some(
                     text(format!(
-                        "Found {} paths over limit ({})",
-                        self.paths_over_limit.len(),
-                        self.scan_limit
+                        "Found {} paths over limit ({} {})",
+                        self.hits(),
+                        self.scan_limit,
+                        self.scan_measure,
                     ))
                     .size(18),
                 )
             },
-            self.exporting.then(|| text("Exporting to CSV...").size(16)),
+            self.spool
+                .as_ref()
+                .filter(|spool| !spool.is_empty())
+                .map(|spool| {
+                    column![
+                        row![
+                            button(text("Previous")).on_press_maybe(
+                                (self.page > 0).then(|| Message::Page(self.page - 1))
+                            ),
+                            text(format!("Page {} of {}", self.page + 1, spool.pages())),
+                            button(text("Next")).on_press_maybe(
+                                (self.page + 1 < spool.pages())
+                                    .then(|| Message::Page(self.page + 1))
+                            ),
+                        ]
+                        .spacing(10)
+                        .align_y(Vertical::Center),
+                        self.table
+                            .view(&self.page_rows, self.scan_limit, true)
+                            .map(Message::Table),
+                    ]
+                    .spacing(10)
+                }),
+            self.paths_over_limit.is_empty().not().then(|| {
+                column![
+                    row![
+                        button(text("List")).on_press_maybe(
+                            self.show_hotspots.then_some(Message::ShowHotspots(false))
+                        ),
+                        button(text("Hotspots")).on_press_maybe(
+                            self.show_hotspots
+                                .not()
+                                .then_some(Message::ShowHotspots(true))
+                        ),
+                    ]
+                    .spacing(10),
+                    if self.show_hotspots {
+                        self.hotspots.view(self.scan_limit).map(Message::Hotspots)
+                    } else {
+                        self.table
+                            .view(&self.paths_over_limit, self.scan_limit, false)
+                            .map(Message::Table)
+                    },
+                ]
+                .spacing(10)
+            }),
+            (self.watching.is_some() || !self.watch_hits.is_empty()).then(|| self.view_watch()),
+            self.findings.is_empty().not().then(|| self.view_findings()),
+            self.skipped_links.is_empty().not().then(|| {
+                column![
+                    text(format!("Skipped Links ({})", self.skipped_links.len())).size(18),
+                    scrollable(column(
+                        self.skipped_links
+                            .iter()
+                            .take(MAX_FINDINGS_SHOWN)
+                            .map(|link| {
+                                row![
+                                    text(&link.path).width(Length::FillPortion(1)),
+                                    text(&link.reason).width(Length::FillPortion(1)),
+                                ]
+                                .spacing(10)
+                                .into()
+                            })
+                    ))
+                    .height(Length::Fixed(150.0))
+                    .width(Length::Fill)
+                ]
+                .spacing(10)
+            }),
+            self.exporting.then(|| text("Exporting...").size(16)),
             self.export_message.as_ref().map(|message| {
                 if self.export_success {
                     text(message)
@@ -360,93 +1339,351 @@ impl PathLengthChecker {
         .into()
     }
 
+    fn view_comparison<'a>(&'a self, comparison: &'a Comparison) -> iced::Element<'a, Message> {
+        use iced::widget::{column, *};
+
+        fn section<'a>(
+            title: String,
+            rows: impl Iterator<Item = (String, &'a str)>,
+        ) -> Column<'a, Message> {
+            column![
+                text(title).size(18),
+                scrollable(column(rows.take(MAX_FINDINGS_SHOWN).map(
+                    |(length, path)| {
+                        row![text(length).width(Length::Fixed(100.0)), text(path)]
+                            .spacing(10)
+                            .into()
+                    }
+                )))
+                .height(Length::Fixed(150.0))
+                .width(Length::Fill),
+            ]
+            .spacing(10)
+        }
+
+        column![
+            row![
+                button(text("Back")).on_press(Message::CloseComparison),
+                button(text("Export Comparison"))
+                    .on_press_maybe(self.exporting.not().then_some(Message::ExportComparison)),
+            ]
+            .spacing(10),
+            text(format!(
+                "Scan of {} compared with {}",
+                comparison.after.format("%Y-%m-%d %H:%M"),
+                comparison.before.format("%Y-%m-%d %H:%M"),
+            ))
+            .size(18),
+            comparison
+                .warning
+                .as_ref()
+                .map(|warning| text(warning).color(iced::Color::from_rgb(0.8, 0.2, 0.2))),
+            section(
+                format!("Fixed ({})", comparison.fixed.len()),
+                comparison
+                    .fixed
+                    .iter()
+                    .map(|path| (path.length.to_string(), path.path.as_str())),
+            ),
+            section(
+                format!("New ({})", comparison.new.len()),
+                comparison
+                    .new
+                    .iter()
+                    .map(|path| (path.length.to_string(), path.path.as_str())),
+            ),
+            section(
+                format!("Remaining ({})", comparison.remaining.len()),
+                comparison.remaining.iter().map(|remaining| {
+                    (
+                        format!("{} → {}", remaining.before, remaining.path.length),
+                        remaining.path.path.as_str(),
+                    )
+                }),
+            ),
+            self.export_message.as_ref().map(|message| {
+                text(message).size(16).color(if self.export_success {
+                    iced::Color::from_rgb(0.0, 0.6, 0.0)
+                } else {
+                    iced::Color::from_rgb(0.8, 0.2, 0.2)
+                })
+            }),
+        ]
+        .spacing(20)
+        .padding(20)
+        .into()
+    }
+
+    fn view_watch(&self) -> iced::Element<'_, Message> {
+        use iced::widget::{column, *};
+
+        column![
+            text(if self.watching.is_some() {
+                format!(
+                    "Watching {} - {} new paths over the limit",
+                    self.scan_root.display(),
+                    self.watch_hits.len()
+                )
+            } else {
+                format!(
+                    "Stopped watching - {} new paths over the limit",
+                    self.watch_hits.len()
+                )
+            })
+            .size(18)
+            .color_maybe(
+                self.watch_hits
+                    .is_empty()
+                    .not()
+                    .then(|| iced::Color::from_rgb(0.8, 0.2, 0.2))
+            ),
+            scrollable(column(
+                self.watch_hits
+                    .iter()
+                    .rev()
+                    .take(MAX_FINDINGS_SHOWN)
+                    .map(|hit| {
+                        row![
+                            text(hit.detected.format("%Y-%m-%d %H:%M:%S").to_string())
+                                .width(Length::Fixed(160.0)),
+                            text(hit.change.to_string()).width(Length::Fixed(80.0)),
+                            text(hit.path.length).width(Length::Fixed(60.0)),
+                            text(&hit.path.path),
+                        ]
+                        .spacing(10)
+                        .into()
+                    })
+            ))
+            .height(Length::Fixed(150.0))
+            .width(Length::Fill),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn view_findings(&self) -> iced::Element<'_, Message> {
+        use iced::widget::{column, *};
+
+        let shown: Vec<&Finding> = self.filtered_findings().take(MAX_FINDINGS_SHOWN).collect();
+        let filtered = self.filtered_finding_count();
+
+        column![
+            text(format!(
+                "Found {} names {} can't handle",
+                self.finding_counts.values().sum::<usize>(),
+                // The profile the findings were checked with
+                match self.scan_measure.profile {
+                    Profile::Windows => "Windows",
+                    Profile::SharePoint => "SharePoint",
+                }
+            ))
+            .size(18),
+            row(lint::Rule::ALL.into_iter().map(|rule| {
+                let count = self.finding_counts.get(&rule).copied().unwrap_or_default();
+                checkbox(self.rule_filter.contains(&rule))
+                    .label(format!("{} ({})", rule, count))
+                    .on_toggle(move |enabled| Message::RuleFilterToggled(rule, enabled))
+                    .into()
+            }))
+            .spacing(20),
+            button(text("Export Findings")).on_press_maybe(
+                (!shown.is_empty() && !self.exporting && self.scan_status.is_done())
+                    .then_some(Message::ExportFindings)
+            ),
+            scrollable(
+                column(shown.iter().map(|finding| {
+                    row![
+                        text(finding.rule.to_string()).width(180),
+                        text(&finding.path).width(Length::Fill),
+                        text(&finding.detail).width(250),
+                    ]
+                    .spacing(10)
+                    .into()
+                }))
+                .push((filtered > shown.len()).then(|| text(format!(
+                    "... and {} more, export them to see all",
+                    filtered - shown.len()
+                ))))
+                .spacing(5)
+            )
+            .height(Length::Fixed(300.0))
+            .width(Length::Fill),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn parse_filter(&mut self) {
+        let parsed = Filter::parse_list(&self.exclude_input).and_then(|exclude| {
+            Ok(Filter {
+                exclude,
+                include: Filter::parse_list(&self.include_input)?,
+            })
+        });
+        match parsed {
+            Ok(filter) => {
+                self.filter = filter;
+                self.filter_error = None;
+            }
+            Err(err) => self.filter_error = Some(err),
+        }
+    }
+
+    fn filtered_finding_count(&self) -> usize {
+        self.rule_filter
+            .iter()
+            .filter_map(|rule| self.finding_counts.get(rule))
+            .sum()
+    }
+
+    fn filtered_findings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| self.rule_filter.contains(&finding.rule))
+    }
+
     fn start_scan(
         &mut self,
-        root: PathBuf,
-        limit: usize,
+        settings: scan::Settings,
+        resume: Option<checkpoint::State>,
+        checkpoint: Option<Checkpoint>,
         token: CancellationToken,
     ) -> Task<Message> {
         let sipper = sipper(move |mut sender| async move {
-            let mut stack = vec![root];
-
-            let mut scanned: u64 = 0;
-            let mut over_limit: Vec<OverLimit> = Vec::new();
-            let mut last_update = Instant::now();
-
-            token
-                .run_until_cancelled(async move {
-                    while let Some(path) = stack.pop() {
-                        match fs::read_dir(&path).await {
-                            Ok(mut entries) => {
-                                while let Ok(Some(entry)) = entries.next_entry().await {
-                                    let entry_path = entry.path();
-                                    let path_str = entry_path.to_string_lossy().to_string();
-                                    let path_length = path_str.len();
-
-                                    if path_length > limit {
-                                        over_limit.push(OverLimit {
-                                            path: path_str,
-                                            size: path_length as u64,
-                                        });
-                                    }
-
-                                    match entry.metadata().await {
-                                        Ok(metadata) => {
-                                            if metadata.is_dir() {
-                                                stack.push(entry_path);
-                                            }
-                                        }
-                                        Err(err) => {
-                                            sender
-                                                .send(Message::Error(format!(
-                                                    "Error reading metadata for {}: {}",
-                                                    entry_path.display(),
-                                                    err
-                                                )))
-                                                .await;
-                                        }
-                                    }
-
-                                    scanned += 1;
-
-                                    let now = Instant::now();
-                                    if now - last_update > Duration::from_millis(100) {
-                                        sender
-                                            .send(Message::ScanUpdate {
-                                                now_scanned: scanned,
-                                                new_paths_over_limit: mem::take(&mut over_limit),
-                                            })
-                                            .await;
-                                        last_update = now;
-                                    }
-                                }
-                            }
-                            Err(err) => {
-                                sender
-                                    .send(Message::Error(format!(
-                                        "Error reading directory {}: {}",
-                                        path.display(),
-                                        err
-                                    )))
-                                    .await;
-                            }
-                        }
-                    }
+            let (updates, mut receiver) = mpsc::channel::<scan::Update>(16);
 
+            let forward = async {
+                while let Some(update) = receiver.recv().await {
+                    for error in update.errors {
+                        sender.send(Message::Error(error)).await;
+                    }
                     sender
                         .send(Message::ScanUpdate {
-                            now_scanned: scanned,
-                            new_paths_over_limit: mem::take(&mut over_limit),
+                            now_scanned: update.scanned,
+                            new_paths_over_limit: update.over_limit,
+                            new_findings: update.findings,
+                            new_skipped_links: update.skipped_links,
                         })
                         .await;
-                })
-                .await;
+                }
+            };
+
+            tokio::join!(
+                scan::run(settings, resume, checkpoint, token, updates),
+                forward
+            );
         });
 
         Task::sip(sipper, |value| value, |_| Message::ScanComplete)
     }
 
+    /// Look for a scan that can be resumed, without blocking the UI on the temp folder.
+    pub(crate) fn find_interrupted() -> Task<Message> {
+        Task::future(async {
+            let root = tokio::task::spawn_blocking(interrupted_scan)
+                .await
+                .ok()
+                .flatten();
+            Message::Interrupted(root)
+        })
+    }
+
     pub(crate) fn cancel_scan(&mut self) {
         self.scan_status.cancel();
+        self.stop_watching();
     }
+
+    fn stop_watching(&mut self) {
+        if let Some(token) = self.watching.take() {
+            token.cancel();
+        }
+    }
+}
+
+const CHECKPOINT_PREFIX: &str = "toolbox-path-length-checkpoint-";
+
+/// Every folder has its own checkpoint, so windows scanning different folders don't overwrite
+/// each other's.
+fn checkpoint_path(root: &Path) -> PathBuf {
+    let hash = Sha1::digest(root.to_string_lossy().as_bytes());
+    let name: String = hash[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    std::env::temp_dir().join(format!("{}{}.jsonl", CHECKPOINT_PREFIX, name))
+}
+
+/// The folder of the most recently interrupted scan.
+fn interrupted_scan() -> Option<PathBuf> {
+    fs::read_dir(std::env::temp_dir())
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // The state next to it has the same prefix
+            name.starts_with(CHECKPOINT_PREFIX) && name.ends_with(".jsonl")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .and_then(|(_, path)| checkpoint::root(&path))
+}
+
+/// Ask for a saved session and read it.
+async fn load_session() -> Result<Arc<Session>, String> {
+    let Some(file_handle) = AsyncFileDialog::new()
+        .add_filter("Scan Session", &["json"])
+        .pick_file()
+        .await
+    else {
+        return Err("Loading cancelled".to_string());
+    };
+
+    let json = tokio::fs::read_to_string(file_handle.path())
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", file_handle.path().display(), e))?;
+    Session::from_json(&json).map(Arc::new)
+}
+
+/// The inverse of `Filter::parse_list`.
+fn join_patterns(patterns: &[String]) -> String {
+    patterns
+        .iter()
+        .map(|pattern| pattern.replace(',', "\\,"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Ask where to save and write the export there.
+async fn save_export(
+    file_name: &str,
+    format: Format,
+    entries: usize,
+    write: impl FnOnce(&mut dyn Write) -> std::io::Result<()> + Send + 'static,
+) -> Result<String, String> {
+    let Some(file_handle) = AsyncFileDialog::new()
+        .set_file_name(format!("{}.{}", file_name, format.extension()))
+        .add_filter(format.to_string(), &[format.extension()])
+        .save_file()
+        .await
+    else {
+        return Err("Export cancelled".to_string());
+    };
+
+    let file_path = file_handle.path().to_path_buf();
+    let path = file_path.clone();
+    // Spooled reports are read from disk while writing
+    tokio::task::spawn_blocking(move || {
+        let mut file = BufWriter::new(File::create(&path)?);
+        write(&mut file)?;
+        file.flush()
+    })
+    .await
+    .map_err(|e| format!("Failed to write {} file: {}", format, e))?
+    .map_err(|e| format!("Failed to write {} file: {}", format, e))?;
+
+    Ok(format!(
+        "Exported {} entries to {}",
+        entries,
+        file_path.display()
+    ))
 }
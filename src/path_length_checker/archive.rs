@@ -0,0 +1,109 @@
+use std::{fmt::Display, fs::File, path::Path};
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use super::{EntryKind, OverLimit, scan};
+
+/// Office and OpenDocument files are ZIP archives as well.
+const OFFICE_EXTENSIONS: &[&str] = &[
+    "docx", "docm", "dotx", "xlsx", "xlsm", "xltx", "pptx", "pptm", "potx", "vsdx", "odt", "ods",
+    "odp", "odg",
+];
+
+/// Which archives are opened to predict the paths of their entries after extracting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchivePolicy {
+    #[default]
+    Ignore,
+    Zip,
+    /// ZIP archives and files like `.docx` that are ZIP archives inside
+    ZipAndOffice,
+}
+
+impl ArchivePolicy {
+    pub const ALL: [ArchivePolicy; 3] = [
+        ArchivePolicy::Ignore,
+        ArchivePolicy::Zip,
+        ArchivePolicy::ZipAndOffice,
+    ];
+
+    pub fn opens(&self, name: &str) -> bool {
+        let Some((_, extension)) = name.rsplit_once('.') else {
+            return false;
+        };
+        let extension = extension.to_lowercase();
+        match self {
+            ArchivePolicy::Ignore => false,
+            ArchivePolicy::Zip => extension == "zip",
+            ArchivePolicy::ZipAndOffice => {
+                extension == "zip" || OFFICE_EXTENSIONS.contains(&extension.as_str())
+            }
+        }
+    }
+}
+
+impl Display for ArchivePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchivePolicy::Ignore => write!(f, "Don't open archives"),
+            ArchivePolicy::Zip => write!(f, "Check inside ZIP archives"),
+            ArchivePolicy::ZipAndOffice => write!(f, "Check inside ZIP and Office files"),
+        }
+    }
+}
+
+/// The entries of an archive that would be over the limit once extracted.
+///
+/// Like "Extract All" in Explorer, the entries end up in a folder named after the archive
+/// next to it. Only the directory of the archive is read, nothing is unpacked.
+pub fn check(archive: &Path, settings: &scan::Settings) -> Result<Vec<OverLimit>, String> {
+    let error = |err: zip::result::ZipError| {
+        format!("Error reading archive {}: {}", archive.display(), err)
+    };
+    let file = File::open(archive)
+        .map_err(|err| format!("Error reading archive {}: {}", archive.display(), err))?;
+    let mut zip = ZipArchive::new(file).map_err(error)?;
+
+    let folder = archive.with_extension("");
+    let measure = &settings.measure;
+    let mut over_limit = Vec::new();
+    for index in 0..zip.len() {
+        let entry = zip.by_index_raw(index).map_err(error)?;
+        // Names like `../x` would end up somewhere else or not be extracted at all
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+
+        let path = folder.join(name);
+        let measured = measure.text(&path, &settings.root);
+        if measure.mode.count(&measured) <= settings.limit {
+            continue;
+        }
+
+        let mut hit = scan::over_limit(&path, &settings.root, measure, &measured, None);
+        hit.kind = if entry.is_dir() {
+            EntryKind::Folder
+        } else {
+            EntryKind::File
+        };
+        hit.file_size = (!entry.is_dir()).then(|| entry.size());
+        hit.modified = entry.last_modified().and_then(|modified| {
+            let naive = NaiveDate::from_ymd_opt(
+                modified.year().into(),
+                modified.month().into(),
+                modified.day().into(),
+            )?
+            .and_hms_opt(
+                modified.hour().into(),
+                modified.minute().into(),
+                modified.second().into(),
+            )?;
+            Local.from_local_datetime(&naive).earliest()
+        });
+        hit.archive = Some(archive.to_string_lossy().to_string());
+        over_limit.push(hit);
+    }
+    Ok(over_limit)
+}
@@ -0,0 +1,395 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    archive::ArchivePolicy,
+    filter::{Filter, Pattern},
+    measure::Measure,
+    scan::{FolderId, LinkPolicy, Settings, Update},
+    spool::Spools,
+};
+
+/// The progress of a scan, saved so an interrupted scan can be resumed.
+///
+/// The results are a log of JSON lines, every save appends the results since the last save.
+/// The state only ever describes the latest save, so it is written to a file next to the log
+/// that replaces the previous one. It records how long the log was, results after that were
+/// cut off while writing and are ignored, their folders are read again.
+pub struct Checkpoint {
+    file: File,
+    path: PathBuf,
+    /// Length of the log when the state was last saved
+    length: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Start {
+        started: DateTime<Local>,
+        settings: SavedSettings,
+    },
+    Results(Update),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    /// The results in the log up to here belong to the state
+    results_length: u64,
+    state: State,
+}
+
+/// [`Settings`] with the patterns as text.
+#[derive(Serialize, Deserialize)]
+struct SavedSettings {
+    root: PathBuf,
+    limit: usize,
+    measure: Measure,
+    parallelism: usize,
+    links: LinkPolicy,
+    #[serde(default)]
+    archives: ArchivePolicy,
+    exclude: Vec<String>,
+    include: Vec<String>,
+}
+
+/// Where the walk stood when the checkpoint was saved.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct State {
+    pub scanned: u64,
+    /// Folders that still have to be read, including those that were being read
+    pub folders: Vec<PathBuf>,
+    pub links: Vec<PathBuf>,
+    /// Only filled when links are followed
+    pub visited: Vec<FolderId>,
+}
+
+/// An interrupted scan, ready to continue.
+#[derive(Debug)]
+pub struct Resumed {
+    pub started: DateTime<Local>,
+    pub settings: Settings,
+    /// Everything found before the interruption, without the paths and findings when spooled
+    pub results: Update,
+    /// The paths over the limit and findings found before the interruption, when spooled
+    pub spools: Option<Spools>,
+    pub state: State,
+}
+
+impl Checkpoint {
+    /// Start a new checkpoint, replacing an existing one.
+    pub fn create(path: &Path, settings: &Settings, started: DateTime<Local>) -> io::Result<Self> {
+        match fs::remove_file(state_path(path)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        let mut checkpoint = Self {
+            file: File::create(path)?,
+            path: path.to_path_buf(),
+            length: 0,
+        };
+        checkpoint.write(&[Record::Start {
+            started,
+            settings: SavedSettings {
+                root: settings.root.clone(),
+                limit: settings.limit,
+                measure: settings.measure.clone(),
+                parallelism: settings.parallelism,
+                links: settings.links,
+                archives: settings.archives,
+                exclude: patterns(&settings.filter.exclude),
+                include: patterns(&settings.filter.include),
+            },
+        }])?;
+        checkpoint.length = checkpoint.file.metadata()?.len();
+        Ok(checkpoint)
+    }
+
+    /// Continue a checkpoint after it was resumed.
+    ///
+    /// Results that were cut off are removed, so they aren't taken for part of the next state.
+    pub fn append(path: &Path) -> io::Result<Self> {
+        let length = match read_state(path)? {
+            Some(saved) => saved.results_length,
+            None => {
+                let mut start = Vec::new();
+                BufReader::new(File::open(path)?).read_until(b'\n', &mut start)?;
+                start.len() as u64
+            }
+        };
+        let file = OpenOptions::new().append(true).open(path)?;
+        file.set_len(length)?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            length,
+        })
+    }
+
+    pub fn save(&mut self, results: Update, state: State) -> io::Result<()> {
+        self.write(&[Record::Results(results)])?;
+        self.length = self.file.metadata()?.len();
+
+        // Replaced in one step, so there always is a complete state
+        let saved = state_path(&self.path);
+        let temporary = saved.with_extension("tmp");
+        let file = File::create(&temporary)?;
+        let mut writer = BufWriter::new(&file);
+        serde_json::to_writer(
+            &mut writer,
+            &SavedState {
+                results_length: self.length,
+                state,
+            },
+        )?;
+        writer.flush()?;
+        drop(writer);
+        file.sync_data()?;
+        fs::rename(&temporary, &saved)
+    }
+
+    /// A finished scan has nothing to resume.
+    pub fn remove(self) -> io::Result<()> {
+        drop(self.file);
+        match fs::remove_file(state_path(&self.path)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        fs::remove_file(&self.path)
+    }
+
+    fn write(&mut self, records: &[Record]) -> io::Result<()> {
+        let mut writer = BufWriter::new(&self.file);
+        for record in records {
+            serde_json::to_writer(&mut writer, record)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        self.file.sync_data()
+    }
+}
+
+/// The state is kept next to the log of results.
+fn state_path(path: &Path) -> PathBuf {
+    path.with_extension("state.json")
+}
+
+fn read_state(path: &Path) -> io::Result<Option<SavedState>> {
+    match fs::read(state_path(path)) {
+        Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// The folder of the scan a checkpoint belongs to, without reading all of it.
+pub fn root(path: &Path) -> Option<PathBuf> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_line(&mut line)
+        .ok()?;
+    match serde_json::from_str(&line).ok()? {
+        Record::Start { settings, .. } => Some(settings.root),
+        _ => None,
+    }
+}
+
+/// Read an interrupted scan. With spools, the paths over the limit and the findings are written
+/// to them while reading instead of being kept in memory.
+pub fn load(path: &Path, mut spools: Option<Spools>) -> Result<Resumed, String> {
+    let saved = read_state(path)
+        .map_err(|err| format!("Failed to read checkpoint {}: {}", path.display(), err))?;
+    let file = File::open(path)
+        .map_err(|err| format!("Failed to open checkpoint {}: {}", path.display(), err))?;
+    // Results after the saved length were cut off while writing
+    let length = saved
+        .as_ref()
+        .map_or(u64::MAX, |saved| saved.results_length);
+    let mut lines = BufReader::new(file.take(length)).lines();
+
+    let invalid = || format!("{} is not a scan checkpoint", path.display());
+    let first = lines
+        .next()
+        .ok_or_else(invalid)?
+        .map_err(|err| err.to_string())?;
+    let Ok(Record::Start { started, settings }) = serde_json::from_str(&first) else {
+        return Err(invalid());
+    };
+
+    let mut results = Update::default();
+    // Without a state, nothing was saved before the scan stopped
+    if saved.is_some() {
+        for line in lines {
+            let line = line.map_err(|err| err.to_string())?;
+            let Ok(Record::Results(mut update)) = serde_json::from_str(&line) else {
+                return Err(invalid());
+            };
+            if let Some(spools) = &mut spools {
+                spools
+                    .paths
+                    .extend(std::mem::take(&mut update.over_limit))
+                    .and_then(|()| spools.findings.extend(std::mem::take(&mut update.findings)))
+                    .map_err(|err| format!("Failed to write temporary file: {}", err))?;
+            }
+            results.extend(update);
+        }
+    }
+
+    let settings = Settings {
+        filter: Filter {
+            exclude: parse_patterns(&settings.exclude)?,
+            include: parse_patterns(&settings.include)?,
+        },
+        root: settings.root,
+        limit: settings.limit,
+        measure: settings.measure,
+        parallelism: settings.parallelism,
+        links: settings.links,
+        archives: settings.archives,
+    };
+    let state = saved.map(|saved| saved.state).unwrap_or_else(|| State {
+        folders: vec![settings.root.clone()],
+        ..Default::default()
+    });
+    results.scanned = state.scanned;
+
+    Ok(Resumed {
+        started,
+        settings,
+        results,
+        spools,
+        state,
+    })
+}
+
+fn patterns(patterns: &[Pattern]) -> Vec<String> {
+    patterns.iter().map(ToString::to_string).collect()
+}
+
+fn parse_patterns(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::parse(pattern))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(name: &str) -> (PathBuf, Checkpoint) {
+        let path = std::env::temp_dir().join(format!(
+            "toolbox-checkpoint-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        let settings = Settings {
+            root: PathBuf::from("/data"),
+            limit: 240,
+            measure: Measure::default(),
+            parallelism: 1,
+            links: LinkPolicy::DontFollow,
+            archives: ArchivePolicy::Ignore,
+            filter: Filter::default(),
+        };
+        let checkpoint = Checkpoint::create(&path, &settings, Local::now()).unwrap();
+        (path, checkpoint)
+    }
+
+    fn results(error: &str) -> Update {
+        Update {
+            errors: vec![error.to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn state(scanned: u64) -> State {
+        State {
+            scanned,
+            folders: vec![PathBuf::from(format!("/data/{}", scanned))],
+            ..Default::default()
+        }
+    }
+
+    fn remove(path: &Path) {
+        fs::remove_file(path).unwrap();
+        let _ = fs::remove_file(state_path(path));
+    }
+
+    #[test]
+    fn only_start_scans_everything_again() {
+        let (path, _checkpoint) = checkpoint("start");
+        let resumed = load(&path, None);
+        remove(&path);
+
+        let resumed = resumed.unwrap();
+        assert_eq!(resumed.settings.root, PathBuf::from("/data"));
+        assert_eq!(resumed.state.folders, [PathBuf::from("/data")]);
+        assert_eq!(resumed.state.scanned, 0);
+        assert!(resumed.results.errors.is_empty());
+    }
+
+    #[test]
+    fn keeps_only_the_latest_state() {
+        let (path, mut checkpoint) = checkpoint("latest");
+        checkpoint.save(results("a"), state(1)).unwrap();
+        checkpoint.save(results("b"), state(2)).unwrap();
+        let resumed = load(&path, None);
+        let log = fs::read_to_string(&path).unwrap();
+        remove(&path);
+
+        let resumed = resumed.unwrap();
+        assert_eq!(resumed.results.errors, ["a", "b"]);
+        assert_eq!(resumed.results.scanned, 2);
+        assert_eq!(resumed.state.folders, [PathBuf::from("/data/2")]);
+        // The start and one line of results per save, no states
+        assert_eq!(log.lines().count(), 3);
+    }
+
+    #[test]
+    fn ignores_a_truncated_final_line() {
+        let (path, mut checkpoint) = checkpoint("truncated");
+        checkpoint.save(results("a"), state(1)).unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(br#"{"record":"results","scanned":2,"over_"#)
+            .unwrap();
+        let resumed = load(&path, None);
+        remove(&path);
+
+        let resumed = resumed.unwrap();
+        assert_eq!(resumed.results.errors, ["a"]);
+        assert_eq!(resumed.state.scanned, 1);
+    }
+
+    #[test]
+    fn ignores_results_after_the_last_state() {
+        let (path, mut checkpoint) = checkpoint("unconfirmed");
+        checkpoint.save(results("a"), state(1)).unwrap();
+        // Stopped between writing the results and the state
+        checkpoint.write(&[Record::Results(results("b"))]).unwrap();
+        drop(checkpoint);
+        let resumed = load(&path, None).unwrap();
+        assert_eq!(resumed.results.errors, ["a"]);
+        assert_eq!(resumed.state.scanned, 1);
+
+        // Continuing drops them, so they don't count for the next state
+        let mut checkpoint = Checkpoint::append(&path).unwrap();
+        checkpoint.save(results("c"), state(3)).unwrap();
+        let resumed = load(&path, None);
+        remove(&path);
+
+        let resumed = resumed.unwrap();
+        assert_eq!(resumed.results.errors, ["a", "c"]);
+        assert_eq!(resumed.state.scanned, 3);
+    }
+}
@@ -0,0 +1,474 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    archive::ArchivePolicy,
+    checkpoint::{self, Checkpoint},
+    filter::Filter,
+    lint::Rule,
+    measure::{Measure, Mode, Profile},
+    report::{self, CsvOptions, Delimiter, Findings, Format, Paths, Report},
+    scan::{self, LinkPolicy},
+    spool::Spools,
+};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Options saved in a checkpoint, see [`parse`].
+const SCAN_OPTIONS: &[&str] = &[
+    "--limit",
+    "--mode",
+    "--relative",
+    "--target",
+    "--profile",
+    "--parallelism",
+    "--links",
+    "--archives",
+    "--checkpoint",
+    "--exclude",
+    "--include",
+];
+
+const USAGE: &str = "\
+Usage: toolbox path-length <folder> [options]
+       toolbox path-length --resume <checkpoint> [output options]
+
+Options:
+  --limit <n>           Longest allowed path (default 240, 400 with --profile sharepoint)
+  --mode <mode>         utf16, chars or bytes (default utf16)
+  --relative            Don't count the scanned folder itself
+  --target <path>       Measure as if the folder was moved here
+  --profile <profile>   windows or sharepoint, which measures below the --target library URL
+  --parallelism <n>     Folders read at the same time (default 8)
+  --links <policy>      dont-follow, follow or within-root (default dont-follow)
+  --archives <kind>     Check entries inside zip archives, or office for Office files as well
+  --checkpoint <file>   Save the progress here every 30 seconds, removed when done
+  --resume <file>       Continue an interrupted scan from its checkpoint
+  --exclude <patterns>  Comma separated patterns to skip, e.g. \".git, node_modules\"
+  --include <patterns>  Only check files matching these, e.g. \"*.docx, *.xlsx\"
+  --format <format>     csv, json or html (default csv)
+  --delimiter <d>       CSV delimiter: semicolon, comma or tab (default semicolon)
+  --no-bom              Write CSV without byte order mark
+  --out <file>          Write the report here instead of stdout
+  --findings <file>     Write the file names Windows or SharePoint can't handle here as CSV
+  --fail-on-hit         Exit with 1 if any path is over the limit or any name was found
+  --spool               Keep the paths over the limit and name findings in temporary files
+
+Only the output options can be given with --resume, the scan continues with its own settings.
+
+Exit codes: 0 done, 1 paths over the limit or name findings with --fail-on-hit, 2 invalid
+arguments or failed export";
+
+/// A new scan or an interrupted one.
+enum Source {
+    Scan {
+        settings: scan::Settings,
+        checkpoint: Option<PathBuf>,
+    },
+    Resume(PathBuf),
+}
+
+struct Options {
+    source: Source,
+    format: Format,
+    csv: CsvOptions,
+    out: Option<PathBuf>,
+    findings: Option<PathBuf>,
+    fail_on_hit: bool,
+    spool: bool,
+}
+
+/// Run a scan without the window and return the exit code.
+///
+/// Progress and errors go to stderr, the report to `--out` or stdout.
+pub fn run(args: impl Iterator<Item = String>) -> i32 {
+    attach_console();
+
+    let options = match parse(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return 2;
+        }
+    };
+
+    match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime.block_on(scan(options)),
+        Err(err) => {
+            eprintln!("Failed to start: {}", err);
+            2
+        }
+    }
+}
+
+async fn scan(options: Options) -> i32 {
+    let mut started = Local::now();
+    let mut results = scan::Update::default();
+    let mut resume = None;
+    let mut checkpoint = None;
+    let mut spools = if options.spool {
+        match Spools::create() {
+            Ok(spools) => Some(spools),
+            Err(err) => {
+                eprintln!("Failed to create temporary file: {}", err);
+                return 2;
+            }
+        }
+    } else {
+        None
+    };
+    let settings = match options.source {
+        Source::Scan {
+            settings,
+            checkpoint: path,
+        } => {
+            if let Some(path) = path {
+                match Checkpoint::create(&path, &settings, started) {
+                    Ok(created) => checkpoint = Some(created),
+                    Err(err) => {
+                        eprintln!("Failed to create checkpoint {}: {}", path.display(), err);
+                        return 2;
+                    }
+                }
+            }
+            settings
+        }
+        Source::Resume(path) => {
+            let resumed = match checkpoint::load(&path, spools.take()) {
+                Ok(resumed) => resumed,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    return 2;
+                }
+            };
+            match Checkpoint::append(&path) {
+                Ok(appended) => checkpoint = Some(appended),
+                Err(err) => {
+                    eprintln!("Failed to open checkpoint {}: {}", path.display(), err);
+                    return 2;
+                }
+            }
+            eprintln!(
+                "Resuming scan of {} after {} entries",
+                resumed.settings.root.display(),
+                resumed.state.scanned
+            );
+            started = resumed.started;
+            results = resumed.results;
+            spools = resumed.spools;
+            resume = Some(resumed.state);
+            resumed.settings
+        }
+    };
+
+    let (updates, mut receiver) = mpsc::channel::<scan::Update>(16);
+    let mut paths = Vec::new();
+    let mut findings = Vec::new();
+    let mut errors = results.errors;
+    let mut skipped_links = results.skipped_links;
+    let mut scanned = results.scanned;
+    let mut store = |found: Vec<_>, found_findings: Vec<_>| match &mut spools {
+        Some(spools) => spools
+            .paths
+            .extend(found)
+            .and_then(|()| spools.findings.extend(found_findings)),
+        None => {
+            paths.extend(found);
+            findings.extend(found_findings);
+            Ok(())
+        }
+    };
+    let mut stored = store(results.over_limit, results.findings);
+    let forward = async {
+        let mut last_progress = Instant::now();
+        while let Some(update) = receiver.recv().await {
+            scanned = update.scanned;
+            for error in &update.errors {
+                eprintln!("{}", error);
+            }
+            errors.extend(update.errors);
+            skipped_links.extend(update.skipped_links);
+            if stored.is_ok() {
+                stored = store(update.over_limit, update.findings);
+            }
+
+            if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                eprintln!("Scanned {} entries", scanned);
+                last_progress = Instant::now();
+            }
+        }
+    };
+
+    tokio::join!(
+        scan::run(
+            settings.clone(),
+            resume,
+            checkpoint,
+            CancellationToken::new(),
+            updates
+        ),
+        forward
+    );
+
+    if let Err(err) = stored {
+        eprintln!("Failed to write temporary file: {}", err);
+        return 2;
+    }
+    let readers = spools
+        .as_mut()
+        .map(|spools| io::Result::Ok((spools.paths.reader()?, spools.findings.reader()?)))
+        .transpose();
+    let (paths, findings) = match readers {
+        Ok(Some((paths, findings))) => (Paths::Spool(paths), Findings::Spool(findings)),
+        Ok(None) => (Paths::Memory(paths), Findings::Memory(findings)),
+        Err(err) => {
+            eprintln!("Failed to write temporary file: {}", err);
+            return 2;
+        }
+    };
+
+    let hits = paths.len();
+    eprintln!(
+        "Scan finished: {} entries checked, {} over the limit of {} ({}), {} name findings, {} errors",
+        scanned,
+        hits,
+        settings.limit,
+        settings.measure,
+        findings.len(),
+        errors.len()
+    );
+    if options.findings.is_none() && !findings.is_empty() {
+        eprintln!("Write the name findings with --findings <file>");
+    }
+
+    let report = Report {
+        root: settings.root.to_string_lossy().to_string(),
+        limit: settings.limit,
+        measure: settings.measure.clone(),
+        timestamp: started,
+        scanned,
+        paths,
+        skipped_links,
+        errors,
+        filter: settings.filter.clone(),
+    };
+    let write = |out: &mut dyn Write| {
+        let mut out = BufWriter::new(out);
+        report.write(options.format, options.csv, &mut out)?;
+        out.flush()
+    };
+    let written = match &options.out {
+        Some(out) => File::create(out)
+            .and_then(|mut file| write(&mut file))
+            .map(|()| eprintln!("Report written to {}", out.display()))
+            .map_err(|err| format!("Failed to write {}: {}", out.display(), err)),
+        None => write(&mut io::stdout().lock())
+            .map_err(|err| format!("Failed to write report: {}", err)),
+    };
+
+    let written = written.and_then(|()| {
+        let Some(path) = &options.findings else {
+            return Ok(());
+        };
+        File::create(path)
+            .and_then(|file| {
+                let mut out = BufWriter::new(file);
+                report::findings_csv(&findings, &Rule::ALL, options.csv, &mut out)?;
+                out.flush()
+            })
+            .map(|()| eprintln!("Name findings written to {}", path.display()))
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+    });
+
+    if let Err(err) = written {
+        eprintln!("{}", err);
+        2
+    } else if options.fail_on_hit && (hits > 0 || !findings.is_empty()) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Returns `None` when the usage was asked for.
+fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut root = None;
+    let mut limit = None;
+    let mut measure = Measure::default();
+    let mut parallelism = 8;
+    let mut links = LinkPolicy::DontFollow;
+    let mut archives = ArchivePolicy::Ignore;
+    let mut filter = Filter::default();
+    let mut format = Format::Csv;
+    let mut csv = CsvOptions::default();
+    let mut out = None;
+    let mut findings = None;
+    let mut fail_on_hit = false;
+    let mut spool = false;
+    let mut checkpoint = None;
+    let mut resume = None;
+    // Options that change the scan, which comes from the checkpoint when resuming
+    let mut scan_options = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if SCAN_OPTIONS.contains(&arg.as_str()) && !scan_options.contains(&arg) {
+            scan_options.push(arg.clone());
+        }
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--limit" => limit = Some(number(&mut args, &arg)?),
+            "--mode" => {
+                measure.mode = match value(&mut args, &arg)?.as_str() {
+                    "utf16" => Mode::Utf16,
+                    "chars" => Mode::Chars,
+                    "bytes" => Mode::Bytes,
+                    other => return Err(format!("Unknown mode {}", other)),
+                }
+            }
+            "--relative" => measure.include_root = false,
+            "--target" => measure.target = Some(value(&mut args, &arg)?),
+            "--profile" => {
+                measure.profile = match value(&mut args, &arg)?.as_str() {
+                    "windows" => Profile::Windows,
+                    "sharepoint" => Profile::SharePoint,
+                    other => return Err(format!("Unknown profile {}", other)),
+                }
+            }
+            "--parallelism" => parallelism = number(&mut args, &arg)?.max(1),
+            "--links" => {
+                links = match value(&mut args, &arg)?.as_str() {
+                    "dont-follow" => LinkPolicy::DontFollow,
+                    "follow" => LinkPolicy::Follow,
+                    "within-root" => LinkPolicy::WithinRoot,
+                    other => return Err(format!("Unknown link policy {}", other)),
+                }
+            }
+            "--archives" => {
+                archives = match value(&mut args, &arg)?.as_str() {
+                    "zip" => ArchivePolicy::Zip,
+                    "office" => ArchivePolicy::ZipAndOffice,
+                    other => return Err(format!("Unknown archive kind {}", other)),
+                }
+            }
+            "--exclude" => filter.exclude = Filter::parse_list(&value(&mut args, &arg)?)?,
+            "--include" => filter.include = Filter::parse_list(&value(&mut args, &arg)?)?,
+            "--format" => {
+                format = match value(&mut args, &arg)?.as_str() {
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    "html" => Format::Html,
+                    other => return Err(format!("Unknown format {}", other)),
+                }
+            }
+            "--delimiter" => {
+                csv.delimiter = match value(&mut args, &arg)?.as_str() {
+                    "semicolon" | ";" => Delimiter::Semicolon,
+                    "comma" | "," => Delimiter::Comma,
+                    "tab" | "\t" => Delimiter::Tab,
+                    other => return Err(format!("Unknown delimiter {}", other)),
+                }
+            }
+            "--no-bom" => csv.bom = false,
+            "--out" => out = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--findings" => findings = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--fail-on-hit" => fail_on_hit = true,
+            "--spool" => spool = true,
+            "--checkpoint" => checkpoint = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--resume" => resume = Some(PathBuf::from(value(&mut args, &arg)?)),
+            other if other.starts_with("--") => return Err(format!("Unknown option {}", other)),
+            other => {
+                if root.is_some() {
+                    return Err("Only one folder can be scanned at a time".to_string());
+                }
+                root = Some(PathBuf::from(other));
+            }
+        }
+    }
+
+    // The scan settings are in the checkpoint
+    let source = match (root, resume) {
+        (Some(_), Some(_)) => {
+            return Err("Either scan a folder or resume a checkpoint".to_string());
+        }
+        (None, Some(_)) if !scan_options.is_empty() => {
+            return Err(format!(
+                "{} can't be used with --resume, the scan continues with the settings of the checkpoint",
+                scan_options.join(", ")
+            ));
+        }
+        (None, Some(resume)) => Source::Resume(resume),
+        (None, None) => return Err("No folder given".to_string()),
+        (Some(root), None) => {
+            if !root.is_dir() {
+                return Err(format!("{} is not a folder", root.display()));
+            }
+            Source::Scan {
+                settings: scan::Settings {
+                    root,
+                    limit: limit.unwrap_or(measure.profile.limit()),
+                    measure,
+                    parallelism,
+                    links,
+                    archives,
+                    filter,
+                },
+                checkpoint,
+            }
+        }
+    };
+
+    // `--out report` becomes report.csv
+    let out = out.map(|out: PathBuf| {
+        if out.extension().is_none() {
+            out.with_extension(format.extension())
+        } else {
+            out
+        }
+    });
+
+    Ok(Some(Options {
+        source,
+        format,
+        csv,
+        out,
+        findings,
+        fail_on_hit,
+        spool,
+    }))
+}
+
+fn value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{} needs a value", option))
+}
+
+fn number(args: &mut impl Iterator<Item = String>, option: &str) -> Result<usize, String> {
+    value(args, option)?
+        .parse()
+        .map_err(|_| format!("{} needs a number", option))
+}
+
+/// The window build has no console of its own, so output would be lost when started from one.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+    // Fails when the parent has no console, redirected output still works then
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
@@ -0,0 +1,211 @@
+use std::fmt::Display;
+
+use regex::{Regex, RegexBuilder};
+
+/// A glob, or a regex when it starts with `re:`.
+///
+/// Globs without a separator match the name, others the path below the scanned folder.
+/// A glob ending in a separator, like `.git/`, only matches folders.
+/// Regexes always search the path below the scanned folder. Paths use `/` as separator.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    text: String,
+    regex: Regex,
+    whole_path: bool,
+    folders_only: bool,
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (regex, whole_path, folders_only) = match text.strip_prefix("re:") {
+            Some(regex) => (
+                Regex::new(regex).map_err(|err| format!("Invalid regex {}: {}", regex, err))?,
+                true,
+                false,
+            ),
+            None => {
+                let glob = text.trim_end_matches(['/', '\\']);
+                if glob.is_empty() {
+                    return Err(format!(
+                        "Invalid pattern {}: nothing before the separator",
+                        text
+                    ));
+                }
+                (
+                    // Windows doesn't care about case, so globs don't either
+                    RegexBuilder::new(&glob_to_regex(glob))
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|err| format!("Invalid pattern {}: {}", text, err))?,
+                    glob.contains(['/', '\\']),
+                    glob.len() < text.len(),
+                )
+            }
+        };
+
+        Ok(Self {
+            text: text.to_string(),
+            regex,
+            whole_path,
+            folders_only,
+        })
+    }
+
+    fn matches(&self, name: &str, relative: &str, is_dir: bool) -> bool {
+        (is_dir || !self.folders_only)
+            && self
+                .regex
+                .is_match(if self.whole_path { relative } else { name })
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Which entries a scan looks at.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Entries that are skipped, folders including everything below them
+    pub exclude: Vec<Pattern>,
+    /// If set, only files matching one of these are checked
+    pub include: Vec<Pattern>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty() && self.include.is_empty()
+    }
+
+    /// Parse comma separated patterns, a literal comma is written as `\,`.
+    pub fn parse_list(text: &str) -> Result<Vec<Pattern>, String> {
+        let mut patterns = Vec::new();
+        let mut current = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&',') => {
+                    current.push(',');
+                    chars.next();
+                }
+                ',' => patterns.push(std::mem::take(&mut current)),
+                c => current.push(c),
+            }
+        }
+        patterns.push(current);
+
+        patterns
+            .iter()
+            .map(|pattern| pattern.trim())
+            .filter(|pattern| !pattern.is_empty())
+            .map(Pattern::parse)
+            .collect()
+    }
+
+    /// `relative` is the path below the scanned folder.
+    pub fn excludes(&self, name: &str, relative: &str, is_dir: bool) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.matches(name, relative, is_dir))
+    }
+
+    /// Whether a file is checked. Folders are always entered, they may contain included files.
+    pub fn includes(&self, name: &str, relative: &str) -> bool {
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches(name, relative, false))
+    }
+}
+
+/// `*` matches within a name, `**` across folders and `?` a single character.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '/' | '\\' => regex.push('/'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(exclude: &str) -> Filter {
+        Filter {
+            exclude: Filter::parse_list(exclude).unwrap(),
+            include: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn star_stays_within_a_name() {
+        let filter = filter("*.tmp, docs/*.bak");
+        assert!(filter.excludes("a.tmp", "x/y/a.tmp", false));
+        assert!(!filter.excludes("a.tmp.txt", "a.tmp.txt", false));
+        assert!(filter.excludes("a.bak", "docs/a.bak", false));
+        assert!(!filter.excludes("a.bak", "docs/old/a.bak", false));
+    }
+
+    #[test]
+    fn double_star_crosses_folders() {
+        let filter = filter("docs/**/*.bak");
+        assert!(filter.excludes("a.bak", "docs/old/a.bak", false));
+        assert!(filter.excludes("a.bak", "docs/old/older/a.bak", false));
+        assert!(!filter.excludes("a.bak", "other/old/a.bak", false));
+    }
+
+    #[test]
+    fn question_mark_is_one_character() {
+        let filter = filter("file?.txt");
+        assert!(filter.excludes("file1.txt", "file1.txt", false));
+        assert!(!filter.excludes("file12.txt", "file12.txt", false));
+        assert!(!filter.excludes("file.txt", "file.txt", false));
+    }
+
+    #[test]
+    fn globs_ignore_case() {
+        let filter = filter("Thumbs.db, $RECYCLE.BIN");
+        assert!(filter.excludes("thumbs.DB", "a/thumbs.DB", false));
+        assert!(filter.excludes("$Recycle.Bin", "$Recycle.Bin", true));
+    }
+
+    #[test]
+    fn regexes_search_the_path() {
+        let filter = filter(r"re:^backup/\d{4}/");
+        assert!(filter.excludes("x.txt", "backup/2024/x.txt", false));
+        assert!(!filter.excludes("x.txt", "old/backup/2024/x.txt", false));
+        // Regexes are case sensitive
+        assert!(!filter.excludes("x.txt", "Backup/2024/x.txt", false));
+        assert!(Pattern::parse("re:(").is_err());
+    }
+
+    #[test]
+    fn trailing_separator_only_matches_folders() {
+        let filter = filter(r".git/, node_modules\ , build/out/");
+        assert!(filter.excludes(".git", ".git", true));
+        assert!(filter.excludes(".git", "sub/.git", true));
+        assert!(!filter.excludes(".git", ".git", false));
+        assert!(filter.excludes("node_modules", "web/node_modules", true));
+        assert!(!filter.excludes("node_modules", "web/node_modules", false));
+        assert!(filter.excludes("out", "build/out", true));
+        assert!(!filter.excludes("out", "src/out", true));
+        // Saved as written, so it parses the same again
+        assert_eq!(filter.exclude[0].to_string(), ".git/");
+        assert!(Pattern::parse("/").is_err());
+    }
+}
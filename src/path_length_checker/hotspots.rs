@@ -0,0 +1,247 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{button, column, row, scrollable, space, text, text_input},
+};
+
+use super::{EntryKind, MAX_FINDINGS_SHOWN, OverLimit, measure::Mode};
+
+const INDENT: f32 = 20.0;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Toggle(PathBuf),
+    ShortNameChanged(String),
+}
+
+/// A folder with paths over the limit at or below it.
+#[derive(Debug)]
+struct Folder {
+    path: PathBuf,
+    name: String,
+    name_length: usize,
+    /// Lengths of the paths over the limit at or below this folder
+    lengths: Vec<u64>,
+    /// Sorted by the number of paths over the limit
+    children: Vec<Folder>,
+}
+
+impl Folder {
+    /// How many paths get within the limit if this folder's name is shortened by `saved`.
+    fn fixed(&self, saved: usize, limit: usize) -> usize {
+        self.lengths
+            .iter()
+            .filter(|length| length.saturating_sub(saved as u64) <= limit as u64)
+            .count()
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    lengths: Vec<u64>,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn insert(&mut self, components: &[String], length: u64) {
+        self.lengths.push(length);
+        if let Some((first, rest)) = components.split_first() {
+            self.children
+                .entry(first.clone())
+                .or_default()
+                .insert(rest, length);
+        }
+    }
+
+    fn into_folder(self, path: PathBuf, name: String, mode: Mode) -> Folder {
+        let mut children: Vec<Folder> = self
+            .children
+            .into_iter()
+            .map(|(name, node)| node.into_folder(path.join(&name), name, mode))
+            .collect();
+        children.sort_by_key(|child| std::cmp::Reverse(child.lengths.len()));
+
+        Folder {
+            name_length: mode.count(&name),
+            name,
+            path,
+            lengths: self.lengths,
+            children,
+        }
+    }
+}
+
+/// The folders of the scan as a tree, showing where renames fix the most paths.
+pub struct Hotspots {
+    root: Option<Folder>,
+    expanded: HashSet<PathBuf>,
+    short_name_input: String,
+    /// The name length a folder is assumed to be shortened to
+    short_name: usize,
+}
+
+impl Default for Hotspots {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hotspots {
+    pub fn new() -> Self {
+        Self {
+            root: None,
+            expanded: HashSet::new(),
+            short_name_input: "10".to_string(),
+            short_name: 10,
+        }
+    }
+
+    /// Build the tree from the results of a scan.
+    pub fn build(&mut self, paths: &[OverLimit], root: &Path, mode: Mode) {
+        let mut tree = Node::default();
+        for path in paths {
+            let Ok(relative) = Path::new(&path.path).strip_prefix(root) else {
+                continue;
+            };
+            let mut components: Vec<String> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect();
+            // Files are counted for their folder, folders for themselves
+            if path.kind != EntryKind::Folder {
+                components.pop();
+            }
+            tree.insert(&components, path.length);
+        }
+
+        let name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.to_string_lossy().to_string());
+        self.root = Some(tree.into_folder(root.to_path_buf(), name, mode));
+        self.expanded.retain(|path| path.starts_with(root));
+        self.expanded.insert(root.to_path_buf());
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Toggle(path) => {
+                if !self.expanded.remove(&path) {
+                    self.expanded.insert(path);
+                }
+            }
+            Message::ShortNameChanged(short_name) => {
+                if let Ok(parsed) = short_name.parse::<usize>() {
+                    self.short_name = parsed;
+                }
+                self.short_name_input = short_name;
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self, limit: usize) -> Element<'_, Message> {
+        let Some(root) = &self.root else {
+            return text("The hotspots are shown when the scan is finished").into();
+        };
+
+        let mut rows = Vec::new();
+        let mut hidden = 0;
+        self.push_rows(root, 0, limit, &mut rows, &mut hidden);
+
+        column![
+            row![
+                text("Shorten folder names to"),
+                text_input("10", &self.short_name_input)
+                    .on_input(Message::ShortNameChanged)
+                    .width(Length::Fixed(80.0)),
+                text("characters"),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Folder").width(Length::Fill),
+                text("Over Limit").width(90),
+                text("Longest").width(80),
+                text("Name").width(60),
+                text("Saved").width(60),
+                text("Fixed").width(60),
+            ]
+            .spacing(10),
+            scrollable(
+                column(rows)
+                    .push((hidden > 0).then(|| {
+                        text(format!(
+                            "... and {} more folders, collapse some to see them",
+                            hidden
+                        ))
+                    }))
+                    .spacing(5)
+            )
+            .height(Length::Fixed(400.0))
+            .width(Length::Fill),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    fn push_rows<'a>(
+        &'a self,
+        folder: &'a Folder,
+        depth: usize,
+        limit: usize,
+        rows: &mut Vec<Element<'a, Message>>,
+        hidden: &mut usize,
+    ) {
+        if rows.len() >= MAX_FINDINGS_SHOWN {
+            *hidden += 1;
+            return;
+        }
+
+        let expanded = self.expanded.contains(&folder.path);
+        let saved = folder.name_length.saturating_sub(self.short_name);
+        let toggle = if folder.children.is_empty() {
+            "  "
+        } else if expanded {
+            "▼"
+        } else {
+            "▶"
+        };
+
+        rows.push(
+            row![
+                space().width(depth as f32 * INDENT),
+                button(text(toggle))
+                    .on_press_maybe(
+                        (!folder.children.is_empty()).then(|| Message::Toggle(folder.path.clone()))
+                    )
+                    .style(button::text)
+                    .padding([0, 5]),
+                text(&folder.name).width(Length::Fill),
+                text(folder.lengths.len()).width(90),
+                text(folder.lengths.iter().max().copied().unwrap_or_default()).width(80),
+                text(folder.name_length).width(60),
+                text(saved).width(60),
+                text(folder.fixed(saved, limit)).width(60),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into(),
+        );
+
+        if expanded {
+            for child in &folder.children {
+                self.push_rows(child, depth + 1, limit, rows, hidden);
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use super::measure::Profile;
+
+/// Longest file or folder name NTFS and SMB accept, in UTF-16 units.
+const MAX_COMPONENT: usize = 255;
+
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "COM¹", "COM²", "COM³", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8",
+    "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+const INVALID_CHARACTERS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Names SharePoint and OneDrive refuse on top of the Windows rules, compared ignoring case.
+const SHAREPOINT_BLOCKED_NAMES: &[&str] = &[".lock", "desktop.ini", "COM0", "LPT0"];
+
+/// A reason why Windows, or SharePoint with its profile, can't handle a file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Rule {
+    ComponentTooLong,
+    ReservedName,
+    TrailingDotOrSpace,
+    InvalidCharacter,
+    CaseCollision,
+    NormalizationCollision,
+    /// Names that only differ in trailing dots or spaces, which Windows removes
+    TrailingCollision,
+    LeadingSpace,
+    BlockedInSharePoint,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 9] = [
+        Rule::ComponentTooLong,
+        Rule::ReservedName,
+        Rule::TrailingDotOrSpace,
+        Rule::InvalidCharacter,
+        Rule::CaseCollision,
+        Rule::NormalizationCollision,
+        Rule::TrailingCollision,
+        Rule::LeadingSpace,
+        Rule::BlockedInSharePoint,
+    ];
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::ComponentTooLong => write!(f, "Name too long"),
+            Rule::ReservedName => write!(f, "Reserved name"),
+            Rule::TrailingDotOrSpace => write!(f, "Trailing dot or space"),
+            Rule::InvalidCharacter => write!(f, "Invalid character"),
+            Rule::CaseCollision => write!(f, "Case collision"),
+            Rule::NormalizationCollision => write!(f, "Unicode collision"),
+            Rule::TrailingCollision => write!(f, "Trailing dot collision"),
+            Rule::LeadingSpace => write!(f, "Leading space"),
+            Rule::BlockedInSharePoint => write!(f, "Blocked in SharePoint"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule: Rule,
+    pub path: String,
+    pub detail: String,
+}
+
+/// Check a single file or folder name against the naming rules of the profile.
+///
+/// `top_level` is set for entries directly in the scanned folder, which end up in the root
+/// of a SharePoint library.
+pub fn check_name(name: &str, profile: Profile, top_level: bool) -> Vec<(Rule, String)> {
+    let mut problems = Vec::new();
+
+    let length = name.encode_utf16().count();
+    if length > MAX_COMPONENT {
+        problems.push((
+            Rule::ComponentTooLong,
+            format!("{} characters, at most {} allowed", length, MAX_COMPONENT),
+        ));
+    }
+
+    // `NUL.txt` and `con ` are just as reserved as `NUL` and `CON`
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    if let Some(reserved) = RESERVED_NAMES
+        .iter()
+        .find(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        problems.push((Rule::ReservedName, format!("{} is a device name", reserved)));
+    }
+
+    if name.ends_with('.') || name.ends_with(' ') {
+        problems.push((
+            Rule::TrailingDotOrSpace,
+            "Windows removes trailing dots and spaces".to_string(),
+        ));
+    }
+
+    let mut invalid: Vec<String> = Vec::new();
+    for c in name.chars() {
+        let shown = if (c as u32) < 0x20 {
+            format!("0x{:02X}", c as u32)
+        } else if INVALID_CHARACTERS.contains(&c) {
+            c.to_string()
+        } else {
+            continue;
+        };
+        if !invalid.contains(&shown) {
+            invalid.push(shown);
+        }
+    }
+    if !invalid.is_empty() {
+        problems.push((
+            Rule::InvalidCharacter,
+            format!("Contains {}", invalid.join(" ")),
+        ));
+    }
+
+    if profile == Profile::SharePoint {
+        check_sharepoint_name(name, top_level, &mut problems);
+    }
+
+    problems
+}
+
+fn check_sharepoint_name(name: &str, top_level: bool, problems: &mut Vec<(Rule, String)>) {
+    if name.starts_with(' ') {
+        problems.push((
+            Rule::LeadingSpace,
+            "SharePoint doesn't allow leading spaces".to_string(),
+        ));
+    }
+
+    let blocked = if let Some(blocked) = SHAREPOINT_BLOCKED_NAMES
+        .iter()
+        .find(|blocked| name.eq_ignore_ascii_case(blocked))
+    {
+        Some(format!("{} can't be uploaded or synced", blocked))
+    } else if name.starts_with("~$") {
+        Some("Names starting with ~$ are Office lock files and are not synced".to_string())
+    } else if name.to_lowercase().contains("_vti_") {
+        Some("_vti_ is reserved anywhere in a name".to_string())
+    } else if top_level && name.eq_ignore_ascii_case("forms") {
+        Some("Forms is reserved in the root of a library".to_string())
+    } else {
+        None
+    };
+    if let Some(detail) = blocked {
+        problems.push((Rule::BlockedInSharePoint, detail));
+    }
+}
+
+/// Find names in one folder that end up as the same name on Windows or macOS.
+///
+/// Every entry of the folder should be passed, including those the include patterns skip.
+///
+/// Returns the index of the colliding name, the rule and a description.
+/// The first of the colliding names is not reported.
+pub fn check_collisions(names: &[String]) -> Vec<(usize, Rule, String)> {
+    let mut problems = Vec::new();
+    let mut normalized: HashMap<String, &str> = HashMap::new();
+    let mut folded: HashMap<String, &str> = HashMap::new();
+    let mut trimmed: HashMap<String, &str> = HashMap::new();
+
+    for (index, name) in names.iter().enumerate() {
+        // `ü` can be one code point (NFC, Windows and Linux) or `u` plus a combining mark (NFD, macOS)
+        let nfc: String = name.nfc().collect();
+        if let Some(other) = normalized.get(&nfc) {
+            problems.push((
+                index,
+                Rule::NormalizationCollision,
+                format!("Same as \"{}\" after Unicode normalization", other),
+            ));
+            continue;
+        }
+        normalized.insert(nfc.clone(), name);
+
+        let lower = nfc.to_lowercase();
+        if let Some(other) = folded.get(&lower) {
+            problems.push((
+                index,
+                Rule::CaseCollision,
+                format!("Differs from \"{}\" only in case", other),
+            ));
+            continue;
+        }
+        folded.insert(lower.clone(), name);
+
+        let windows = lower.trim_end_matches(['.', ' ']).to_string();
+        match trimmed.get(&windows) {
+            Some(other) => problems.push((
+                index,
+                Rule::TrailingCollision,
+                format!(
+                    "Same as \"{}\" once Windows removes trailing dots and spaces",
+                    other
+                ),
+            )),
+            None => {
+                trimmed.insert(windows, name);
+            }
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_collisions() {
+        let names = [
+            "Projekt",
+            "projekt",
+            "Archiv",
+            "Archiv. ",
+            "Mu\u{308}ller",
+            "Müller",
+        ]
+        .map(String::from);
+        let rules: Vec<_> = check_collisions(&names)
+            .into_iter()
+            .map(|(index, rule, _)| (index, rule))
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                (1, Rule::CaseCollision),
+                (3, Rule::TrailingCollision),
+                (5, Rule::NormalizationCollision),
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{borrow::Cow, fmt::Display, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// What is counted when measuring a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    /// What Windows counts for MAX_PATH
+    Utf16,
+    /// Unicode scalar values
+    Chars,
+    /// UTF-8 bytes, the limit of most Linux filesystems
+    Bytes,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 3] = [Mode::Utf16, Mode::Chars, Mode::Bytes];
+
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Mode::Utf16 => text.encode_utf16().count(),
+            Mode::Chars => text.chars().count(),
+            Mode::Bytes => text.len(),
+        }
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mode::Utf16 => write!(f, "UTF-16 units"),
+            Mode::Chars => write!(f, "Characters"),
+            Mode::Bytes => write!(f, "Bytes"),
+        }
+    }
+}
+
+/// The rules of the place the files are moved to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Profile {
+    #[default]
+    Windows,
+    /// SharePoint Online and OneDrive, the target is the URL of the document library
+    SharePoint,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 2] = [Profile::Windows, Profile::SharePoint];
+
+    /// The usual limit of the profile.
+    pub fn limit(&self) -> usize {
+        match self {
+            Profile::Windows => 240,
+            // For the decoded path below the domain, including the file name
+            Profile::SharePoint => 400,
+        }
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Profile::Windows => write!(f, "Windows file share"),
+            Profile::SharePoint => write!(f, "SharePoint / OneDrive"),
+        }
+    }
+}
+
+/// How the paths of a scan are measured.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Measure {
+    pub mode: Mode,
+    /// Count the scanned folder itself, or only the path below it
+    pub include_root: bool,
+    /// Measure the paths as if the scanned folder was moved here
+    pub target: Option<String>,
+    #[serde(default)]
+    pub profile: Profile,
+}
+
+impl Measure {
+    /// The part of the path that is measured.
+    ///
+    /// With the root included, the drive (`C:\`) or UNC share (`\\server\share\`) counts
+    /// fully, but a `\\?\` prefix doesn't, since it only lifts the limit.
+    ///
+    /// SharePoint counts the decoded URL path below the domain, so the library URL is
+    /// decoded and everything is joined with `/`.
+    pub fn text<'a>(&self, path: &'a Path, root: &Path) -> Cow<'a, str> {
+        if self.profile == Profile::SharePoint {
+            let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy();
+            match self.target.as_deref().map(library_path) {
+                Some(library) if !library.is_empty() => {
+                    Cow::Owned(join_target(&library, &relative))
+                }
+                _ => Cow::Owned(relative.replace('\\', "/")),
+            }
+        } else if let Some(target) = &self.target {
+            let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy();
+            Cow::Owned(join_target(target, &relative))
+        } else if self.include_root {
+            match path.to_string_lossy() {
+                Cow::Borrowed(text) => strip_verbatim(text),
+                Cow::Owned(text) => Cow::Owned(strip_verbatim(&text).into_owned()),
+            }
+        } else {
+            path.strip_prefix(root).unwrap_or(path).to_string_lossy()
+        }
+    }
+}
+
+impl Display for Measure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.profile == Profile::SharePoint {
+            match &self.target {
+                Some(target) => write!(f, "{}, SharePoint library {}", self.mode, target),
+                None => write!(f, "{}, SharePoint path below the library", self.mode),
+            }
+        } else if let Some(target) = &self.target {
+            write!(f, "{}, moved to {}", self.mode, target)
+        } else if self.include_root {
+            write!(f, "{}, including root", self.mode)
+        } else {
+            write!(f, "{}, relative to root", self.mode)
+        }
+    }
+}
+
+impl Default for Measure {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Utf16,
+            include_root: true,
+            target: None,
+            profile: Profile::Windows,
+        }
+    }
+}
+
+/// Turn `\\?\C:\dir` into `C:\dir` and `\\?\UNC\server\share` into `\\server\share`.
+fn strip_verbatim(path: &str) -> Cow<'_, str> {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        Cow::Owned(format!(r"\\{}", rest))
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        Cow::Borrowed(rest)
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// The path a file gets after moving the scanned folder to `target`.
+///
+/// The separators follow the target, so a Linux scan can simulate a move to a Windows share.
+fn join_target(target: &str, relative: &str) -> String {
+    let target = strip_verbatim(target);
+    let separator = if target.contains('\\') || target.get(1..2) == Some(":") {
+        '\\'
+    } else {
+        '/'
+    };
+
+    let mut joined = target.trim_end_matches(['\\', '/']).to_string();
+    if !relative.is_empty() {
+        joined.push(separator);
+        joined.extend(
+            relative
+                .chars()
+                .map(|c| if c == '\\' || c == '/' { separator } else { c }),
+        );
+    }
+    joined
+}
+
+/// The decoded path of a library URL below the domain.
+///
+/// `https://contoso.sharepoint.com/sites/Team/Shared%20Documents` becomes
+/// `sites/Team/Shared Documents`.
+fn library_path(url: &str) -> String {
+    let url = url.trim();
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest
+            .split_once('/')
+            .map(|(_, path)| path)
+            .unwrap_or_default(),
+        None => url,
+    };
+    // Links copied from the browser often point to a view of the library
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let path = path
+        .strip_suffix("/Forms/AllItems.aspx")
+        .unwrap_or(path)
+        .trim_matches('/');
+    percent_decode(path)
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
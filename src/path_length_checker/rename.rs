@@ -0,0 +1,845 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Display,
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use super::measure::Measure;
+
+/// Created and removed again to find out if a folder can be changed.
+const WRITE_CHECK: &str = ".toolbox-write-check";
+
+/// Runs of letters, the words that can be abbreviated.
+static WORDS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\p{L}+").expect("valid regex"));
+
+/// Lowercase words and their abbreviation.
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("administration", "admin"),
+    ("application", "app"),
+    ("applications", "apps"),
+    ("archive", "arch"),
+    ("backup", "bak"),
+    ("configuration", "config"),
+    ("customer", "cust"),
+    ("customers", "cust"),
+    ("department", "dept"),
+    ("development", "dev"),
+    ("document", "doc"),
+    ("documents", "docs"),
+    ("information", "info"),
+    ("international", "intl"),
+    ("management", "mgmt"),
+    ("number", "no"),
+    ("organization", "org"),
+    ("presentation", "pres"),
+    ("project", "proj"),
+    ("projects", "proj"),
+    ("temporary", "temp"),
+    ("version", "ver"),
+    ("abteilung", "abt"),
+    ("allgemein", "allg"),
+    ("angebote", "angeb"),
+    ("archiv", "arch"),
+    ("besprechung", "bespr"),
+    ("buchhaltung", "buchh"),
+    ("dokument", "dok"),
+    ("dokumente", "dok"),
+    ("geschäftsführung", "gf"),
+    ("korrespondenz", "korr"),
+    ("projekt", "proj"),
+    ("projekte", "proj"),
+    ("protokoll", "prot"),
+    ("protokolle", "prot"),
+    ("rechnung", "rechn"),
+    ("rechnungen", "rechn"),
+    ("unterlagen", "unterl"),
+    ("verträge", "vtr"),
+    ("vertrag", "vtr"),
+    ("verwaltung", "verw"),
+];
+
+/// What may be changed to shorten the paths.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Names are cut to this many characters, file extensions are kept
+    pub max_name: usize,
+    pub abbreviate: bool,
+    /// Remove folders that only contain a single folder
+    pub collapse: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_name: 40,
+            abbreviate: true,
+            collapse: true,
+        }
+    }
+}
+
+/// A single step of a batch, as written to the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum Operation {
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// A folder that became empty when its only folder was moved up
+    RemoveFolder {
+        path: PathBuf,
+    },
+}
+
+impl Operation {
+    fn apply(&self) -> std::io::Result<()> {
+        match self {
+            Operation::Rename { from, to } => fs::rename(from, to),
+            Operation::RemoveFolder { path } => fs::remove_dir(path),
+        }
+    }
+
+    fn revert(&self) -> std::io::Result<()> {
+        match self {
+            Operation::Rename { from, to } => fs::rename(to, from),
+            Operation::RemoveFolder { path } => fs::create_dir(path),
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Rename { from, to } => {
+                write!(f, "Rename {} to {}", from.display(), to.display())
+            }
+            Operation::RemoveFolder { path } => {
+                write!(f, "Remove empty folder {}", path.display())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Change {
+    Rename(String),
+    /// The folder takes the content of the last folder in the chain
+    Collapse {
+        chain: Vec<PathBuf>,
+        name: String,
+    },
+}
+
+/// A path over the limit and what it would look like after the batch.
+#[derive(Debug, Clone)]
+pub struct Preview {
+    pub path: String,
+    pub length: usize,
+    pub new_path: String,
+    pub new_length: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    /// What happens, in words
+    pub changes: Vec<String>,
+    /// The steps in the order they are applied
+    pub operations: Vec<Operation>,
+    pub previews: Vec<Preview>,
+    /// Changes that were left out, and why
+    pub problems: Vec<String>,
+}
+
+/// Propose new names for everything on the way to the given paths.
+///
+/// Changes that would collide with an existing name or are in a read-only folder are left out.
+/// Nothing is written here, whether the folders can really be changed is only found out by
+/// [`apply`]. This blocks, so it runs on the blocking thread pool.
+pub fn plan(paths: &[String], root: &Path, measure: &Measure, options: &Options) -> Plan {
+    let mut plan = Plan::default();
+
+    // Parents sort before their children
+    let mut entries = BTreeSet::new();
+    for path in paths {
+        let Ok(relative) = Path::new(path).strip_prefix(root) else {
+            continue;
+        };
+        let mut entry = root.to_path_buf();
+        for component in relative.components() {
+            entry.push(component);
+            entries.insert(entry.clone());
+        }
+    }
+
+    let mut changes = BTreeMap::new();
+    let mut collapsed = HashSet::new();
+    for entry in &entries {
+        if collapsed.contains(entry) {
+            continue;
+        }
+        // Names that aren't valid Unicode are left alone
+        let Some(name) = entry.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let metadata = match fs::symlink_metadata(entry) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                plan.problems
+                    .push(format!("Can't read {}: {}", entry.display(), err));
+                continue;
+            }
+        };
+
+        let new_name = shorten_name(name, metadata.is_file(), options);
+        let chain = if options.collapse && metadata.is_dir() {
+            single_folder_chain(entry)
+        } else {
+            Vec::new()
+        };
+
+        if !chain.is_empty() {
+            collapsed.extend(chain.iter().cloned());
+            changes.insert(
+                entry.clone(),
+                Change::Collapse {
+                    chain,
+                    name: new_name,
+                },
+            );
+        } else if new_name != name {
+            changes.insert(entry.clone(), Change::Rename(new_name));
+        }
+    }
+
+    check_collisions(&mut changes, &mut plan.problems);
+    check_permissions(&mut changes, &mut plan.problems);
+
+    // The deepest changes go first, so the paths of the others stay valid
+    let mut ordered: Vec<(&PathBuf, &Change)> = changes.iter().collect();
+    let mut temporaries = HashSet::new();
+    ordered.sort_by_key(|(entry, _)| std::cmp::Reverse(entry.components().count()));
+    for (entry, change) in ordered {
+        let parent = entry.parent().unwrap_or(root);
+        match change {
+            Change::Rename(name) => {
+                plan.changes
+                    .push(format!("Rename {} to {}", entry.display(), name));
+                plan.operations.push(Operation::Rename {
+                    from: entry.clone(),
+                    to: parent.join(name),
+                });
+            }
+            Change::Collapse { chain, name } => {
+                let last = chain.last().expect("collapsed chains are never empty");
+                plan.changes.push(format!(
+                    "Replace {} with the content of {} and name it {}",
+                    entry.display(),
+                    last.display(),
+                    name
+                ));
+
+                let temporary = free_name(parent, &mut temporaries);
+                let moved = |path: &Path| temporary.join(path.strip_prefix(entry).unwrap_or(path));
+                plan.operations.push(Operation::Rename {
+                    from: entry.clone(),
+                    to: temporary.clone(),
+                });
+                plan.operations.push(Operation::Rename {
+                    from: moved(last),
+                    to: parent.join(name),
+                });
+                for folder in chain[..chain.len() - 1].iter().rev() {
+                    plan.operations.push(Operation::RemoveFolder {
+                        path: moved(folder),
+                    });
+                }
+                plan.operations
+                    .push(Operation::RemoveFolder { path: temporary });
+            }
+        }
+    }
+
+    for path in paths {
+        let new_path = new_path(Path::new(path), root, &changes);
+        plan.previews.push(Preview {
+            path: path.clone(),
+            length: measure.mode.count(&measure.text(Path::new(path), root)),
+            new_path: new_path.to_string_lossy().to_string(),
+            new_length: measure.mode.count(&measure.text(&new_path, root)),
+        });
+    }
+
+    plan
+}
+
+/// Apply the operations and write each one to the journal right after it was done.
+///
+/// Everything is checked first. If an operation still fails, the ones before it are reverted.
+pub fn apply(operations: &[Operation], journal: &Path) -> Result<String, String> {
+    check_operations(operations)?;
+
+    let mut file = File::create(journal)
+        .map_err(|err| format!("Failed to create journal {}: {}", journal.display(), err))?;
+
+    for (index, operation) in operations.iter().enumerate() {
+        if let Err(err) = operation.apply() {
+            let err = format!("{} failed: {}", operation, err);
+            return Err(roll_back(&operations[..index], journal, &file, err));
+        }
+        // A change that isn't in the journal can't be undone later, so it is reverted now
+        if let Err(err) = write_operation(&mut file, operation) {
+            let err = format!("Failed to write journal: {}", err);
+            return Err(roll_back(&operations[..=index], journal, &file, err));
+        }
+    }
+
+    Ok(format!(
+        "Applied {} changes, journal written to {}",
+        operations.len(),
+        journal.display()
+    ))
+}
+
+/// Revert everything listed in a journal, newest first.
+///
+/// Afterwards the journal only lists what could not be reverted.
+pub fn undo(journal: &Path) -> Result<String, String> {
+    let file = File::open(journal)
+        .map_err(|err| format!("Failed to open journal {}: {}", journal.display(), err))?;
+    let mut operations = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|err| format!("Failed to read journal: {}", err))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        operations
+            .push(serde_json::from_str::<Operation>(&line).map_err(|err| {
+                format!("Line {} of the journal is invalid: {}", number + 1, err)
+            })?);
+    }
+
+    if operations.is_empty() {
+        return Err("The journal is empty, there is nothing to undo".to_string());
+    }
+
+    match revert(&operations) {
+        Ok(()) => {
+            rewrite_journal(journal, &[])
+                .map_err(|err| format!("Undone, but clearing the journal failed: {}", err))?;
+            Ok(format!("Undid {} changes", operations.len()))
+        }
+        Err((remaining, err)) => {
+            rewrite_journal(journal, &remaining).map_err(|write_err| {
+                format!("{}, rewriting the journal failed: {}", err, write_err)
+            })?;
+            Err(format!(
+                "Undid {} changes, then {}. The journal lists the {} that remain.",
+                operations.len() - remaining.len(),
+                err,
+                remaining.len()
+            ))
+        }
+    }
+}
+
+/// Revert the operations newest first and return the ones still applied on failure.
+fn revert(operations: &[Operation]) -> Result<(), (Vec<Operation>, String)> {
+    for (index, operation) in operations.iter().enumerate().rev() {
+        if let Err(err) = operation.revert() {
+            return Err((
+                operations[..=index].to_vec(),
+                format!("undoing \"{}\" failed: {}", operation, err),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Revert a batch that failed halfway and describe what happened.
+fn roll_back(applied: &[Operation], journal: &Path, file: &File, err: String) -> String {
+    match revert(applied) {
+        Ok(()) => {
+            let _ = file.set_len(0);
+            format!("{}. Everything done so far was undone.", err)
+        }
+        Err((remaining, revert_err)) => {
+            let _ = rewrite_journal(journal, &remaining);
+            format!(
+                "{}. Undoing failed as well: {}. The journal lists the {} changes that remain.",
+                err,
+                revert_err,
+                remaining.len()
+            )
+        }
+    }
+}
+
+fn write_operation(file: &mut File, operation: &Operation) -> std::io::Result<()> {
+    let line = serde_json::to_string(operation)?;
+    writeln!(file, "{}", line)?;
+    file.sync_data()
+}
+
+fn rewrite_journal(journal: &Path, operations: &[Operation]) -> std::io::Result<()> {
+    let mut file = File::create(journal)?;
+    for operation in operations {
+        write_operation(&mut file, operation)?;
+    }
+    Ok(())
+}
+
+/// Make sure nothing changed since the plan was made, before anything is touched.
+fn check_operations(operations: &[Operation]) -> Result<(), String> {
+    let mut created: Vec<&Path> = Vec::new();
+    let mut freed: Vec<&Path> = Vec::new();
+    let mut checked_folders = HashSet::new();
+
+    for operation in operations {
+        let Operation::Rename { from, to } = operation else {
+            continue;
+        };
+
+        // Paths below a moved folder only exist once the batch runs
+        if !created.iter().any(|created| from.starts_with(created)) {
+            if fs::symlink_metadata(from).is_err() {
+                return Err(format!(
+                    "{} no longer exists, propose the renames again",
+                    from.display()
+                ));
+            }
+            if let Some(parent) = from.parent()
+                && checked_folders.insert(parent)
+            {
+                check_writable(parent)?;
+            }
+        }
+
+        let same_name =
+            from.to_string_lossy().to_lowercase() == to.to_string_lossy().to_lowercase();
+        if !same_name && !freed.contains(&to.as_path()) && fs::symlink_metadata(to).is_ok() {
+            return Err(format!("{} already exists", to.display()));
+        }
+
+        created.push(to);
+        freed.push(from);
+    }
+
+    Ok(())
+}
+
+fn check_read_only(folder: &Path) -> Result<(), String> {
+    match fs::metadata(folder) {
+        Ok(metadata) if metadata.permissions().readonly() => {
+            Err(format!("{} is read-only", folder.display()))
+        }
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("Can't read {}: {}", folder.display(), err)),
+    }
+}
+
+/// Creates and removes a folder, so this only runs right before applying.
+fn check_writable(folder: &Path) -> Result<(), String> {
+    let probe = folder.join(WRITE_CHECK);
+    fs::create_dir(&probe)
+        .and_then(|()| fs::remove_dir(&probe))
+        .map_err(|err| format!("Can't change {}: {}", folder.display(), err))
+}
+
+/// Leave out changes whose new name is already taken in the same folder.
+///
+/// Names are compared ignoring case, like Windows does.
+fn check_collisions(changes: &mut BTreeMap<PathBuf, Change>, problems: &mut Vec<String>) {
+    loop {
+        let mut by_parent: BTreeMap<&Path, Vec<(&PathBuf, &str)>> = BTreeMap::new();
+        for (entry, change) in changes.iter() {
+            let name = match change {
+                Change::Rename(name) => name,
+                Change::Collapse { name, .. } => name,
+            };
+            if let Some(parent) = entry.parent() {
+                by_parent.entry(parent).or_default().push((entry, name));
+            }
+        }
+
+        let mut colliding = None;
+        'parents: for (parent, renamed) in by_parent {
+            let freed: HashSet<String> = renamed
+                .iter()
+                .filter_map(|(entry, _)| entry.file_name())
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .collect();
+            let mut taken: HashSet<String> = fs::read_dir(parent)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().to_lowercase())
+                .filter(|name| !freed.contains(name))
+                .collect();
+
+            for (entry, name) in renamed {
+                if !taken.insert(name.to_lowercase()) {
+                    colliding = Some((entry.clone(), name.to_string()));
+                    break 'parents;
+                }
+            }
+        }
+
+        // Leaving a change out keeps its old name, which can collide again
+        match colliding {
+            Some((entry, name)) => {
+                problems.push(format!(
+                    "Left out {}: {} is already taken",
+                    entry.display(),
+                    name
+                ));
+                changes.remove(&entry);
+            }
+            None => break,
+        }
+    }
+}
+
+/// Leave out changes in folders that are marked read-only.
+///
+/// Only the attributes are read, so making the preview doesn't touch the folders.
+fn check_permissions(changes: &mut BTreeMap<PathBuf, Change>, problems: &mut Vec<String>) {
+    let mut results: BTreeMap<PathBuf, Result<(), String>> = BTreeMap::new();
+    let mut check = |folder: &Path| {
+        results
+            .entry(folder.to_path_buf())
+            .or_insert_with(|| check_read_only(folder))
+            .clone()
+    };
+
+    changes.retain(|entry, change| {
+        let mut folders = entry.parent().into_iter().collect::<Vec<_>>();
+        if let Change::Collapse { chain, .. } = change {
+            folders.push(entry);
+            folders.extend(chain[..chain.len() - 1].iter().map(PathBuf::as_path));
+        }
+
+        match folders.into_iter().try_for_each(&mut check) {
+            Ok(()) => true,
+            Err(err) => {
+                problems.push(format!("Left out {}: {}", entry.display(), err));
+                false
+            }
+        }
+    });
+}
+
+/// Folders below `folder` that each are the only entry of their parent.
+fn single_folder_chain(folder: &Path) -> Vec<PathBuf> {
+    let mut chain = Vec::new();
+    let mut current = folder.to_path_buf();
+    loop {
+        let Ok(entries) = fs::read_dir(&current) else {
+            break;
+        };
+        let entries: Vec<_> = entries.take(2).collect();
+        let [Ok(only)] = entries.as_slice() else {
+            break;
+        };
+        if !only.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            break;
+        }
+        current = only.path();
+        chain.push(current.clone());
+    }
+    chain
+}
+
+/// A name that doesn't exist in the folder yet, for moving a folder out of the way.
+fn free_name(folder: &Path, used: &mut HashSet<PathBuf>) -> PathBuf {
+    let path = (0..)
+        .map(|index| folder.join(format!(".toolbox-collapse-{}", index)))
+        .find(|path| !used.contains(path) && fs::symlink_metadata(path).is_err())
+        .expect("some name is free");
+    used.insert(path.clone());
+    path
+}
+
+/// The path after all changes are applied.
+fn new_path(path: &Path, root: &Path, changes: &BTreeMap<PathBuf, Change>) -> PathBuf {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return path.to_path_buf();
+    };
+
+    let mut entry = root.to_path_buf();
+    let mut new_path = root.to_path_buf();
+    let mut skip = 0;
+    for component in relative.components() {
+        entry.push(component);
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        match changes.get(&entry) {
+            Some(Change::Rename(name)) => new_path.push(name),
+            Some(Change::Collapse { chain, name }) => {
+                new_path.push(name);
+                skip = chain.len();
+            }
+            None => new_path.push(component),
+        }
+    }
+    new_path
+}
+
+/// Abbreviate common words and cut the name to the maximum length.
+fn shorten_name(name: &str, is_file: bool, options: &Options) -> String {
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if is_file && dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+
+    let stem = if options.abbreviate {
+        abbreviate(stem)
+    } else {
+        stem.to_string()
+    };
+
+    let max_stem = options
+        .max_name
+        .saturating_sub(extension.chars().count())
+        .max(1);
+    let mut stem: String = stem.chars().take(max_stem).collect();
+    // Windows drops trailing dots and spaces
+    let trimmed = stem.trim_end_matches([' ', '.', '-', '_']).len();
+    if trimmed > 0 {
+        stem.truncate(trimmed);
+    }
+
+    format!("{}{}", stem, extension)
+}
+
+fn abbreviate(text: &str) -> String {
+    WORDS
+        .replace_all(text, |captures: &Captures| {
+            let word = &captures[0];
+            let lower = word.to_lowercase();
+            let Some((_, short)) = ABBREVIATIONS.iter().find(|(long, _)| *long == lower) else {
+                return word.to_string();
+            };
+
+            let mut chars = word.chars();
+            if word.chars().all(char::is_uppercase) {
+                short.to_uppercase()
+            } else if chars.next().is_some_and(char::is_uppercase) {
+                let mut short_chars = short.chars();
+                short_chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(short_chars).collect())
+                    .unwrap_or_default()
+            } else {
+                short.to_string()
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONG_FILE: &str = "report_of_the_quarterly_numbers.txt";
+
+    /// A folder with a long name, a long file name and a chain of single folders.
+    fn tree(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("toolbox-rename-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let long = root.join("Projects Documentation Archive");
+        fs::create_dir_all(&long).unwrap();
+        fs::write(long.join(LONG_FILE), "long").unwrap();
+        let inner = root.join("wrap").join("only").join("inner");
+        fs::create_dir_all(&inner).unwrap();
+        fs::write(inner.join("x.txt"), "x").unwrap();
+        fs::write(inner.join("y.txt"), "y").unwrap();
+        root
+    }
+
+    /// Every entry below the root with the content of the files.
+    fn snapshot(root: &Path) -> Vec<(String, Option<String>)> {
+        let mut entries = Vec::new();
+        let mut folders = vec![root.to_path_buf()];
+        while let Some(folder) = folders.pop() {
+            for entry in fs::read_dir(&folder).unwrap() {
+                let path = entry.unwrap().path();
+                let relative = path
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                if path.is_dir() {
+                    entries.push((relative, None));
+                    folders.push(path);
+                } else {
+                    entries.push((relative, Some(fs::read_to_string(&path).unwrap())));
+                }
+            }
+        }
+        entries.sort();
+        entries
+    }
+
+    fn planned(root: &Path) -> Plan {
+        let paths = [
+            root.join("Projects Documentation Archive").join(LONG_FILE),
+            root.join("wrap").join("only").join("inner").join("x.txt"),
+        ]
+        .map(|path| path.to_string_lossy().to_string());
+        plan(
+            &paths,
+            root,
+            &Measure::default(),
+            &Options {
+                max_name: 20,
+                abbreviate: true,
+                collapse: true,
+            },
+        )
+    }
+
+    #[test]
+    fn plans_short_names_and_collapses_chains() {
+        let root = tree("plan");
+        let plan = planned(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        let long = root.join("Projects Documentation Archive");
+        let temporary = root.join(".toolbox-collapse-0");
+        assert_eq!(plan.problems, Vec::<String>::new());
+        assert_eq!(
+            plan.operations,
+            [
+                Operation::Rename {
+                    from: long.join(LONG_FILE),
+                    to: long.join("report_of_the_qu.txt"),
+                },
+                Operation::Rename {
+                    from: long.clone(),
+                    to: root.join("Proj Documentation A"),
+                },
+                Operation::Rename {
+                    from: root.join("wrap"),
+                    to: temporary.clone(),
+                },
+                Operation::Rename {
+                    from: temporary.join("only").join("inner"),
+                    to: root.join("wrap"),
+                },
+                Operation::RemoveFolder {
+                    path: temporary.join("only"),
+                },
+                Operation::RemoveFolder { path: temporary },
+            ]
+        );
+
+        let new_paths: Vec<_> = plan
+            .previews
+            .iter()
+            .map(|preview| PathBuf::from(&preview.new_path))
+            .collect();
+        assert_eq!(
+            new_paths,
+            [
+                root.join("Proj Documentation A")
+                    .join("report_of_the_qu.txt"),
+                root.join("wrap").join("x.txt"),
+            ]
+        );
+        assert!(
+            plan.previews
+                .iter()
+                .all(|preview| preview.new_length < preview.length)
+        );
+    }
+
+    #[test]
+    fn applies_the_plan() {
+        let root = tree("apply");
+        let journal = root.with_extension("journal");
+        let plan = planned(&root);
+        let result = apply(&plan.operations, &journal);
+        let after = snapshot(&root);
+        let journaled = fs::read_to_string(&journal).unwrap().lines().count();
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_file(&journal).unwrap();
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(journaled, plan.operations.len());
+        let entry = |path: &str, content: Option<&str>| {
+            (
+                PathBuf::from(path).to_string_lossy().to_string(),
+                content.map(str::to_string),
+            )
+        };
+        assert_eq!(
+            after,
+            [
+                entry("Proj Documentation A", None),
+                entry("Proj Documentation A/report_of_the_qu.txt", Some("long")),
+                entry("wrap", None),
+                entry("wrap/x.txt", Some("x")),
+                entry("wrap/y.txt", Some("y")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rolls_back_when_an_operation_fails() {
+        let root = tree("roll-back");
+        let journal = root.with_extension("journal");
+        let before = snapshot(&root);
+        let mut operations = planned(&root).operations;
+        // Passes the checks, but the folder isn't empty
+        operations.push(Operation::RemoveFolder {
+            path: root.join("wrap"),
+        });
+        let result = apply(&operations, &journal);
+        let after = snapshot(&root);
+        let journaled = fs::read_to_string(&journal).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_file(&journal).unwrap();
+
+        let err = result.unwrap_err();
+        assert!(
+            err.ends_with("Everything done so far was undone."),
+            "{}",
+            err
+        );
+        assert_eq!(after, before);
+        assert_eq!(journaled, "");
+    }
+
+    #[test]
+    fn undo_restores_the_tree() {
+        let root = tree("undo");
+        let journal = root.with_extension("journal");
+        let before = snapshot(&root);
+        let plan = planned(&root);
+        apply(&plan.operations, &journal).unwrap();
+        assert_ne!(snapshot(&root), before);
+
+        let result = undo(&journal);
+        let after = snapshot(&root);
+        let journaled = fs::read_to_string(&journal).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_file(&journal).unwrap();
+
+        assert_eq!(
+            result,
+            Ok(format!("Undid {} changes", plan.operations.len()))
+        );
+        assert_eq!(after, before);
+        assert_eq!(journaled, "");
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use iced::{
+    Color, Element, Length, Task,
+    alignment::Vertical,
+    widget::{button, checkbox, column, row, scrollable, text, text_input},
+};
+use rfd::AsyncFileDialog;
+
+use super::{
+    MAX_FINDINGS_SHOWN, OverLimit,
+    measure::Measure,
+    rename::{self, Options, Plan},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    MaxName(String),
+    Abbreviate(bool),
+    Collapse(bool),
+    Propose,
+    Proposed(Arc<Plan>),
+    Apply,
+    Applied(Option<PathBuf>, Result<String, String>),
+    UndoLast,
+    UndoFromJournal,
+    Undone(Option<Result<String, String>>),
+}
+
+/// The scan the renames are proposed for.
+pub struct Scan<'a> {
+    pub paths: &'a [OverLimit],
+    pub root: &'a Path,
+    pub measure: &'a Measure,
+}
+
+/// Proposes shorter names for the paths over the limit and applies them in one batch.
+pub struct RenameTool {
+    max_name_input: String,
+    options: Options,
+    busy: bool,
+    plan: Option<Arc<Plan>>,
+    /// Root of the scan the plan was made for
+    plan_root: PathBuf,
+    /// Journal of the last applied batch
+    journal: Option<PathBuf>,
+    status: Option<Result<String, String>>,
+}
+
+impl RenameTool {
+    pub fn new() -> Self {
+        let options = Options::default();
+        Self {
+            max_name_input: options.max_name.to_string(),
+            options,
+            busy: false,
+            plan: None,
+            plan_root: PathBuf::new(),
+            journal: None,
+            status: None,
+        }
+    }
+
+    pub fn update(&mut self, message: Message, scan: Scan<'_>) -> Task<Message> {
+        match message {
+            Message::MaxName(max_name) => {
+                if let Ok(parsed) = max_name.parse::<usize>()
+                    && parsed > 0
+                {
+                    self.options.max_name = parsed;
+                }
+                self.max_name_input = max_name;
+            }
+            Message::Abbreviate(abbreviate) => self.options.abbreviate = abbreviate,
+            Message::Collapse(collapse) => self.options.collapse = collapse,
+            Message::Propose => {
+                self.busy = true;
+                self.status = None;
+                self.plan_root = scan.root.to_path_buf();
+                // Entries inside archives can't be renamed on disk
+                let paths: Vec<String> = scan
+                    .paths
+                    .iter()
+                    .filter(|path| path.archive.is_none())
+                    .map(|path| path.path.clone())
+                    .collect();
+                let root = scan.root.to_path_buf();
+                let measure = scan.measure.clone();
+                let options = self.options.clone();
+                return Task::future(async move {
+                    let plan = tokio::task::spawn_blocking(move || {
+                        rename::plan(&paths, &root, &measure, &options)
+                    })
+                    .await
+                    .unwrap_or_default();
+                    Message::Proposed(Arc::new(plan))
+                });
+            }
+            Message::Proposed(plan) => {
+                self.busy = false;
+                self.plan = Some(plan);
+            }
+            Message::Apply => {
+                let Some(plan) = self.plan.clone() else {
+                    return Task::none();
+                };
+                self.busy = true;
+                self.status = None;
+                let root = self.plan_root.clone();
+                return Task::future(async move {
+                    let Some(file) = AsyncFileDialog::new()
+                        .set_title("Save the undo journal")
+                        .set_file_name("rename_journal.jsonl")
+                        .add_filter("Journal", &["jsonl"])
+                        .save_file()
+                        .await
+                    else {
+                        return Message::Applied(None, Err("Renaming cancelled".to_string()));
+                    };
+
+                    let journal = file.path().to_path_buf();
+                    // Renaming a folder fails on Windows while the journal inside it is open
+                    if journal.starts_with(&root) {
+                        return Message::Applied(
+                            None,
+                            Err("Save the journal outside the scanned folder".to_string()),
+                        );
+                    }
+
+                    let result = tokio::task::spawn_blocking({
+                        let journal = journal.clone();
+                        move || rename::apply(&plan.operations, &journal)
+                    })
+                    .await
+                    .unwrap_or_else(|err| Err(format!("Renaming failed: {}", err)));
+                    Message::Applied(Some(journal), result)
+                });
+            }
+            Message::Applied(journal, result) => {
+                self.busy = false;
+                if result.is_ok() {
+                    self.plan = None;
+                    self.journal = journal;
+                }
+                self.status = Some(
+                    result
+                        .map(|message| format!("{}. Scan again to see the new lengths.", message)),
+                );
+            }
+            Message::UndoLast => {
+                if let Some(journal) = self.journal.clone() {
+                    self.busy = true;
+                    self.status = None;
+                    return Task::future(async move { Message::Undone(Some(undo(journal).await)) });
+                }
+            }
+            Message::UndoFromJournal => {
+                self.busy = true;
+                self.status = None;
+                return Task::future(async {
+                    let Some(file) = AsyncFileDialog::new()
+                        .set_title("Select the journal to undo")
+                        .add_filter("Journal", &["jsonl"])
+                        .pick_file()
+                        .await
+                    else {
+                        return Message::Undone(None);
+                    };
+                    Message::Undone(Some(undo(file.path().to_path_buf()).await))
+                });
+            }
+            Message::Undone(result) => {
+                self.busy = false;
+                if let Some(result) = result {
+                    if result.is_ok() {
+                        self.journal = None;
+                    }
+                    self.status = Some(result);
+                }
+            }
+        }
+        Task::none()
+    }
+
+    /// `can_propose` is false while there are no finished results.
+    pub fn view(&self, limit: usize, can_propose: bool) -> Element<'_, Message> {
+        let plan = self.plan.as_ref().map(|plan| {
+            let within = plan
+                .previews
+                .iter()
+                .filter(|preview| preview.new_length <= limit)
+                .count();
+
+            column![
+                text(format!(
+                    "{} changes, {} of {} paths are within the limit afterwards",
+                    plan.changes.len(),
+                    within,
+                    plan.previews.len()
+                ))
+                .size(18),
+                (!plan.problems.is_empty()).then(|| {
+                    scrollable(
+                        column(plan.problems.iter().map(|problem| {
+                            text(problem).color(Color::from_rgb8(255, 165, 0)).into()
+                        }))
+                        .spacing(5),
+                    )
+                    .height(Length::Fixed(120.0))
+                    .width(Length::Fill)
+                }),
+                scrollable(
+                    column(
+                        plan.changes
+                            .iter()
+                            .take(MAX_FINDINGS_SHOWN)
+                            .map(|change| text(change).into())
+                    )
+                    .spacing(5)
+                )
+                .height(Length::Fixed(200.0))
+                .width(Length::Fill),
+                row![
+                    text("Length").width(80),
+                    text("New").width(80),
+                    text("New Path").width(Length::Fill),
+                ]
+                .spacing(10),
+                scrollable(
+                    column(
+                        plan.previews
+                            .iter()
+                            .take(MAX_FINDINGS_SHOWN)
+                            .map(|preview| {
+                                let color = if preview.new_length <= limit {
+                                    Color::from_rgb8(0, 160, 0)
+                                } else {
+                                    Color::from_rgb8(255, 0, 0)
+                                };
+                                row![
+                                    text(preview.length).width(80),
+                                    text(preview.new_length).color(color).width(80),
+                                    text(&preview.new_path).width(Length::Fill),
+                                ]
+                                .spacing(10)
+                                .into()
+                            })
+                    )
+                    .spacing(5)
+                )
+                .height(Length::Fixed(300.0))
+                .width(Length::Fill),
+            ]
+            .spacing(10)
+        });
+
+        column![
+            row![
+                text("Longest Name:"),
+                text_input("40", &self.max_name_input)
+                    .on_input(Message::MaxName)
+                    .width(Length::Fixed(100.0)),
+                checkbox(self.options.abbreviate)
+                    .label("Abbreviate common words")
+                    .on_toggle(Message::Abbreviate),
+                checkbox(self.options.collapse)
+                    .label("Remove folders that only contain one folder")
+                    .on_toggle(Message::Collapse),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                button(text("Propose Renames"))
+                    .on_press_maybe((can_propose && !self.busy).then_some(Message::Propose)),
+                button(text("Apply Renames")).on_press_maybe(
+                    self.plan
+                        .as_ref()
+                        .is_some_and(|plan| !plan.operations.is_empty() && !self.busy)
+                        .then_some(Message::Apply)
+                ),
+                button(text("Undo Last Batch")).on_press_maybe(
+                    (self.journal.is_some() && !self.busy).then_some(Message::UndoLast)
+                ),
+                button(text("Undo from Journal..."))
+                    .on_press_maybe((!self.busy).then_some(Message::UndoFromJournal)),
+            ]
+            .spacing(10),
+            self.busy.then(|| text("Working...").size(16)),
+            self.status.as_ref().map(|status| match status {
+                Ok(message) => text(message).color(Color::from_rgb8(0, 160, 0)),
+                Err(error) => text(error).color(Color::from_rgb8(255, 0, 0)),
+            }),
+            plan,
+        ]
+        .spacing(10)
+        .into()
+    }
+}
+
+impl Default for RenameTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn undo(journal: PathBuf) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || rename::undo(&journal))
+        .await
+        .unwrap_or_else(|err| Err(format!("Undo failed: {}", err)))
+}
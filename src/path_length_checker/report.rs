@@ -0,0 +1,620 @@
+use std::{
+    fmt::Display,
+    io::{self, Write},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Serialize, Serializer, de::DeserializeOwned, ser::SerializeSeq};
+
+use super::{
+    OverLimit,
+    filter::{Filter, Pattern},
+    lint::{Finding, Rule},
+    measure::Measure,
+    scan::SkippedLink,
+    spool,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+    Html,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Csv, Format::Json, Format::Html];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Html => "html",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Csv => write!(f, "CSV"),
+            Format::Json => write!(f, "JSON"),
+            Format::Html => write!(f, "HTML Report"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Semicolon,
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    pub const ALL: [Delimiter; 3] = [Delimiter::Semicolon, Delimiter::Comma, Delimiter::Tab];
+
+    fn char(&self) -> char {
+        match self {
+            Delimiter::Semicolon => ';',
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+}
+
+impl Display for Delimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Delimiter::Semicolon => write!(f, "Semicolon"),
+            Delimiter::Comma => write!(f, "Comma"),
+            Delimiter::Tab => write!(f, "Tab"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CsvOptions {
+    pub delimiter: Delimiter,
+    /// Lets Excel detect UTF-8, so umlauts show up right
+    pub bom: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: Delimiter::Semicolon,
+            bom: true,
+        }
+    }
+}
+
+/// Write a CSV file as described in RFC 4180.
+///
+/// Fields are quoted when they contain the delimiter, a quote or a line break, and quotes
+/// inside them are doubled. Lines end with CRLF.
+pub fn csv<I, R>(header: &[&str], rows: I, options: CsvOptions) -> String
+where
+    I: IntoIterator<Item = R>,
+    R: IntoIterator<Item = String>,
+{
+    let mut out = Vec::new();
+    let mut writer = CsvWriter::new(&mut out, header, options).expect("writing to memory");
+    for row in rows {
+        writer.row(row).expect("writing to memory");
+    }
+    String::from_utf8(out).expect("CSV is built from strings")
+}
+
+/// Write the name findings of the given rules as CSV, one row per finding.
+pub fn findings_csv(
+    findings: &Findings,
+    rules: &[Rule],
+    options: CsvOptions,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let mut writer = CsvWriter::new(out, &["Rule", "Path", "Detail"], options)?;
+    findings.try_for_each(|finding| {
+        if !rules.contains(&finding.rule) {
+            return Ok(());
+        }
+        writer.row([
+            finding.rule.to_string(),
+            finding.path.clone(),
+            finding.detail.clone(),
+        ])
+    })
+}
+
+/// Writes CSV rows one at a time, see [`csv`].
+struct CsvWriter<W> {
+    out: W,
+    delimiter: char,
+}
+
+impl<W: Write> CsvWriter<W> {
+    fn new(mut out: W, header: &[&str], options: CsvOptions) -> io::Result<Self> {
+        if options.bom {
+            out.write_all("\u{FEFF}".as_bytes())?;
+        }
+        let mut writer = Self {
+            out,
+            delimiter: options.delimiter.char(),
+        };
+        writer.row(header.iter().map(|field| field.to_string()))?;
+        Ok(writer)
+    }
+
+    fn row(&mut self, fields: impl IntoIterator<Item = String>) -> io::Result<()> {
+        let mut line = String::new();
+        for (index, field) in fields.into_iter().enumerate() {
+            if index > 0 {
+                line.push(self.delimiter);
+            }
+            if field.contains([self.delimiter, '"', '\r', '\n']) {
+                line.push('"');
+                line.push_str(&field.replace('"', "\"\""));
+                line.push('"');
+            } else {
+                line.push_str(&field);
+            }
+        }
+        line.push_str("\r\n");
+        self.out.write_all(line.as_bytes())
+    }
+}
+
+/// The rows of a report, in memory or spooled to disk.
+#[derive(Debug, Clone)]
+pub enum Rows<T> {
+    Memory(Vec<T>),
+    Spool(spool::Reader<T>),
+}
+
+pub type Paths = Rows<OverLimit>;
+pub type Findings = Rows<Finding>;
+
+impl<T: DeserializeOwned> Rows<T> {
+    pub fn len(&self) -> usize {
+        match self {
+            Rows::Memory(rows) => rows.len(),
+            Rows::Spool(reader) => reader.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every row in the order they were found.
+    pub fn try_for_each(&self, f: impl FnMut(&T) -> io::Result<()>) -> io::Result<()> {
+        match self {
+            Rows::Memory(rows) => rows.iter().try_for_each(f),
+            Rows::Spool(reader) => reader.try_for_each(f),
+        }
+    }
+}
+
+/// Everything about a finished scan that goes into an export.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub root: String,
+    pub limit: usize,
+    pub measure: Measure,
+    pub timestamp: DateTime<Local>,
+    pub scanned: u64,
+    pub paths: Paths,
+    /// Links to folders that were not followed, only in the JSON and HTML exports
+    pub skipped_links: Vec<SkippedLink>,
+    pub errors: Vec<String>,
+    pub filter: Filter,
+}
+
+impl Report {
+    fn excess(&self, path: &OverLimit) -> u64 {
+        path.length.saturating_sub(self.limit as u64)
+    }
+
+    pub fn write(
+        &self,
+        format: Format,
+        options: CsvOptions,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        match format {
+            Format::Csv => self.csv(options, out),
+            Format::Json => self.json(out),
+            Format::Html => self.html(out),
+        }
+    }
+
+    fn csv(&self, options: CsvOptions, out: &mut dyn Write) -> io::Result<()> {
+        let mut writer = CsvWriter::new(
+            out,
+            &[
+                "Length",
+                "Over Limit",
+                "Mode",
+                "Type",
+                "Size",
+                "Modified",
+                "Depth",
+                "Longest Name",
+                "Longest Name Length",
+                "Path",
+                "Target Path",
+                "Archive",
+            ],
+            options,
+        )?;
+        self.paths.try_for_each(|path| {
+            writer.row([
+                path.length.to_string(),
+                self.excess(path).to_string(),
+                path.mode.to_string(),
+                path.kind.to_string(),
+                path.file_size
+                    .map(|size| size.to_string())
+                    .unwrap_or_default(),
+                format_time(path.modified),
+                path.depth.to_string(),
+                path.longest_name.clone(),
+                path.longest_name_length.to_string(),
+                path.path.clone(),
+                path.target_path.clone().unwrap_or_default(),
+                path.archive.clone().unwrap_or_default(),
+            ])
+        })
+    }
+
+    fn json(&self, out: &mut dyn Write) -> io::Result<()> {
+        #[derive(Serialize)]
+        struct Json<'a> {
+            root: &'a str,
+            limit: usize,
+            measure: String,
+            target: Option<&'a str>,
+            timestamp: String,
+            scanned: u64,
+            exclude: Vec<String>,
+            include: Vec<String>,
+            paths_over_limit: JsonPaths<'a>,
+            skipped_links: &'a [SkippedLink],
+            errors: &'a [String],
+        }
+
+        #[derive(Serialize)]
+        struct JsonPath<'a> {
+            length: u64,
+            over_limit: u64,
+            kind: String,
+            file_size: Option<u64>,
+            modified: Option<String>,
+            depth: usize,
+            longest_name: &'a str,
+            longest_name_length: usize,
+            path: &'a str,
+            target_path: Option<&'a str>,
+            /// Set for entries that only exist once the archive is extracted
+            archive: Option<&'a str>,
+        }
+
+        /// Serializes the paths one by one, so spooled paths are never all in memory.
+        struct JsonPaths<'a>(&'a Report);
+
+        impl Serialize for JsonPaths<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let report = self.0;
+                let mut seq = serializer.serialize_seq(Some(report.paths.len()))?;
+                let mut failed = None;
+                let read = report.paths.try_for_each(|path| {
+                    seq.serialize_element(&JsonPath {
+                        length: path.length,
+                        over_limit: report.excess(path),
+                        kind: path.kind.to_string(),
+                        file_size: path.file_size,
+                        modified: path.modified.map(|modified| modified.to_rfc3339()),
+                        depth: path.depth,
+                        longest_name: &path.longest_name,
+                        longest_name_length: path.longest_name_length,
+                        path: &path.path,
+                        target_path: path.target_path.as_deref(),
+                        archive: path.archive.as_deref(),
+                    })
+                    .map_err(|err| {
+                        failed = Some(err);
+                        io::Error::other("Failed to write JSON")
+                    })
+                });
+                if let Some(err) = failed {
+                    return Err(err);
+                }
+                read.map_err(serde::ser::Error::custom)?;
+                seq.end()
+            }
+        }
+
+        serde_json::to_writer_pretty(
+            &mut *out,
+            &Json {
+                root: &self.root,
+                limit: self.limit,
+                measure: self.measure.to_string(),
+                target: self.measure.target.as_deref(),
+                timestamp: self.timestamp.to_rfc3339(),
+                scanned: self.scanned,
+                exclude: patterns(&self.filter.exclude),
+                include: patterns(&self.filter.include),
+                paths_over_limit: JsonPaths(self),
+                skipped_links: &self.skipped_links,
+                errors: &self.errors,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// A single HTML file with inline styles, which can be sent to customers as is.
+    ///
+    /// Paths in memory are sorted by length, spooled ones are listed in the order they were found.
+    fn html(&self, out: &mut dyn Write) -> io::Result<()> {
+        let with_target = self.measure.target.is_some();
+
+        let mut longest = 0;
+        let mut total_excess = 0;
+        self.paths.try_for_each(|path| {
+            longest = longest.max(path.length);
+            total_excess += self.excess(path);
+            Ok(())
+        })?;
+        let count = self.paths.len();
+        let average_excess = if count == 0 {
+            0.0
+        } else {
+            total_excess as f64 / count as f64
+        };
+
+        write!(
+            out,
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Path Length Report - {root}</title>
+<style>
+body {{ font-family: Segoe UI, Arial, sans-serif; margin: 2em; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 2em; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }}
+th {{ background: #eee; }}
+td.number {{ text-align: right; white-space: nowrap; }}
+td.path {{ word-break: break-all; }}
+.error {{ color: #c00; }}
+</style>
+</head>
+<body>
+<h1>Path Length Report</h1>
+<table>
+<tr><th>Folder</th><td>{root}</td></tr>
+<tr><th>Limit</th><td>{limit} {measure}</td></tr>
+<tr><th>Scanned</th><td>{timestamp}</td></tr>
+<tr><th>Excluded</th><td>{exclude}</td></tr>
+<tr><th>Only checked</th><td>{include}</td></tr>
+<tr><th>Paths checked</th><td>{scanned}</td></tr>
+<tr><th>Paths over the limit</th><td>{count}</td></tr>
+<tr><th>Longest path</th><td>{longest}</td></tr>
+<tr><th>Average over the limit</th><td>{average_excess:.1}</td></tr>
+<tr><th>Links not followed</th><td>{skipped_links}</td></tr>
+<tr><th>Errors</th><td>{errors}</td></tr>
+</table>
+<h2>Paths over the limit</h2>
+<table>
+<tr><th>Length</th><th>Over Limit</th><th>Type</th><th>Size</th><th>Modified</th><th>Depth</th><th>Longest Name</th><th>Path</th>{target_header}</tr>
+"#,
+            root = escape(&self.root),
+            limit = self.limit,
+            measure = escape(&self.measure.to_string()),
+            timestamp = self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            exclude = escape(&patterns(&self.filter.exclude).join(", ")),
+            include = escape(&patterns(&self.filter.include).join(", ")),
+            scanned = self.scanned,
+            count = count,
+            longest = longest,
+            average_excess = average_excess,
+            skipped_links = self.skipped_links.len(),
+            errors = self.errors.len(),
+            target_header = if with_target {
+                "<th>Target Path</th>"
+            } else {
+                ""
+            },
+        )?;
+
+        let write_row = |path: &OverLimit| -> io::Result<()> {
+            write!(
+                out,
+                r#"<tr><td class="number">{}</td><td class="number">{}</td><td>{}</td><td class="number">{}</td><td>{}</td><td class="number">{}</td><td class="path">{} ({})</td><td class="path">{}{}</td>"#,
+                path.length,
+                self.excess(path),
+                path.kind,
+                path.file_size
+                    .map(|size| size.to_string())
+                    .unwrap_or_default(),
+                format_time(path.modified),
+                path.depth,
+                escape(&path.longest_name),
+                path.longest_name_length,
+                escape(&path.path),
+                path.archive
+                    .as_ref()
+                    .map(|archive| format!("<br><small>Extracted from {}</small>", escape(archive)))
+                    .unwrap_or_default(),
+            )?;
+            if with_target {
+                write!(
+                    out,
+                    r#"<td class="path">{}</td>"#,
+                    escape(path.target_path.as_deref().unwrap_or_default())
+                )?;
+            }
+            writeln!(out, "</tr>")
+        };
+        match &self.paths {
+            Paths::Memory(paths) => {
+                let mut sorted: Vec<&OverLimit> = paths.iter().collect();
+                sorted.sort_by_key(|path| std::cmp::Reverse(path.length));
+                sorted.into_iter().try_for_each(write_row)?;
+            }
+            Paths::Spool(reader) => reader.try_for_each(write_row)?,
+        }
+        writeln!(out, "</table>")?;
+
+        if !self.skipped_links.is_empty() {
+            writeln!(
+                out,
+                "<h2>Links not followed</h2>\n<table>\n<tr><th>Path</th><th>Reason</th></tr>"
+            )?;
+            for link in &self.skipped_links {
+                writeln!(
+                    out,
+                    r#"<tr><td class="path">{}</td><td>{}</td></tr>"#,
+                    escape(&link.path),
+                    escape(&link.reason)
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+
+        if !self.errors.is_empty() {
+            writeln!(out, "<h2>Errors</h2>\n<ul class=\"error\">")?;
+            for error in &self.errors {
+                writeln!(out, "<li>{}</li>", escape(error))?;
+            }
+            writeln!(out, "</ul>")?;
+        }
+
+        writeln!(out, "</body>\n</html>")
+    }
+}
+
+fn patterns(patterns: &[Pattern]) -> Vec<String> {
+    patterns.iter().map(Pattern::to_string).collect()
+}
+
+fn format_time(time: Option<DateTime<Local>>) -> String {
+    time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_length_checker::{EntryKind, measure::Mode};
+
+    fn report(path: &str) -> Report {
+        Report {
+            root: "C:\\Data".to_string(),
+            limit: 10,
+            measure: Measure::default(),
+            timestamp: Local::now(),
+            scanned: 3,
+            paths: Paths::Memory(vec![OverLimit {
+                path: path.to_string(),
+                length: 25,
+                mode: Mode::Utf16,
+                target_path: None,
+                kind: EntryKind::File,
+                file_size: Some(42),
+                modified: None,
+                depth: 2,
+                longest_name: "name".to_string(),
+                longest_name_length: 4,
+                archive: None,
+            }]),
+            skipped_links: vec![SkippedLink {
+                path: "C:\\Data\\link".to_string(),
+                reason: "Not followed".to_string(),
+            }],
+            errors: vec!["Access denied".to_string()],
+            filter: Filter::default(),
+        }
+    }
+
+    #[test]
+    fn csv_quotes_fields() {
+        let rows = [
+            ["say \"hi\"", "a;b"].map(String::from),
+            ["line\nbreak", "plain"].map(String::from),
+        ];
+        assert_eq!(
+            csv(&["A", "B"], rows.clone(), CsvOptions::default()).as_bytes(),
+            b"\xEF\xBB\xBFA;B\r\n\"say \"\"hi\"\"\";\"a;b\"\r\n\"line\nbreak\";plain\r\n"
+        );
+        assert_eq!(
+            csv(
+                &["A", "B"],
+                rows,
+                CsvOptions {
+                    delimiter: Delimiter::Comma,
+                    bom: false,
+                }
+            )
+            .as_bytes(),
+            b"A,B\r\n\"say \"\"hi\"\"\",a;b\r\n\"line\nbreak\",plain\r\n"
+        );
+    }
+
+    #[test]
+    fn json_has_everything() {
+        let mut out = Vec::new();
+        report("C:\\Data\\a\\b.txt")
+            .write(Format::Json, CsvOptions::default(), &mut out)
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(json["root"], "C:\\Data");
+        assert_eq!(json["limit"], 10);
+        assert_eq!(json["scanned"], 3);
+        let paths = json["paths_over_limit"].as_array().unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0]["path"], "C:\\Data\\a\\b.txt");
+        assert_eq!(paths[0]["length"], 25);
+        assert_eq!(paths[0]["over_limit"], 15);
+        assert_eq!(paths[0]["file_size"], 42);
+        assert_eq!(
+            json["skipped_links"],
+            serde_json::json!([{ "path": "C:\\Data\\link", "reason": "Not followed" }])
+        );
+        assert_eq!(json["errors"], serde_json::json!(["Access denied"]));
+    }
+
+    #[test]
+    fn html_escapes_paths() {
+        let mut out = Vec::new();
+        report("C:\\Data\\<b>&\"c\".txt")
+            .write(Format::Html, CsvOptions::default(), &mut out)
+            .unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.contains("C:\\Data\\&lt;b&gt;&amp;&quot;c&quot;.txt"));
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("<tr><th>Paths over the limit</th><td>1</td></tr>"));
+        assert!(
+            html.contains(r#"<tr><td class="path">C:\Data\link</td><td>Not followed</td></tr>"#)
+        );
+        assert!(html.contains("<li>Access denied</li>"));
+    }
+}
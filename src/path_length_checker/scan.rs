@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc,
+    task::{self, JoinSet},
+    time::{Instant, MissedTickBehavior, interval, interval_at},
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    EntryKind, OverLimit,
+    archive::{self, ArchivePolicy},
+    checkpoint::{Checkpoint, State},
+    filter::Filter,
+    lint::{self, Finding},
+    measure::Measure,
+};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What to do with symbolic links and junctions to folders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinkPolicy {
+    DontFollow,
+    /// Follow, but never enter a folder twice
+    Follow,
+    /// Follow links that point to a folder below the root
+    WithinRoot,
+}
+
+impl LinkPolicy {
+    pub const ALL: [LinkPolicy; 3] = [
+        LinkPolicy::DontFollow,
+        LinkPolicy::Follow,
+        LinkPolicy::WithinRoot,
+    ];
+}
+
+impl Display for LinkPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkPolicy::DontFollow => write!(f, "Don't follow links"),
+            LinkPolicy::Follow => write!(f, "Follow links"),
+            LinkPolicy::WithinRoot => write!(f, "Follow links within root"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub root: PathBuf,
+    pub limit: usize,
+    pub measure: Measure,
+    /// How many folders are read at the same time
+    pub parallelism: usize,
+    pub links: LinkPolicy,
+    pub archives: ArchivePolicy,
+    pub filter: Filter,
+}
+
+/// A link to a folder that was not followed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedLink {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Identifies a folder independent of the path it was reached through.
+#[cfg(unix)]
+pub type FolderId = (u64, u64);
+#[cfg(not(unix))]
+pub type FolderId = PathBuf;
+
+#[cfg(unix)]
+fn folder_id(path: &Path) -> std::io::Result<FolderId> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn folder_id(path: &Path) -> std::io::Result<FolderId> {
+    fs::canonicalize(path)
+}
+
+struct Walk {
+    settings: Settings,
+    /// Only filled when links are followed
+    visited: Mutex<HashSet<FolderId>>,
+    /// Canonical root for [`LinkPolicy::WithinRoot`]
+    canonical_root: Option<PathBuf>,
+}
+
+/// Everything found since the last update.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Update {
+    /// Total number of entries checked so far
+    pub scanned: u64,
+    pub over_limit: Vec<OverLimit>,
+    pub findings: Vec<Finding>,
+    pub skipped_links: Vec<SkippedLink>,
+    pub errors: Vec<String>,
+}
+
+impl Update {
+    /// Add the results of a later update.
+    pub fn extend(&mut self, other: Update) {
+        self.scanned = self.scanned.max(other.scanned);
+        self.over_limit.extend(other.over_limit);
+        self.findings.extend(other.findings);
+        self.skipped_links.extend(other.skipped_links);
+        self.errors.extend(other.errors);
+    }
+}
+
+/// What a worker is busy with, so it can be started again after an interruption.
+enum Job {
+    Folder(PathBuf),
+    Link(PathBuf),
+}
+
+/// Walk the tree below the root and send what was found every 100ms.
+///
+/// Up to `parallelism` folders are read at once, which hides the latency of network shares.
+/// Starts from `resume` if given. With a checkpoint, the progress is saved every 30 seconds
+/// and when the scan is interrupted, and the checkpoint is removed once the scan is done.
+/// The results found since the last update are sent before returning, also when cancelled.
+/// Returns when the tree is done, the token is cancelled or the receiver is dropped.
+pub async fn run(
+    settings: Settings,
+    resume: Option<State>,
+    mut checkpoint: Option<Checkpoint>,
+    token: CancellationToken,
+    updates: mpsc::Sender<Update>,
+) {
+    let resume = resume.unwrap_or_else(|| State {
+        folders: vec![settings.root.clone()],
+        ..Default::default()
+    });
+    let mut visited: HashSet<FolderId> = resume.visited.into_iter().collect();
+    if settings.links != LinkPolicy::DontFollow
+        && let Ok(id) = folder_id(&settings.root)
+    {
+        visited.insert(id);
+    }
+    let walk = Arc::new(Walk {
+        canonical_root: fs::canonicalize(&settings.root).ok(),
+        visited: Mutex::new(visited),
+        settings,
+    });
+    let mut folders = resume.folders;
+    // Links are followed after the real folders, so a folder is listed under its own path
+    let mut links = resume.links;
+    let mut workers = JoinSet::new();
+    let mut jobs = HashMap::new();
+    let mut batch = Update::default();
+    // Results since the last checkpoint
+    let mut unsaved = Update::default();
+    let mut scanned = resume.scanned;
+
+    let mut ticker = interval(UPDATE_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut checkpoint_ticker =
+        interval_at(Instant::now() + CHECKPOINT_INTERVAL, CHECKPOINT_INTERVAL);
+
+    loop {
+        while workers.len() < walk.settings.parallelism.max(1) {
+            let walk = walk.clone();
+            let token = token.clone();
+            if let Some(path) = folders.pop() {
+                let job = path.clone();
+                let worker = workers.spawn_blocking(move || read_directory(&path, &walk, &token));
+                jobs.insert(worker.id(), Job::Folder(job));
+            } else if workers.is_empty()
+                && let Some(link) = links.pop()
+            {
+                let job = link.clone();
+                let worker = workers.spawn_blocking(move || follow_link(&link, &walk, &token));
+                jobs.insert(worker.id(), Job::Link(job));
+            } else {
+                break;
+            }
+        }
+
+        if workers.is_empty() {
+            break;
+        }
+
+        let save = tokio::select! {
+            // Dropping the JoinSet stops the workers at their next entry
+            _ = token.cancelled() => true,
+            Some(result) = workers.join_next_with_id() => {
+                let found = match result {
+                    Ok((id, directory)) => {
+                        jobs.remove(&id);
+                        scanned += directory.scanned;
+                        folders.extend(directory.subdirectories);
+                        links.extend(directory.links);
+                        Update {
+                            scanned: 0,
+                            over_limit: directory.over_limit,
+                            findings: directory.findings,
+                            skipped_links: directory.skipped_links,
+                            errors: directory.errors,
+                        }
+                    }
+                    Err(err) => {
+                        jobs.remove(&err.id());
+                        Update {
+                            errors: vec![format!("Scan worker failed: {}", err)],
+                            ..Default::default()
+                        }
+                    }
+                };
+                if checkpoint.is_some() {
+                    unsaved.extend(found.clone());
+                }
+                batch.extend(found);
+                false
+            },
+            _ = ticker.tick() => {
+                batch.scanned = scanned;
+                updates.send(mem::take(&mut batch)).await.is_err()
+            }
+            _ = checkpoint_ticker.tick(), if checkpoint.is_some() => {
+                let state = walk.state(scanned, &folders, &links, &jobs);
+                save_checkpoint(&mut checkpoint, mem::take(&mut unsaved), state, &mut batch);
+                false
+            }
+        };
+
+        // Interrupted, everything not read yet is left for resuming
+        if save {
+            let state = walk.state(scanned, &folders, &links, &jobs);
+            save_checkpoint(&mut checkpoint, unsaved, state, &mut batch);
+            // Results found since the last tick, the receiver may be gone already
+            batch.scanned = scanned;
+            let _ = updates.send(batch).await;
+            return;
+        }
+    }
+
+    if let Some(checkpoint) = checkpoint
+        && let Err(err) = checkpoint.remove()
+    {
+        batch
+            .errors
+            .push(format!("Failed to remove checkpoint: {}", err));
+    }
+    batch.scanned = scanned;
+    let _ = updates.send(batch).await;
+}
+
+impl Walk {
+    fn state(
+        &self,
+        scanned: u64,
+        folders: &[PathBuf],
+        links: &[PathBuf],
+        jobs: &HashMap<task::Id, Job>,
+    ) -> State {
+        let mut state = State {
+            scanned,
+            folders: folders.to_vec(),
+            links: links.to_vec(),
+            visited: Vec::new(),
+        };
+        let mut visited = self.visited.lock().unwrap().clone();
+        for job in jobs.values() {
+            match job {
+                Job::Folder(path) => state.folders.push(path.clone()),
+                Job::Link(link) => {
+                    // The link may have been marked as scanned already, it is followed again
+                    if let Ok(id) = folder_id(link) {
+                        visited.remove(&id);
+                    }
+                    state.links.push(link.clone());
+                }
+            }
+        }
+        state.visited = visited.into_iter().collect();
+        state
+    }
+}
+
+fn save_checkpoint(
+    checkpoint: &mut Option<Checkpoint>,
+    results: Update,
+    state: State,
+    batch: &mut Update,
+) {
+    if let Some(saving) = checkpoint
+        && let Err(err) = saving.save(results, state)
+    {
+        batch.errors.push(format!(
+            "Failed to save checkpoint, scan can't be resumed: {}",
+            err
+        ));
+        *checkpoint = None;
+    }
+}
+
+#[derive(Default)]
+struct Directory {
+    subdirectories: Vec<PathBuf>,
+    /// Links to folders that are followed later
+    links: Vec<PathBuf>,
+    scanned: u64,
+    over_limit: Vec<OverLimit>,
+    findings: Vec<Finding>,
+    skipped_links: Vec<SkippedLink>,
+    errors: Vec<String>,
+}
+
+/// Check all entries of one folder. This blocks, so it runs on the blocking thread pool.
+fn read_directory(path: &Path, walk: &Walk, token: &CancellationToken) -> Directory {
+    let settings = &walk.settings;
+    let mut directory = Directory::default();
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            directory.errors.push(format!(
+                "Error reading directory {}: {}",
+                path.display(),
+                err
+            ));
+            return directory;
+        }
+    };
+
+    let measure = &settings.measure;
+    let mut names = Vec::new();
+
+    for entry in entries {
+        if token.is_cancelled() {
+            break;
+        }
+
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                directory.errors.push(format!(
+                    "Error reading directory {}: {}",
+                    path.display(),
+                    err
+                ));
+                continue;
+            }
+        };
+
+        let entry_path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let file_type = entry.file_type();
+        let is_dir = file_type.as_ref().is_ok_and(|file_type| file_type.is_dir());
+
+        let filter = &settings.filter;
+        let checked = if filter.is_empty() {
+            true
+        } else {
+            let relative = entry_path
+                .strip_prefix(&settings.root)
+                .unwrap_or(&entry_path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            if filter.excludes(&name, &relative, is_dir) {
+                // Excluded folders are not entered at all
+                continue;
+            }
+            // With include patterns, folders are only entered
+            filter.include.is_empty() || (!is_dir && filter.includes(&name, &relative))
+        };
+
+        if checked {
+            for (rule, detail) in lint::check_name(&name, measure.profile, path == settings.root) {
+                directory.findings.push(Finding {
+                    rule,
+                    path: entry_path.to_string_lossy().to_string(),
+                    detail,
+                });
+            }
+            let measured = measure.text(&entry_path, &settings.root);
+            if measure.mode.count(&measured) > settings.limit {
+                // Only read for hits, most entries are below the limit
+                let metadata = entry.metadata().ok();
+                directory.over_limit.push(over_limit(
+                    &entry_path,
+                    &settings.root,
+                    measure,
+                    &measured,
+                    metadata.as_ref(),
+                ));
+            }
+
+            let is_file = file_type
+                .as_ref()
+                .is_ok_and(|file_type| file_type.is_file());
+            if is_file && settings.archives.opens(&name) {
+                match archive::check(&entry_path, settings) {
+                    Ok(entries) => directory.over_limit.extend(entries),
+                    Err(err) => directory.errors.push(err),
+                }
+            }
+        }
+
+        match file_type {
+            Ok(file_type) if file_type.is_symlink() => match fs::metadata(&entry_path) {
+                // Links to files are measured like any other file
+                Ok(metadata) if !metadata.is_dir() => {}
+                Ok(_) if settings.links == LinkPolicy::DontFollow => {
+                    directory.skipped_links.push(SkippedLink {
+                        path: entry_path.to_string_lossy().to_string(),
+                        reason: "Not followed".to_string(),
+                    });
+                }
+                Ok(_) => directory.links.push(entry_path),
+                Err(_) if settings.links == LinkPolicy::DontFollow => {}
+                Err(err) => directory.skipped_links.push(SkippedLink {
+                    path: entry_path.to_string_lossy().to_string(),
+                    reason: format!("Broken link: {}", err),
+                }),
+            },
+            Ok(file_type) if file_type.is_dir() => {
+                if settings.links == LinkPolicy::DontFollow {
+                    directory.subdirectories.push(entry_path);
+                } else {
+                    match folder_id(&entry_path) {
+                        Ok(id) => {
+                            if walk.visited.lock().unwrap().insert(id) {
+                                directory.subdirectories.push(entry_path);
+                            } else {
+                                // Only possible below a followed link
+                                directory.skipped_links.push(SkippedLink {
+                                    path: entry_path.to_string_lossy().to_string(),
+                                    reason: "Already scanned through another link".to_string(),
+                                });
+                            }
+                        }
+                        Err(err) => directory.errors.push(format!(
+                            "Error reading metadata for {}: {}",
+                            entry_path.display(),
+                            err
+                        )),
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                directory.errors.push(format!(
+                    "Error reading metadata for {}: {}",
+                    entry_path.display(),
+                    err
+                ));
+            }
+        }
+
+        directory.scanned += 1;
+        // Folders collide whether or not the include patterns check them
+        names.push(name);
+    }
+
+    for (index, rule, detail) in lint::check_collisions(&names) {
+        directory.findings.push(Finding {
+            rule,
+            path: path.join(&names[index]).to_string_lossy().to_string(),
+            detail,
+        });
+    }
+
+    directory
+}
+
+/// Describe an entry over the limit. `measured` is the text the limit was checked against.
+pub fn over_limit(
+    path: &Path,
+    root: &Path,
+    measure: &Measure,
+    measured: &str,
+    metadata: Option<&fs::Metadata>,
+) -> OverLimit {
+    let kind = match metadata.map(fs::Metadata::file_type) {
+        Some(file_type) if file_type.is_symlink() => EntryKind::Link,
+        Some(file_type) if file_type.is_dir() => EntryKind::Folder,
+        _ => EntryKind::File,
+    };
+    let longest_name = measured
+        .split(['/', '\\'])
+        .max_by_key(|name| measure.mode.count(name))
+        .unwrap_or_default();
+
+    OverLimit {
+        path: path.to_string_lossy().to_string(),
+        length: measure.mode.count(measured) as u64,
+        mode: measure.mode,
+        target_path: measure.target.is_some().then(|| measured.to_string()),
+        kind,
+        file_size: metadata
+            .filter(|_| kind == EntryKind::File)
+            .map(fs::Metadata::len),
+        modified: metadata
+            .and_then(|metadata| metadata.modified().ok())
+            .map(DateTime::<Local>::from),
+        depth: path
+            .strip_prefix(root)
+            .map(|relative| relative.components().count())
+            .unwrap_or_default(),
+        longest_name: longest_name.to_string(),
+        longest_name_length: measure.mode.count(longest_name),
+        archive: None,
+    }
+}
+
+/// Scan the folder a link points to, unless it was scanned already or is outside the root.
+fn follow_link(link: &Path, walk: &Walk, token: &CancellationToken) -> Directory {
+    match check_link(link, walk) {
+        Ok(()) => read_directory(link, walk, token),
+        Err(reason) => Directory {
+            skipped_links: vec![SkippedLink {
+                path: link.to_string_lossy().to_string(),
+                reason,
+            }],
+            ..Default::default()
+        },
+    }
+}
+
+fn check_link(link: &Path, walk: &Walk) -> Result<(), String> {
+    let target = fs::canonicalize(link).map_err(|err| format!("Broken link: {}", err))?;
+
+    if walk.settings.links == LinkPolicy::WithinRoot
+        && !walk
+            .canonical_root
+            .as_ref()
+            .is_some_and(|root| target.starts_with(root))
+    {
+        return Err(format!("Points outside the root to {}", target.display()));
+    }
+
+    let id = folder_id(link).map_err(|err| format!("Broken link: {}", err))?;
+    if !walk.visited.lock().unwrap().insert(id) {
+        let is_loop = link
+            .parent()
+            .and_then(|parent| fs::canonicalize(parent).ok())
+            .is_some_and(|parent| parent.starts_with(&target));
+        return Err(if is_loop {
+            format!("Loop back to {}", target.display())
+        } else {
+            format!("Already scanned at {}", target.display())
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("toolbox-scan-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn settings(root: &Path, links: LinkPolicy) -> Settings {
+        Settings {
+            root: root.to_path_buf(),
+            // Everything is over the limit, so every entry shows up in the results
+            limit: 0,
+            measure: Measure::default(),
+            parallelism: 1,
+            links,
+            archives: ArchivePolicy::Ignore,
+            filter: Filter::default(),
+        }
+    }
+
+    async fn scan(settings: Settings) -> Update {
+        let (sender, mut receiver) = mpsc::channel(16);
+        let scan = tokio::spawn(run(settings, None, None, CancellationToken::new(), sender));
+        let mut results = Update::default();
+        while let Some(update) = receiver.recv().await {
+            results.extend(update);
+        }
+        scan.await.unwrap();
+        results
+    }
+
+    #[tokio::test]
+    async fn cancelling_sends_pending_results() {
+        let root = temp_root("cancel");
+        for folder in 0..100 {
+            let folder = root.join(format!("folder{}", folder));
+            fs::create_dir(&folder).unwrap();
+            for file in 0..50 {
+                fs::write(folder.join(format!("file{}", file)), "").unwrap();
+            }
+        }
+        let checkpoint_path = root.with_extension("jsonl");
+        let settings = settings(&root, LinkPolicy::DontFollow);
+        let checkpoint = Checkpoint::create(&checkpoint_path, &settings, Local::now()).unwrap();
+
+        let token = CancellationToken::new();
+        let (sender, mut receiver) = mpsc::channel(16);
+        let scan = tokio::spawn(run(settings, None, Some(checkpoint), token.clone(), sender));
+        // Stop in the middle, with results waiting for the next tick
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        token.cancel();
+        let mut received = Update::default();
+        while let Some(update) = receiver.recv().await {
+            received.extend(update);
+        }
+        scan.await.unwrap();
+        let resumed = crate::path_length_checker::checkpoint::load(&checkpoint_path, None).unwrap();
+        fs::remove_file(&checkpoint_path).unwrap();
+        let _ = fs::remove_file(checkpoint_path.with_extension("state.json"));
+        fs::remove_dir_all(&root).unwrap();
+
+        // What was saved for resuming has also been sent, nothing is lost in between
+        assert!(received.errors.is_empty(), "{:?}", received.errors);
+        assert_eq!(received.scanned, resumed.state.scanned);
+        assert_eq!(received.over_limit.len(), resumed.results.over_limit.len());
+        assert_eq!(received.over_limit.len() as u64, received.scanned);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn detects_links_back_to_an_ancestor() {
+        let root = temp_root("loop");
+        fs::create_dir_all(root.join("a").join("b")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("a").join("b").join("up")).unwrap();
+
+        let results = scan(settings(&root, LinkPolicy::Follow)).await;
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(results.skipped_links.len(), 1);
+        assert!(results.skipped_links[0].path.ends_with("up"));
+        assert!(
+            results.skipped_links[0].reason.starts_with("Loop back to"),
+            "{}",
+            results.skipped_links[0].reason
+        );
+        // a, b and the link itself
+        assert_eq!(results.scanned, 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn never_follows_links_when_told_not_to() {
+        let root = temp_root("dont-follow");
+        let outside = root.with_extension("outside");
+        fs::create_dir_all(outside.join("inner")).unwrap();
+        fs::write(outside.join("inner").join("file"), "").unwrap();
+        fs::create_dir(root.join("a")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("a").join("out")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("a").join("up")).unwrap();
+
+        let results = scan(settings(&root, LinkPolicy::DontFollow)).await;
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+
+        let mut skipped: Vec<_> = results
+            .skipped_links
+            .iter()
+            .map(|link| (link.path.rsplit('/').next().unwrap(), link.reason.as_str()))
+            .collect();
+        skipped.sort();
+        assert_eq!(skipped, [("out", "Not followed"), ("up", "Not followed")]);
+        // a and the two links, nothing behind them
+        assert_eq!(results.scanned, 3);
+        assert!(
+            results
+                .over_limit
+                .iter()
+                .all(|path| !path.path.contains("inner") && !path.path.contains("up/"))
+        );
+    }
+}
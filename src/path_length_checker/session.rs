@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::{OverLimit, lint::Finding, measure::Measure, scan::SkippedLink};
+
+/// Increased when a saved session can't be read by older versions anymore.
+pub const VERSION: u32 = 1;
+
+/// A complete scan result, which can be saved and loaded without scanning again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub root: String,
+    pub limit: usize,
+    pub measure: Measure,
+    pub timestamp: DateTime<Local>,
+    pub scanned: u64,
+    pub exclude: Vec<String>,
+    pub include: Vec<String>,
+    pub paths: Vec<OverLimit>,
+    pub findings: Vec<Finding>,
+    pub skipped_links: Vec<SkippedLink>,
+    pub errors: Vec<String>,
+}
+
+impl Session {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|err| format!("Failed to save session: {}", err))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let session: Self =
+            serde_json::from_str(json).map_err(|err| format!("Not a scan session: {}", err))?;
+        if session.version > VERSION {
+            return Err(format!(
+                "The session was saved by a newer version (format {})",
+                session.version
+            ));
+        }
+        Ok(session)
+    }
+
+    /// The path below the scanned folder, so sessions of a share mounted elsewhere still match.
+    fn key(&self, path: &OverLimit) -> String {
+        path.path
+            .strip_prefix(&self.root)
+            .unwrap_or(&path.path)
+            .trim_start_matches(['/', '\\'])
+            .replace('\\', "/")
+            // Windows doesn't care about case, renaming only the case fixes nothing
+            .to_lowercase()
+    }
+}
+
+/// A path over the limit in both sessions.
+#[derive(Debug, Clone)]
+pub struct Remaining {
+    pub before: u64,
+    pub path: OverLimit,
+}
+
+/// The difference between an earlier and a later scan of the same folder.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub before: DateTime<Local>,
+    pub after: DateTime<Local>,
+    /// Set when the sessions were checked against different limits or modes
+    pub warning: Option<String>,
+    /// Over the limit before, but not anymore
+    pub fixed: Vec<OverLimit>,
+    /// Only over the limit in the later session
+    pub new: Vec<OverLimit>,
+    pub remaining: Vec<Remaining>,
+}
+
+impl Comparison {
+    pub fn new(before: &Session, after: &Session) -> Self {
+        let mut earlier: HashMap<String, &OverLimit> = before
+            .paths
+            .iter()
+            .map(|path| (before.key(path), path))
+            .collect();
+
+        let mut new = Vec::new();
+        let mut remaining = Vec::new();
+        for path in &after.paths {
+            match earlier.remove(&after.key(path)) {
+                Some(previous) => remaining.push(Remaining {
+                    before: previous.length,
+                    path: path.clone(),
+                }),
+                None => new.push(path.clone()),
+            }
+        }
+        let mut fixed: Vec<OverLimit> = earlier.into_values().cloned().collect();
+        fixed.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let warning = if before.limit != after.limit || before.measure != after.measure {
+            Some(format!(
+                "The scans used different settings: {} ({}) before, {} ({}) after",
+                before.limit, before.measure, after.limit, after.measure
+            ))
+        } else {
+            None
+        };
+
+        Self {
+            before: before.timestamp,
+            after: after.timestamp,
+            warning,
+            fixed,
+            new,
+            remaining,
+        }
+    }
+}
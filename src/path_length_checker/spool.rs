@@ -0,0 +1,212 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{OverLimit, lint::Finding};
+
+/// Rows shown at once when paging through a spool.
+pub const PAGE_SIZE: usize = 500;
+
+static NEXT_SPOOL: AtomicUsize = AtomicUsize::new(0);
+
+/// Paths over the limit, or other results, kept in a temporary file instead of memory.
+///
+/// Every row is a JSON line. The offset of the first row of every page goes to a second file,
+/// so memory stays the same no matter how many rows are stored.
+#[derive(Debug)]
+pub struct Spool<T = OverLimit> {
+    // Closed before the files are removed
+    writer: BufWriter<File>,
+    /// The page offsets as little endian u64
+    index: BufWriter<File>,
+    file: Arc<SpoolFile>,
+    len: usize,
+    end: u64,
+    pages: usize,
+    rows: PhantomData<T>,
+}
+
+/// The paths over the limit and the name findings of one scan.
+#[derive(Debug)]
+pub struct Spools {
+    pub paths: Spool,
+    pub findings: Spool<Finding>,
+}
+
+impl Spools {
+    pub fn create() -> io::Result<Self> {
+        Ok(Self {
+            paths: Spool::create()?,
+            findings: Spool::create()?,
+        })
+    }
+}
+
+/// Removes the files once neither the spool nor a reader uses them anymore.
+#[derive(Debug)]
+struct SpoolFile {
+    path: PathBuf,
+    index: PathBuf,
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_file(&self.index);
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Spool<T> {
+    pub fn create() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "toolbox-path-length-{}-{}.jsonl",
+            std::process::id(),
+            NEXT_SPOOL.fetch_add(1, Ordering::Relaxed)
+        ));
+        let index = path.with_extension("pages");
+        Ok(Self {
+            writer: BufWriter::new(File::create(&path)?),
+            index: BufWriter::new(File::create(&index)?),
+            file: Arc::new(SpoolFile { path, index }),
+            len: 0,
+            end: 0,
+            pages: 0,
+            rows: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    pub fn extend(&mut self, rows: impl IntoIterator<Item = T>) -> io::Result<()> {
+        for row in rows {
+            if self.len.is_multiple_of(PAGE_SIZE) {
+                self.index.write_all(&self.end.to_le_bytes())?;
+                self.pages += 1;
+            }
+            let mut line = serde_json::to_vec(&row)?;
+            line.push(b'\n');
+            self.writer.write_all(&line)?;
+            self.end += line.len() as u64;
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    /// The rows of one page, in the order they were found.
+    pub fn page(&mut self, index: usize) -> io::Result<Vec<T>> {
+        if index >= self.pages {
+            return Ok(Vec::new());
+        }
+        self.writer.flush()?;
+        self.index.flush()?;
+
+        let mut offset = [0; 8];
+        let mut pages = File::open(&self.file.index)?;
+        pages.seek(SeekFrom::Start(index as u64 * 8))?;
+        pages.read_exact(&mut offset)?;
+        let start = u64::from_le_bytes(offset);
+
+        let mut file = File::open(&self.file.path)?;
+        file.seek(SeekFrom::Start(start))?;
+        BufReader::new(file.take(self.end - start))
+            .lines()
+            .take(PAGE_SIZE)
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+
+    /// Everything stored so far, for exports that run while the spool keeps growing.
+    pub fn reader(&mut self) -> io::Result<Reader<T>> {
+        self.writer.flush()?;
+        Ok(Reader {
+            file: self.file.clone(),
+            len: self.len,
+            end: self.end,
+            rows: PhantomData,
+        })
+    }
+}
+
+/// Reads the rows a spool had when the reader was made.
+#[derive(Debug, Clone)]
+pub struct Reader<T = OverLimit> {
+    file: Arc<SpoolFile>,
+    len: usize,
+    end: u64,
+    rows: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> Reader<T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn try_for_each(&self, mut f: impl FnMut(&T) -> io::Result<()>) -> io::Result<()> {
+        let file = File::open(&self.file.path)?;
+        for line in BufReader::new(file.take(self.end)).lines() {
+            f(&serde_json::from_str(&line?)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_length_checker::lint::Rule;
+
+    #[test]
+    fn spools_findings() {
+        let mut spool = Spool::<Finding>::create().unwrap();
+        spool
+            .extend((0..PAGE_SIZE + 1).map(|index| Finding {
+                rule: Rule::ReservedName,
+                path: format!("/share/CON-{}", index),
+                detail: String::new(),
+            }))
+            .unwrap();
+
+        assert_eq!(spool.pages(), 2);
+        assert_eq!(spool.page(0).unwrap()[0].path, "/share/CON-0");
+        assert_eq!(
+            spool.page(1).unwrap()[0].path,
+            format!("/share/CON-{}", PAGE_SIZE)
+        );
+        assert_eq!(spool.page(1).unwrap().len(), 1);
+        assert!(spool.page(2).unwrap().is_empty());
+
+        let reader = spool.reader().unwrap();
+        let mut read = 0;
+        reader
+            .try_for_each(|_| {
+                read += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(read, PAGE_SIZE + 1);
+
+        let files = [reader.file.path.clone(), reader.file.index.clone()];
+        drop(spool);
+        drop(reader);
+        assert!(files.iter().all(|file| !file.exists()));
+    }
+}
@@ -0,0 +1,318 @@
+use std::{cmp::Ordering, fmt::Display, path::Path};
+
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{
+        button, column, container, operation, row, scrollable, space, text, text::Wrapping,
+        text_input, tooltip,
+    },
+};
+
+use super::OverLimit;
+
+/// Every row has the same height, so only the visible rows need to be built.
+const ROW_HEIGHT: f32 = 32.0;
+const TABLE_HEIGHT: f32 = 400.0;
+const SCROLLABLE_ID: &str = "path-length-results";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Length,
+    Excess,
+    Kind,
+    FileSize,
+    Modified,
+    Depth,
+    LongestName,
+    Path,
+}
+
+impl Column {
+    /// Columns before the path, with their width
+    const FIXED: [(Column, f32); 7] = [
+        (Column::Length, 70.0),
+        (Column::Excess, 90.0),
+        (Column::Kind, 70.0),
+        (Column::FileSize, 90.0),
+        (Column::Modified, 150.0),
+        (Column::Depth, 70.0),
+        (Column::LongestName, 130.0),
+    ];
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Column::Length => write!(f, "Length"),
+            Column::Excess => write!(f, "Over Limit"),
+            Column::Kind => write!(f, "Type"),
+            Column::FileSize => write!(f, "Size"),
+            Column::Modified => write!(f, "Modified"),
+            Column::Depth => write!(f, "Depth"),
+            Column::LongestName => write!(f, "Longest Name"),
+            Column::Path => write!(f, "Path"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Sort(Column),
+    Filter(String),
+    Scrolled(scrollable::Viewport),
+    CopyPath(usize),
+    OpenFolder(usize),
+}
+
+/// The paths over the limit as a sortable and filterable table.
+pub struct Table {
+    sort: Column,
+    descending: bool,
+    filter: String,
+    /// Indices of the rows that match the filter, in display order
+    shown: Vec<usize>,
+    /// How many rows were already looked at
+    seen: usize,
+    offset: f32,
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self {
+            sort: Column::Length,
+            descending: true,
+            filter: String::new(),
+            shown: Vec::new(),
+            seen: 0,
+            offset: 0.0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.shown.clear();
+        self.seen = 0;
+        self.offset = 0.0;
+    }
+
+    /// Add the rows that were found since the last call.
+    pub fn extend(&mut self, rows: &[OverLimit]) {
+        let filter = self.filter.to_lowercase();
+        self.shown.extend(
+            (self.seen..rows.len())
+                .filter(|index| filter.is_empty() || matches(&rows[*index], &filter)),
+        );
+        self.seen = rows.len();
+        // The shown rows are already sorted, so this is close to linear
+        self.sort(rows);
+    }
+
+    pub fn update(&mut self, message: Message, rows: &[OverLimit]) -> Task<Message> {
+        match message {
+            Message::Sort(column) => {
+                if self.sort == column {
+                    self.descending = !self.descending;
+                } else {
+                    self.sort = column;
+                    self.descending = !matches!(column, Column::Path | Column::Kind);
+                }
+                self.sort(rows);
+                Task::none()
+            }
+            Message::Filter(filter) => {
+                self.filter = filter;
+                self.clear();
+                self.extend(rows);
+                operation::snap_to(SCROLLABLE_ID, scrollable::RelativeOffset::START)
+            }
+            Message::Scrolled(viewport) => {
+                self.offset = viewport.absolute_offset().y;
+                Task::none()
+            }
+            Message::CopyPath(index) => match rows.get(index) {
+                Some(row) => iced::clipboard::write(row.path.clone()),
+                None => Task::none(),
+            },
+            Message::OpenFolder(index) => {
+                // Entries of an archive don't exist yet, the archive is next to their folder
+                if let Some(parent) = rows
+                    .get(index)
+                    .and_then(|row| Path::new(row.archive.as_ref().unwrap_or(&row.path)).parent())
+                {
+                    let _ = open::that_in_background(parent);
+                }
+                Task::none()
+            }
+        }
+    }
+
+    fn sort(&mut self, rows: &[OverLimit]) {
+        let compare = |a: &usize, b: &usize| -> Ordering {
+            let (a, b) = (&rows[*a], &rows[*b]);
+            match self.sort {
+                // The excess is the length minus the same limit, so it sorts the same
+                Column::Length | Column::Excess => a.length.cmp(&b.length),
+                Column::Kind => a.kind.cmp(&b.kind),
+                Column::FileSize => a.file_size.cmp(&b.file_size),
+                Column::Modified => a.modified.cmp(&b.modified),
+                Column::Depth => a.depth.cmp(&b.depth),
+                Column::LongestName => a.longest_name_length.cmp(&b.longest_name_length),
+                Column::Path => a.path.cmp(&b.path),
+            }
+        };
+
+        if self.descending {
+            self.shown.sort_by(|a, b| compare(b, a));
+        } else {
+            self.shown.sort_by(compare);
+        }
+    }
+
+    /// With `paged`, the rows are one page of a spool and the table says that sorting and
+    /// filtering only apply to them.
+    pub fn view<'a>(
+        &'a self,
+        rows: &'a [OverLimit],
+        limit: usize,
+        paged: bool,
+    ) -> Element<'a, Message> {
+        let visible = (TABLE_HEIGHT / ROW_HEIGHT).ceil() as usize + 1;
+        let first =
+            ((self.offset / ROW_HEIGHT) as usize).min(self.shown.len().saturating_sub(visible));
+        let last = (first + visible).min(self.shown.len());
+
+        let header = |column: Column, width: Length| {
+            let arrow = match (self.sort == column, self.descending) {
+                (false, _) => "",
+                (true, true) => " ▼",
+                (true, false) => " ▲",
+            };
+            button(text(format!("{}{}", column, arrow)))
+                .on_press(Message::Sort(column))
+                .style(button::text)
+                .width(width)
+        };
+
+        let cells = self.shown[first..last].iter().map(|&index| {
+            let row = &rows[index];
+            let path = text(&row.path).wrapping(Wrapping::None).width(Length::Fill);
+            let path: Element<'a, Message> = match &row.archive {
+                Some(archive) => tooltip(
+                    path,
+                    container(text(format!("Only exists once {} is extracted", archive)))
+                        .padding(5)
+                        .style(container::dark),
+                    tooltip::Position::Top,
+                )
+                .into(),
+                None => path.into(),
+            };
+            container(
+                row![
+                    text(row.length).width(70),
+                    text(row.length.saturating_sub(limit as u64)).width(90),
+                    text(row.kind.to_string()).width(70),
+                    text(row.file_size.map(format_size).unwrap_or_default()).width(90),
+                    text(
+                        row.modified
+                            .map(|modified| modified.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_default()
+                    )
+                    .width(150),
+                    text(row.depth).width(70),
+                    tooltip(
+                        text(row.longest_name_length).width(130),
+                        container(text(&row.longest_name))
+                            .padding(5)
+                            .style(container::dark),
+                        tooltip::Position::Top,
+                    ),
+                    path,
+                    button(text("Copy"))
+                        .on_press(Message::CopyPath(index))
+                        .padding([2, 8]),
+                    button(text("Open Folder"))
+                        .on_press(Message::OpenFolder(index))
+                        .padding([2, 8]),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            )
+            .height(ROW_HEIGHT)
+            .clip(true)
+            .into()
+        });
+
+        column![
+            row![
+                text("Filter:"),
+                text_input(
+                    if paged {
+                        "Part of the path, on this page"
+                    } else {
+                        "Part of the path"
+                    },
+                    &self.filter
+                )
+                .on_input(Message::Filter),
+                text(if paged {
+                    format!("{} of {} on this page shown", self.shown.len(), rows.len())
+                } else {
+                    format!("{} of {} shown", self.shown.len(), rows.len())
+                }),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            paged.then(|| text("Sorting and filtering only apply to the rows of this page")),
+            row(Column::FIXED.into_iter().map(|(column, width)| header(
+                column,
+                Length::Fixed(width)
+            )
+            .into()))
+            .push(header(Column::Path, Length::Fill))
+            .push(space().width(190))
+            .spacing(10),
+            scrollable(
+                column![space().height(first as f32 * ROW_HEIGHT)]
+                    .extend(cells)
+                    .push(space().height((self.shown.len() - last) as f32 * ROW_HEIGHT))
+            )
+            .id(SCROLLABLE_ID)
+            .on_scroll(Message::Scrolled)
+            .height(TABLE_HEIGHT)
+            .width(Length::Fill),
+        ]
+        .spacing(10)
+        .into()
+    }
+}
+
+/// `filter` has to be lowercase already.
+fn matches(row: &OverLimit, filter: &str) -> bool {
+    row.path.to_lowercase().contains(filter)
+        || row
+            .archive
+            .as_ref()
+            .is_some_and(|archive| archive.to_lowercase().contains(filter))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Local};
+use notify::{
+    Event, EventKind, RecursiveMode, Watcher,
+    event::{ModifyKind, RenameMode},
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    OverLimit,
+    scan::{self, Settings},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Created,
+    Renamed,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Created => write!(f, "Created"),
+            Change::Renamed => write!(f, "Renamed"),
+        }
+    }
+}
+
+/// A path that went over the limit while watching.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub detected: DateTime<Local>,
+    pub change: Change,
+    pub path: OverLimit,
+}
+
+struct Watch {
+    settings: Settings,
+    /// Every path is reported once, a folder and its entries can be reported by several events
+    reported: Mutex<HashSet<PathBuf>>,
+}
+
+/// Watch the root for created and renamed entries and send those over the limit.
+///
+/// Folders that are renamed or moved in are checked with everything below them, since all
+/// their paths changed. Returns when the token is cancelled or the receiver is dropped.
+pub async fn run(
+    settings: Settings,
+    token: CancellationToken,
+    hits: mpsc::Sender<Result<Vec<Hit>, String>>,
+) {
+    let (events, mut receiver) = mpsc::unbounded_channel();
+    // Called on the thread of the watcher
+    let watcher = notify::recommended_watcher(move |event| {
+        let _ = events.send(event);
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            let _ = hits
+                .send(Err(format!("Failed to start watching: {}", err)))
+                .await;
+            return;
+        }
+    };
+    if let Err(err) = watcher.watch(&settings.root, RecursiveMode::Recursive) {
+        let _ = hits
+            .send(Err(format!(
+                "Failed to watch {}: {}",
+                settings.root.display(),
+                err
+            )))
+            .await;
+        return;
+    }
+
+    let watch = Arc::new(Watch {
+        settings,
+        reported: Mutex::new(HashSet::new()),
+    });
+
+    loop {
+        let event = tokio::select! {
+            _ = token.cancelled() => return,
+            event = receiver.recv() => match event {
+                Some(event) => event,
+                None => return,
+            },
+        };
+
+        let result = match event {
+            Ok(event) => {
+                let watch = watch.clone();
+                tokio::task::spawn_blocking(move || check_event(&event, &watch))
+                    .await
+                    .map_err(|err| format!("Watch worker failed: {}", err))
+            }
+            Err(err) => Err(format!("Watch error: {}", err)),
+        };
+        if result.as_ref().is_ok_and(Vec::is_empty) {
+            continue;
+        }
+        if hits.send(result).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn check_event(event: &Event, watch: &Watch) -> Vec<Hit> {
+    let (change, path) = match event.kind {
+        EventKind::Create(_) => (Change::Created, event.paths.first()),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            (Change::Renamed, event.paths.get(1))
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::To | RenameMode::Any)) => {
+            (Change::Renamed, event.paths.first())
+        }
+        _ => return Vec::new(),
+    };
+    let mut hits = Vec::new();
+    // Renames report the old name as well on some systems, it doesn't exist anymore
+    if let Some(path) = path
+        && let Ok(metadata) = fs::symlink_metadata(path)
+        && !excluded_folder(path, &watch.settings)
+    {
+        check(path, &metadata, change, Local::now(), watch, &mut hits);
+    }
+    hits
+}
+
+/// Check an entry and, for a folder, everything below it.
+fn check(
+    path: &Path,
+    metadata: &fs::Metadata,
+    change: Change,
+    detected: DateTime<Local>,
+    watch: &Watch,
+    hits: &mut Vec<Hit>,
+) {
+    let settings = &watch.settings;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let relative = relative(path, &settings.root);
+    let is_dir = metadata.is_dir();
+    if settings.filter.excludes(&name, &relative, is_dir) {
+        return;
+    }
+
+    let checked = settings.filter.include.is_empty()
+        || (!is_dir && settings.filter.includes(&name, &relative));
+    if checked {
+        let measured = settings.measure.text(path, &settings.root);
+        if settings.measure.mode.count(&measured) > settings.limit
+            && watch.reported.lock().unwrap().insert(path.to_path_buf())
+        {
+            hits.push(Hit {
+                detected,
+                change,
+                path: scan::over_limit(
+                    path,
+                    &settings.root,
+                    &settings.measure,
+                    &measured,
+                    Some(metadata),
+                ),
+            });
+        }
+    }
+
+    // Links are never followed while watching, a link to a folder above would never end
+    if is_dir
+        && !metadata.file_type().is_symlink()
+        && let Ok(entries) = fs::read_dir(path)
+    {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Ok(metadata) = fs::symlink_metadata(&path) {
+                check(&path, &metadata, change, detected, watch, hits);
+            }
+        }
+    }
+}
+
+/// Whether a folder above the path, below the root, is excluded.
+fn excluded_folder(path: &Path, settings: &Settings) -> bool {
+    path.ancestors()
+        .skip(1)
+        .take_while(|ancestor| *ancestor != settings.root)
+        .any(|ancestor| {
+            let name = ancestor
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            settings
+                .filter
+                .excludes(&name, &relative(ancestor, &settings.root), true)
+        })
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_length_checker::{
+        archive::ArchivePolicy, filter::Filter, measure::Measure, scan::LinkPolicy,
+    };
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_links_to_folders_above() {
+        let root = std::env::temp_dir().join(format!("toolbox-watch-{}", std::process::id()));
+        let moved = root.join("moved");
+        fs::create_dir_all(moved.join("inner")).unwrap();
+        std::os::unix::fs::symlink(&root, moved.join("inner").join("up")).unwrap();
+
+        let watch = Watch {
+            settings: Settings {
+                root: root.clone(),
+                limit: 0,
+                measure: Measure::default(),
+                parallelism: 1,
+                links: LinkPolicy::DontFollow,
+                archives: ArchivePolicy::Ignore,
+                filter: Filter::default(),
+            },
+            reported: Mutex::new(HashSet::new()),
+        };
+        let mut hits = Vec::new();
+        let metadata = fs::symlink_metadata(&moved).unwrap();
+        check(
+            &moved,
+            &metadata,
+            Change::Renamed,
+            Local::now(),
+            &watch,
+            &mut hits,
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        // The link itself is reported, nothing behind it
+        assert_eq!(hits.len(), 3);
+    }
+}